@@ -0,0 +1,193 @@
+//! Compares time and peak heap use of parsing a large synthetic plan through
+//! the DOM (`Element::parse` then `TestClass::root`) and through
+//! `TestClass::from_reader`.
+//!
+//! The samplers sit directly in the thread group, in a Simple Controller,
+//! whose subtree is streamed too, and in a Loop Controller, which the model
+//! keeps as `Unknown` with its whole `<hashTree>` read into an `Element`.
+//!
+//! `cargo bench --bench parse`, with `JMX_BENCH_SAMPLERS` setting the number
+//! of samplers (default 20000).
+
+use jmeter_to_goose::xmltree::Element;
+use jmeter_to_goose::TestClass;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs::{read_to_string, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Tracks live and peak heap bytes.
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<jmeterTestPlan version="1.2" properties="5.0" jmeter="5.5">
+  <hashTree>
+    <TestPlan guiclass="TestPlanGui" testclass="TestPlan" testname="Large" enabled="true">
+      <elementProp name="TestPlan.user_defined_variables" elementType="Arguments" guiclass="ArgumentsPanel" testclass="Arguments" testname="User Defined Variables" enabled="true">
+        <collectionProp name="Arguments.arguments"/>
+      </elementProp>
+    </TestPlan>
+    <hashTree>
+      <ThreadGroup guiclass="ThreadGroupGui" testclass="ThreadGroup" testname="Users" enabled="true">
+        <stringProp name="ThreadGroup.on_sample_error">continue</stringProp>
+        <elementProp name="ThreadGroup.main_controller" elementType="LoopController" guiclass="LoopControlPanel" testclass="LoopController" testname="Loop Controller" enabled="true">
+          <stringProp name="LoopController.loops">1</stringProp>
+        </elementProp>
+        <stringProp name="ThreadGroup.num_threads">10</stringProp>
+        <stringProp name="ThreadGroup.ramp_time">1</stringProp>
+        <boolProp name="ThreadGroup.scheduler">false</boolProp>
+        <stringProp name="ThreadGroup.duration"></stringProp>
+        <stringProp name="ThreadGroup.delay"></stringProp>
+      </ThreadGroup>
+      <hashTree>
+"#;
+
+const FOOTER: &str = "      </hashTree>
+    </hashTree>
+  </hashTree>
+</jmeterTestPlan>
+";
+
+fn sampler(i: usize) -> String {
+    format!(
+        r#"        <HTTPSamplerProxy guiclass="HttpTestSampleGui" testclass="HTTPSamplerProxy" testname="Request {i}" enabled="true">
+          <elementProp name="HTTPsampler.Arguments" elementType="Arguments" guiclass="HTTPArgumentsPanel" testclass="Arguments" testname="User Defined Variables" enabled="true">
+            <collectionProp name="Arguments.arguments">
+              <elementProp name="id" elementType="HTTPArgument">
+                <boolProp name="HTTPArgument.always_encode">false</boolProp>
+                <stringProp name="Argument.value">{i}</stringProp>
+                <stringProp name="Argument.metadata">=</stringProp>
+                <boolProp name="HTTPArgument.use_equals">true</boolProp>
+                <stringProp name="Argument.name">id</stringProp>
+              </elementProp>
+            </collectionProp>
+          </elementProp>
+          <stringProp name="HTTPSampler.domain">example.com</stringProp>
+          <stringProp name="HTTPSampler.port"></stringProp>
+          <stringProp name="HTTPSampler.protocol">https</stringProp>
+          <stringProp name="HTTPSampler.contentEncoding"></stringProp>
+          <stringProp name="HTTPSampler.path">/items</stringProp>
+          <stringProp name="HTTPSampler.method">GET</stringProp>
+          <boolProp name="HTTPSampler.follow_redirects">true</boolProp>
+          <boolProp name="HTTPSampler.auto_redirects">false</boolProp>
+          <boolProp name="HTTPSampler.use_keepalive">true</boolProp>
+          <boolProp name="HTTPSampler.DO_MULTIPART_POST">false</boolProp>
+        </HTTPSamplerProxy>
+        <hashTree>
+          <ResponseAssertion guiclass="AssertionGui" testclass="ResponseAssertion" testname="Status {i}" enabled="true">
+            <collectionProp name="Asserion.test_strings">
+              <stringProp name="1629">2\d\d</stringProp>
+            </collectionProp>
+            <stringProp name="Assertion.custom_message"></stringProp>
+            <stringProp name="Assertion.test_field">Assertion.response_code</stringProp>
+            <boolProp name="Assertion.assume_success">false</boolProp>
+            <intProp name="Assertion.test_type">1</intProp>
+          </ResponseAssertion>
+          <hashTree/>
+        </hashTree>
+"#
+    )
+}
+
+/// The start of the controller around the samplers, up to its `<hashTree>`
+const SIMPLE: &str = r#"        <GenericController guiclass="LogicControllerGui" testclass="GenericController" testname="Items" enabled="true"/>
+        <hashTree>
+"#;
+
+const LOOP: &str = r#"        <LoopController guiclass="LoopControlPanel" testclass="LoopController" testname="Items" enabled="true">
+          <boolProp name="LoopController.continue_forever">true</boolProp>
+          <stringProp name="LoopController.loops">2</stringProp>
+        </LoopController>
+        <hashTree>
+"#;
+
+fn write_plan(path: &Path, samplers: usize, controller: Option<&str>) -> std::io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    w.write_all(HEADER.as_bytes())?;
+    if let Some(controller) = controller {
+        w.write_all(controller.as_bytes())?;
+    }
+    for i in 0..samplers {
+        w.write_all(sampler(i).as_bytes())?;
+    }
+    if controller.is_some() {
+        w.write_all(b"        </hashTree>\n")?;
+    }
+    w.write_all(FOOTER.as_bytes())?;
+    w.flush()
+}
+
+/// Runs `parse`, printing its time and the peak heap use above what was
+/// live before it.
+fn measure(label: &str, parse: impl FnOnce() -> TestClass) -> TestClass {
+    let before = LIVE.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let start = Instant::now();
+    let plan = parse();
+    let elapsed = start.elapsed();
+    let peak = PEAK.load(Ordering::Relaxed) - before;
+    println!(
+        "{label:>14}: {:>8.1} ms, peak {:>8.1} MiB",
+        elapsed.as_secs_f64() * 1e3,
+        peak as f64 / (1024.0 * 1024.0)
+    );
+    plan
+}
+
+fn main() {
+    let samplers = std::env::var("JMX_BENCH_SAMPLERS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(20_000);
+    for (layout, controller) in [
+        ("flat", None),
+        ("simple", Some(SIMPLE)),
+        ("loop", Some(LOOP)),
+    ] {
+        let path =
+            std::env::temp_dir().join(format!("jmeter-to-goose-bench-{layout}-{samplers}.jmx"));
+        write_plan(&path, samplers, controller).unwrap();
+        let size = std::fs::metadata(&path).unwrap().len();
+        println!(
+            "{samplers} samplers {layout}, {:.1} MiB of .jmx",
+            size as f64 / (1024.0 * 1024.0)
+        );
+
+        let dom = measure(&format!("{layout} dom"), || {
+            let xml = read_to_string(&path).unwrap();
+            TestClass::root(&Element::parse(xml.as_bytes()).unwrap()).unwrap()
+        });
+        drop(dom);
+        let streamed = measure(&format!("{layout} stream"), || {
+            TestClass::from_reader(BufReader::new(File::open(&path).unwrap())).unwrap()
+        });
+        let group = &streamed.children()[0];
+        match layout {
+            "flat" => assert_eq!(group.children().len(), samplers),
+            "simple" => assert_eq!(group.children()[0].children().len(), samplers),
+            _ => assert!(matches!(group.children()[0], TestClass::Unknown(..))),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
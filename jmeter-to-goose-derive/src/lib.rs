@@ -0,0 +1,180 @@
+//! `#[derive(JmxElement)]` for `jmeter-to-goose`.
+//!
+//! Generates `FromJmx`, and through it a fallible `parse`, plus `Serializer` for a
+//! struct whose fields map one-to-one onto properties of a JMeter element:
+//!
+//! ```ignore
+//! use jmeter_to_goose::JmxElement;
+//!
+//! #[derive(JmxElement)]
+//! #[jmx(tag = "ConstantTimer", gui = "ConstantTimerGui")]
+//! pub struct ConstantTimer {
+//!     #[jmx(test_name)]
+//!     pub test_name: String,
+//!     #[jmx(enabled)]
+//!     pub enabled: bool,
+//!     #[jmx(prop = "ConstantTimer.delay", default = "300")]
+//!     pub delay: i64,
+//! }
+//! ```
+//!
+//! Field types convert through `jmeter_to_goose::FromProperty` and
+//! `ToProperty`; a missing property takes `default` when given and JMeter's
+//! own default otherwise. `gui` is the `guiclass` written back and defaults
+//! to `TestBeanGUI`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+enum Source {
+    TestName,
+    Enabled,
+    Prop(LitStr, Option<LitStr>),
+}
+
+#[proc_macro_derive(JmxElement, attributes(jmx))]
+pub fn derive_jmx_element(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let mut tag = None;
+    let mut gui = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("jmx")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tag") {
+                tag = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else if meta.path.is_ident("gui") {
+                gui = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `tag = \"...\"` or `gui = \"...\"`"))
+            }
+        })?;
+    }
+    let gui = gui.unwrap_or_else(|| LitStr::new("TestBeanGUI", ident.span()));
+    let tag = tag.ok_or_else(|| {
+        syn::Error::new_spanned(ident, "missing #[jmx(tag = \"...\")] on the struct")
+    })?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "JmxElement needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "JmxElement can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut inits = vec![];
+    let mut test_name = quote! { "" };
+    let mut enabled = quote! { true };
+    let mut props = vec![];
+    for field in fields {
+        let name = field.ident.as_ref().unwrap();
+        let mut source = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("jmx")) {
+            let mut prop = None;
+            let mut default = None;
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("test_name") {
+                    source = Some(Source::TestName);
+                } else if meta.path.is_ident("enabled") {
+                    source = Some(Source::Enabled);
+                } else if meta.path.is_ident("prop") {
+                    prop = Some(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("default") {
+                    default = Some(meta.value()?.parse::<LitStr>()?);
+                } else {
+                    return Err(meta.error(
+                        "expected `test_name`, `enabled`, `prop = \"...\"` or `default = \"...\"`",
+                    ));
+                }
+                Ok(())
+            })?;
+            if let Some(prop) = prop {
+                source = Some(Source::Prop(prop, default));
+            } else if let Some(default) = default {
+                return Err(syn::Error::new_spanned(
+                    default,
+                    "`default` only applies together with `prop`",
+                ));
+            }
+        }
+        let init = match source {
+            Some(Source::TestName) => {
+                test_name = quote! { &self.#name };
+                quote! { e.test_name.clone() }
+            }
+            Some(Source::Enabled) => {
+                enabled = quote! { self.#name };
+                quote! { e.enabled }
+            }
+            Some(Source::Prop(prop, default)) => {
+                props.push(quote! {
+                    ::jmeter_to_goose::Serializer::to_element(
+                        &::jmeter_to_goose::ToProperty::to_property(&self.#name, #prop),
+                    )
+                });
+                let default = match default {
+                    Some(default) => quote! { ::core::option::Option::Some(#default) },
+                    None => quote! { ::core::option::Option::None },
+                };
+                let context = format!("{ident}.{name}");
+                quote! {
+                    ::jmeter_to_goose::anyhow::Context::with_context(
+                        ::jmeter_to_goose::FromProperty::from_property(e, #prop, #default),
+                        || #context,
+                    )?
+                }
+            }
+            None => {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "missing #[jmx(test_name)], #[jmx(enabled)] or #[jmx(prop = \"...\")]",
+                ))
+            }
+        };
+        inits.push(quote! { #name: #init });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::jmeter_to_goose::FromJmx for #ident #ty_generics #where_clause {
+            fn from_jmx(e: &::jmeter_to_goose::JmxElement) -> ::jmeter_to_goose::anyhow::Result<Self> {
+                e.expect_tag(#tag)?;
+                ::core::result::Result::Ok(Self {
+                    #(#inits,)*
+                })
+            }
+        }
+
+        impl #impl_generics ::jmeter_to_goose::Serializer for #ident #ty_generics #where_clause {
+            fn to_element(&self) -> ::jmeter_to_goose::xmltree::Element {
+                ::jmeter_to_goose::test_element(
+                    #tag,
+                    #gui,
+                    #test_name,
+                    #enabled,
+                    ::std::vec![#(#props),*],
+                )
+            }
+        }
+    })
+}
@@ -0,0 +1,249 @@
+use crate::config_element::string_collection;
+use crate::{
+    test_element, FromJmx, GooseCodegen, IntProp, JmxElement, Property, PropertyBag, Serializer,
+    ToProperty,
+};
+use anyhow::anyhow;
+use xmltree::Element;
+
+/// What a [`ResponseAssertion`] tests, `Assertion.test_field`
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AssertionField {
+    ResponseData,
+    ResponseDocument,
+    ResponseCode,
+    ResponseMessage,
+    ResponseHeaders,
+    RequestHeaders,
+    RequestData,
+    Url,
+}
+
+const FIELDS: [(AssertionField, &str); 8] = [
+    (AssertionField::ResponseData, "Assertion.response_data"),
+    (
+        AssertionField::ResponseDocument,
+        "Assertion.response_data_as_document",
+    ),
+    (AssertionField::ResponseCode, "Assertion.response_code"),
+    (
+        AssertionField::ResponseMessage,
+        "Assertion.response_message",
+    ),
+    (
+        AssertionField::ResponseHeaders,
+        "Assertion.response_headers",
+    ),
+    (AssertionField::RequestHeaders, "Assertion.request_headers"),
+    (AssertionField::RequestData, "Assertion.request_data"),
+    (AssertionField::Url, "Assertion.sample_label"),
+];
+
+/// How patterns are matched, the low bits of `Assertion.test_type`
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MatchRule {
+    /// The whole field matches the regex
+    Matches,
+    /// The regex matches somewhere in the field
+    Contains,
+    Equals,
+    Substring,
+}
+
+const MATCH: i32 = 1;
+const CONTAINS: i32 = 1 << 1;
+const NOT: i32 = 1 << 2;
+const EQUALS: i32 = 1 << 3;
+const SUBSTRING: i32 = 1 << 4;
+const OR: i32 = 1 << 5;
+
+/// Response Assertion
+/// `<ResponseAssertion>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResponseAssertion {
+    pub test_name: String,
+    pub enabled: bool,
+    pub field: AssertionField,
+    pub rule: MatchRule,
+    /// Passes when the patterns don't match
+    pub not: bool,
+    /// Passes when any pattern matches rather than all of them
+    pub or: bool,
+    pub patterns: Vec<String>,
+    /// Ignore the response status, like "Ignore Status"
+    pub assume_success: bool,
+    pub custom_message: String,
+}
+
+impl FromJmx for ResponseAssertion {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("ResponseAssertion")?;
+        let test_field = e.get_str("Assertion.test_field")?;
+        let field = FIELDS
+            .iter()
+            .find(|(_, name)| *name == test_field)
+            .map(|(field, _)| *field)
+            .ok_or_else(|| anyhow!("Assertion.test_field: unknown field {test_field:?}"))?;
+        let test_type = i32::try_from(e.get_i64("Assertion.test_type")?)?;
+        let rule = if test_type & MATCH != 0 {
+            MatchRule::Matches
+        } else if test_type & EQUALS != 0 {
+            MatchRule::Equals
+        } else if test_type & SUBSTRING != 0 {
+            MatchRule::Substring
+        } else {
+            MatchRule::Contains
+        };
+        // JMeter itself misspells the collection's name
+        let patterns = e
+            .get_collection("Asserion.test_strings")?
+            .iter()
+            .map(|p| {
+                p.as_text()
+                    .ok_or_else(|| anyhow!("Asserion.test_strings: expected strings, found {p:?}"))
+            })
+            .collect::<anyhow::Result<Vec<String>>>()?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            field,
+            rule,
+            not: test_type & NOT != 0,
+            or: test_type & OR != 0,
+            patterns,
+            assume_success: e.get_bool("Assertion.assume_success")?,
+            custom_message: e.get_str("Assertion.custom_message")?,
+        })
+    }
+}
+
+impl Serializer for ResponseAssertion {
+    fn to_element(&self) -> Element {
+        let rule = match self.rule {
+            MatchRule::Matches => MATCH,
+            MatchRule::Contains => CONTAINS,
+            MatchRule::Equals => EQUALS,
+            MatchRule::Substring => SUBSTRING,
+        };
+        let test_type = rule | if self.not { NOT } else { 0 } | if self.or { OR } else { 0 };
+        let field = FIELDS
+            .iter()
+            .find(|(field, _)| *field == self.field)
+            .map(|(_, name)| name.to_string())
+            .unwrap();
+        test_element(
+            "ResponseAssertion",
+            "AssertionGui",
+            &self.test_name,
+            self.enabled,
+            [
+                string_collection("Asserion.test_strings", &self.patterns),
+                self.custom_message.to_property("Assertion.custom_message"),
+                field.to_property("Assertion.test_field"),
+                self.assume_success.to_property("Assertion.assume_success"),
+                Property::Int(IntProp {
+                    name: String::from("Assertion.test_type"),
+                    value: test_type,
+                }),
+            ]
+            .iter()
+            .map(|p| p.to_element())
+            .collect(),
+        )
+    }
+}
+
+impl GooseCodegen for ResponseAssertion {
+    /// A block evaluating to the failure message, `None` when the assertion
+    /// passes. Besides the post-processor inputs it reads the sampled `url`.
+    fn to_goose(&self) -> String {
+        let subject = match self.field {
+            AssertionField::ResponseData | AssertionField::ResponseDocument => "body.to_string()",
+            AssertionField::ResponseCode => "status.to_string()",
+            AssertionField::ResponseMessage => {
+                "reqwest::StatusCode::from_u16(status).ok().and_then(|s| s.canonical_reason()).unwrap_or_default().to_string()"
+            }
+            AssertionField::ResponseHeaders => "headers.to_string()",
+            AssertionField::Url => "url.to_string()",
+            // the request isn't kept around after sending it
+            AssertionField::RequestHeaders | AssertionField::RequestData => "String::new()",
+        };
+        let test = match self.rule {
+            MatchRule::Matches => {
+                "regex::Regex::new(&format!(\"^(?:{pattern})$\")).map_or(false, |r| r.is_match(&subject))"
+            }
+            MatchRule::Contains => {
+                "regex::Regex::new(&pattern).map_or(false, |r| r.is_match(&subject))"
+            }
+            MatchRule::Equals => "subject == pattern",
+            MatchRule::Substring => "subject.contains(pattern.as_str())",
+        };
+        let combine = if self.or { "any" } else { "all" };
+        let failed = if self.not { "passed" } else { "!passed" };
+        let message = if self.custom_message.is_empty() {
+            format!("{} failed", self.test_name)
+        } else {
+            self.custom_message.clone()
+        };
+        format!(
+            r#"{{
+    let subject: String = {subject};
+    let patterns: [String; {}] = [{}];
+    let passed = patterns.iter().{combine}(|pattern| {{
+        let pattern = substitute(pattern, vars);
+        {test}
+    }});
+    if {failed} {{
+        Some({message:?}.to_string())
+    }} else {{
+        None
+    }}
+}}"#,
+            self.patterns.len(),
+            self.patterns
+                .iter()
+                .map(|p| format!("{p:?}.to_string()"))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{AssertionField, FromJmx, MatchRule, ResponseAssertion, Serializer};
+    use xmltree::Element;
+
+    #[test]
+    fn check_parse_response_assertion() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/assertion/response_assertion.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let assertion = ResponseAssertion::parse(&xml).unwrap();
+        assert_eq!(
+            ResponseAssertion::parse(&assertion.to_element()).unwrap(),
+            assertion
+        );
+        assert_eq!(
+            assertion,
+            ResponseAssertion {
+                test_name: String::from("Status is 2xx"),
+                enabled: true,
+                field: AssertionField::ResponseCode,
+                rule: MatchRule::Matches,
+                not: false,
+                or: false,
+                patterns: vec![String::from("2\\d\\d")],
+                assume_success: true,
+                custom_message: String::from("unexpected status"),
+            }
+        );
+    }
+}
@@ -1,8 +1,66 @@
-use crate::Deserializer;
-use xmltree::Element;
+use crate::{CollectionProp, Deserializer, Serializer};
+use std::str::FromStr;
+use xmltree::{Element, XMLNode};
+
+fn text(e: &Element) -> String {
+    text_str(e).to_string()
+}
+
+fn text_str(e: &Element) -> &str {
+    e.children
+        .first()
+        .and_then(|c| match c {
+            XMLNode::Text(t) | XMLNode::CData(t) => Some(t.as_str()),
+            _ => None,
+        })
+        .unwrap_or("")
+}
+
+/// The `name` of a prop, from its attribute or, for props JMeter saves by
+/// reflection like `<doubleProp>`, its `<name>` child.
+fn prop_name(e: &Element) -> String {
+    prop_name_str(e).to_string()
+}
+
+fn prop_name_str(e: &Element) -> &str {
+    e.attributes
+        .get("name")
+        .map(String::as_str)
+        .or_else(|| e.get_child("name").map(text_str))
+        .unwrap_or("")
+}
+
+/// The value of a numeric prop, from its `<value>` child or its text, or
+/// `None` when it doesn't parse.
+fn number<T: FromStr>(e: &Element) -> Option<T> {
+    e.get_child("value")
+        .map(text)
+        .unwrap_or_else(|| text(e))
+        .trim()
+        .parse::<T>()
+        .ok()
+}
+
+/// `<tag name="name">text</tag>`
+fn named_text(tag: &str, name: &str, text: &str) -> Element {
+    let mut e = Element::new(tag);
+    e.attributes.insert(String::from("name"), name.to_string());
+    if !text.is_empty() {
+        e.children.push(XMLNode::Text(text.to_string()));
+    }
+    e
+}
+
+/// `<tag>text</tag>`, for props that nest their name and value
+fn text_child(tag: &str, text: &str) -> XMLNode {
+    let mut e = Element::new(tag);
+    e.children.push(XMLNode::Text(text.to_string()));
+    XMLNode::Element(e)
+}
 
 /// `<stringProp>`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StringProp {
     pub name: String,
     pub value: String,
@@ -11,19 +69,21 @@ pub struct StringProp {
 impl Deserializer for StringProp {
     fn parse(e: &Element) -> Self {
         assert_eq!(e.name.as_str(), "stringProp");
-        let name = e.attributes.get("name").unwrap().clone();
-        let value = if let Some(child) = e.children.first() {
-            child.as_text().unwrap()
-        } else {
-            ""
-        }
-        .to_string();
+        let name = prop_name(e);
+        let value = text(e);
         Self { name, value }
     }
 }
 
+impl Serializer for StringProp {
+    fn to_element(&self) -> Element {
+        named_text("stringProp", &self.name, &self.value)
+    }
+}
+
 /// `<boolProp>`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoolProp {
     pub name: String,
     pub value: bool,
@@ -32,19 +92,415 @@ pub struct BoolProp {
 impl Deserializer for BoolProp {
     fn parse(e: &Element) -> Self {
         assert_eq!(e.name.as_str(), "boolProp");
-        let name = e.attributes.get("name").unwrap().clone();
-        let value = e
-            .children
-            .first()
-            .unwrap()
-            .as_text()
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
+        let name = prop_name(e);
+        // Like Java's `Boolean.parseBoolean`, anything but `true` is false.
+        let value = text(e).trim().eq_ignore_ascii_case("true");
+        Self { name, value }
+    }
+}
+
+impl Serializer for BoolProp {
+    fn to_element(&self) -> Element {
+        named_text("boolProp", &self.name, &self.value.to_string())
+    }
+}
+
+/// `<intProp>`. A value that isn't a number reads as 0; [`Property`] keeps
+/// such props raw instead, as do the other numeric props.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntProp {
+    pub name: String,
+    pub value: i32,
+}
+
+impl Deserializer for IntProp {
+    fn parse(e: &Element) -> Self {
+        assert_eq!(e.name.as_str(), "intProp");
+        let name = prop_name(e);
+        let value = number(e).unwrap_or_default();
         Self { name, value }
     }
 }
 
+impl Serializer for IntProp {
+    fn to_element(&self) -> Element {
+        named_text("intProp", &self.name, &self.value.to_string())
+    }
+}
+
+/// `<longProp>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LongProp {
+    pub name: String,
+    pub value: i64,
+}
+
+impl Deserializer for LongProp {
+    fn parse(e: &Element) -> Self {
+        assert_eq!(e.name.as_str(), "longProp");
+        let name = prop_name(e);
+        let value = number(e).unwrap_or_default();
+        Self { name, value }
+    }
+}
+
+impl Serializer for LongProp {
+    fn to_element(&self) -> Element {
+        named_text("longProp", &self.name, &self.value.to_string())
+    }
+}
+
+/// `<doubleProp>`, which JMeter writes with `<name>` and `<value>` children
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DoubleProp {
+    pub name: String,
+    pub value: f64,
+}
+
+impl Deserializer for DoubleProp {
+    fn parse(e: &Element) -> Self {
+        assert_eq!(e.name.as_str(), "doubleProp");
+        let name = prop_name(e);
+        let value = number(e).unwrap_or_default();
+        Self { name, value }
+    }
+}
+
+impl Serializer for DoubleProp {
+    fn to_element(&self) -> Element {
+        let mut e = Element::new("doubleProp");
+        e.children.push(text_child("name", &self.name));
+        e.children
+            .push(text_child("value", &format!("{:?}", self.value)));
+        e.children.push(text_child("savedValue", "0.0"));
+        e
+    }
+}
+
+/// `<floatProp>`, saved like `<doubleProp>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FloatProp {
+    pub name: String,
+    pub value: f32,
+}
+
+impl Deserializer for FloatProp {
+    fn parse(e: &Element) -> Self {
+        assert_eq!(e.name.as_str(), "floatProp");
+        let name = prop_name(e);
+        let value = number(e).unwrap_or_default();
+        Self { name, value }
+    }
+}
+
+impl Serializer for FloatProp {
+    fn to_element(&self) -> Element {
+        let mut e = Element::new("floatProp");
+        e.children.push(text_child("name", &self.name));
+        e.children
+            .push(text_child("value", &format!("{:?}", self.value)));
+        e.children.push(text_child("savedValue", "0.0"));
+        e
+    }
+}
+
+/// `<objectProp>`, a serialized Java object such as a listener's
+/// `SampleSaveConfiguration`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectProp {
+    pub name: String,
+    pub class: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl Deserializer for ObjectProp {
+    fn parse(e: &Element) -> Self {
+        assert_eq!(e.name.as_str(), "objectProp");
+        let name = prop_name(e);
+        let value = e.get_child("value");
+        let class = value
+            .and_then(|v| v.attributes.get("class"))
+            .cloned()
+            .unwrap_or_default();
+        let fields = value
+            .map(|v| {
+                v.children
+                    .iter()
+                    .filter_map(|c| c.as_element())
+                    .map(|c| (c.name.clone(), text(c)))
+                    .collect::<Vec<(String, String)>>()
+            })
+            .unwrap_or_default();
+        Self {
+            name,
+            class,
+            fields,
+        }
+    }
+}
+
+impl Serializer for ObjectProp {
+    fn to_element(&self) -> Element {
+        let mut value = Element::new("value");
+        value
+            .attributes
+            .insert(String::from("class"), self.class.clone());
+        for (name, text) in &self.fields {
+            value.children.push(text_child(name, text));
+        }
+        let mut e = Element::new("objectProp");
+        e.attributes.insert(String::from("name"), self.name.clone());
+        e.children.push(text_child("name", &self.name));
+        e.children.push(XMLNode::Element(value));
+        e
+    }
+}
+
+/// Any nested `<elementProp>`, whatever its `elementType`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElementProp {
+    pub name: String,
+    pub element_type: String,
+    /// Its other attributes in order, such as the `guiclass`, `testclass`,
+    /// `testname` and `enabled` of a nested test element
+    pub attributes: Vec<(String, String)>,
+    pub props: Vec<Property>,
+}
+
+impl ElementProp {
+    pub fn get(&self, name: &str) -> Option<&Property> {
+        Property::find(&self.props, name)
+    }
+}
+
+impl Deserializer for ElementProp {
+    fn parse(e: &Element) -> Self {
+        assert_eq!(e.name.as_str(), "elementProp");
+        let name = prop_name(e);
+        let element_type = e.attributes.get("elementType").cloned().unwrap_or_default();
+        let attributes = e
+            .attributes
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "name" | "elementType"))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<(String, String)>>();
+        let props = Property::parse_children(e);
+        Self {
+            name,
+            element_type,
+            attributes,
+            props,
+        }
+    }
+}
+
+impl Serializer for ElementProp {
+    fn to_element(&self) -> Element {
+        let mut e = Element::new("elementProp");
+        e.attributes.insert(String::from("name"), self.name.clone());
+        e.attributes
+            .insert(String::from("elementType"), self.element_type.clone());
+        for (key, value) in &self.attributes {
+            e.attributes.insert(key.clone(), value.clone());
+        }
+        e.children = self
+            .props
+            .iter()
+            .map(|p| XMLNode::Element(p.to_element()))
+            .collect::<Vec<XMLNode>>();
+        e
+    }
+}
+
+impl Deserializer for CollectionProp<Property> {
+    fn parse(e: &Element) -> Self {
+        assert_eq!(e.name.as_str(), "collectionProp");
+        Self {
+            props: Property::parse_children(e),
+        }
+    }
+}
+
+/// Any JMeter property. Props of an unknown kind, and numeric props whose
+/// value doesn't parse, are kept [`Raw`](Property::Raw) so they still
+/// round-trip.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Property {
+    String(StringProp),
+    Bool(BoolProp),
+    Int(IntProp),
+    Long(LongProp),
+    Float(FloatProp),
+    Double(DoubleProp),
+    Object(ObjectProp),
+    Element(ElementProp),
+    Collection(String, CollectionProp<Property>),
+    Raw(#[cfg_attr(feature = "serde", serde(with = "crate::xml_serde"))] Element),
+}
+
+impl Deserializer for Property {
+    fn parse(e: &Element) -> Self {
+        let raw = || Property::Raw(e.clone());
+        match e.name.as_str() {
+            "stringProp" => Property::String(StringProp::parse(e)),
+            "boolProp" => Property::Bool(BoolProp::parse(e)),
+            "intProp" => number::<i32>(e).map_or_else(raw, |_| Property::Int(IntProp::parse(e))),
+            "longProp" => number::<i64>(e).map_or_else(raw, |_| Property::Long(LongProp::parse(e))),
+            "floatProp" => {
+                number::<f32>(e).map_or_else(raw, |_| Property::Float(FloatProp::parse(e)))
+            }
+            "doubleProp" => {
+                number::<f64>(e).map_or_else(raw, |_| Property::Double(DoubleProp::parse(e)))
+            }
+            "objectProp" => Property::Object(ObjectProp::parse(e)),
+            "elementProp" => Property::Element(ElementProp::parse(e)),
+            "collectionProp" => Property::Collection(
+                e.attributes.get("name").cloned().unwrap_or_default(),
+                CollectionProp::<Property>::parse(e),
+            ),
+            _ => raw(),
+        }
+    }
+}
+
+impl Serializer for Property {
+    fn to_element(&self) -> Element {
+        match self {
+            Property::String(p) => p.to_element(),
+            Property::Bool(p) => p.to_element(),
+            Property::Int(p) => p.to_element(),
+            Property::Long(p) => p.to_element(),
+            Property::Float(p) => p.to_element(),
+            Property::Double(p) => p.to_element(),
+            Property::Object(p) => p.to_element(),
+            Property::Element(p) => p.to_element(),
+            Property::Collection(name, c) => {
+                let mut e = named_text("collectionProp", name, "");
+                e.children = c
+                    .props
+                    .iter()
+                    .map(|p| XMLNode::Element(p.to_element()))
+                    .collect::<Vec<XMLNode>>();
+                e
+            }
+            Property::Raw(e) => e.clone(),
+        }
+    }
+}
+
+impl Property {
+    /// Parses every property directly under `e`, skipping text and comments.
+    pub fn parse_children(e: &Element) -> Vec<Property> {
+        e.children
+            .iter()
+            .filter_map(|c| c.as_element())
+            .map(Property::parse)
+            .collect::<Vec<Property>>()
+    }
+
+    pub fn find<'a>(props: &'a [Property], name: &str) -> Option<&'a Property> {
+        props.iter().find(|p| p.name() == name)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Property::String(p) => &p.name,
+            Property::Bool(p) => &p.name,
+            Property::Int(p) => &p.name,
+            Property::Long(p) => &p.name,
+            Property::Float(p) => &p.name,
+            Property::Double(p) => &p.name,
+            Property::Object(p) => &p.name,
+            Property::Element(p) => &p.name,
+            Property::Collection(name, _) => name,
+            Property::Raw(e) => prop_name_str(e),
+        }
+    }
+
+    /// A scalar property's value as text, like JMeter's `getStringValue`.
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            Property::String(p) => Some(p.value.clone()),
+            Property::Bool(p) => Some(p.value.to_string()),
+            Property::Int(p) => Some(p.value.to_string()),
+            Property::Long(p) => Some(p.value.to_string()),
+            Property::Float(p) => Some(p.value.to_string()),
+            Property::Double(p) => Some(p.value.to_string()),
+            // A scalar kept raw, like an `<intProp>` holding `${threads}`
+            Property::Raw(e) if !e.children.iter().any(|c| c.as_element().is_some()) => {
+                Some(text(e))
+            }
+            Property::Raw(e) => e.get_child("value").map(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Property::String(p) => Some(&p.value),
+            _ => None,
+        }
+    }
+
+    /// Like JMeter, a `<stringProp>` holding `true`/`false` reads as a bool.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Property::Bool(p) => Some(p.value),
+            Property::String(p) => p.value.parse::<bool>().ok(),
+            _ => None,
+        }
+    }
+
+    /// Numbers are often saved as `<stringProp>`, which is parsed here.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Property::Int(p) => Some(p.value as i64),
+            Property::Long(p) => Some(p.value),
+            Property::String(p) => p.value.trim().parse::<i64>().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Property::Double(p) => Some(p.value),
+            Property::Float(p) => Some(p.value as f64),
+            Property::Int(p) => Some(p.value as f64),
+            Property::Long(p) => Some(p.value as f64),
+            Property::String(p) => p.value.trim().parse::<f64>().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&ObjectProp> {
+        match self {
+            Property::Object(p) => Some(p),
+            _ => None,
+        }
+    }
+
+    pub fn as_element(&self) -> Option<&ElementProp> {
+        match self {
+            Property::Element(p) => Some(p),
+            _ => None,
+        }
+    }
+
+    pub fn as_collection(&self) -> Option<&[Property]> {
+        match self {
+            Property::Collection(_, c) => Some(&c.props),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::Deserializer;
@@ -89,4 +545,185 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn int_prop_check_parse() {
+        use crate::IntProp;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/basic_prop/int_prop.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            IntProp::parse(&xml),
+            IntProp {
+                name: String::from("LoopController.loops"),
+                value: -1
+            }
+        )
+    }
+
+    #[test]
+    fn long_prop_check_parse() {
+        use crate::LongProp;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/basic_prop/long_prop.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            LongProp::parse(&xml),
+            LongProp {
+                name: String::from("ThreadGroup.duration"),
+                value: 3600
+            }
+        )
+    }
+
+    #[test]
+    fn double_prop_check_parse() {
+        use crate::DoubleProp;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/basic_prop/double_prop.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            DoubleProp::parse(&xml),
+            DoubleProp {
+                name: String::from("throughput"),
+                value: 60.0
+            }
+        )
+    }
+
+    #[test]
+    fn float_prop_check_parse() {
+        use crate::{FloatProp, Property, Serializer};
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/basic_prop/float_prop.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let prop = Property::parse(&xml);
+        assert_eq!(
+            prop,
+            Property::Float(FloatProp {
+                name: String::from("ratio"),
+                value: 0.5
+            })
+        );
+        assert_eq!(prop.as_f64(), Some(0.5));
+        assert_eq!(Property::parse(&prop.to_element()), prop);
+    }
+
+    #[test]
+    fn raw_props_check_parse() {
+        use crate::{Property, Serializer};
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/basic_prop/raw_props.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let prop = Property::parse(&xml);
+        let element = prop.as_element().unwrap();
+        assert!(matches!(element.get("threads"), Some(Property::Raw(_))));
+        assert_eq!(element.get("threads").and_then(|p| p.as_i64()), None);
+        assert_eq!(
+            element.get("threads").and_then(|p| p.as_text()).as_deref(),
+            Some("${__P(threads)}")
+        );
+        assert_eq!(element.get("custom").and_then(|p| p.as_text()), None);
+        assert!(matches!(element.get("custom"), Some(Property::Raw(_))));
+        assert_eq!(element.get("flag").and_then(|p| p.as_bool()), Some(true));
+        // Named by its `<name>` child, as a parsed `<doubleProp>` would be
+        let ratio = element.get("ratio").unwrap();
+        assert!(matches!(ratio, Property::Raw(_)));
+        assert_eq!(ratio.name(), "ratio");
+        assert_eq!(ratio.as_text().as_deref(), Some("${ratio}"));
+        let saved = prop.to_element();
+        assert_eq!(
+            saved.children[0].as_element(),
+            xml.children.iter().find_map(|c| c.as_element())
+        );
+        assert_eq!(Property::parse(&saved), prop);
+    }
+
+    #[test]
+    fn object_prop_check_parse() {
+        use crate::ObjectProp;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/basic_prop/object_prop.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            ObjectProp::parse(&xml),
+            ObjectProp {
+                name: String::from("saveConfig"),
+                class: String::from("SampleSaveConfiguration"),
+                fields: vec![
+                    (String::from("time"), String::from("true")),
+                    (String::from("latency"), String::from("true")),
+                    (String::from("threadCounts"), String::from("true")),
+                ]
+            }
+        )
+    }
+
+    #[test]
+    fn element_prop_check_parse() {
+        use crate::{Property, Serializer};
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/basic_prop/element_prop.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let prop = Property::parse(&xml);
+        let element = prop.as_element().unwrap();
+        assert_eq!(prop.name(), "ThreadGroup.main_controller");
+        assert_eq!(element.element_type, "LoopController");
+        assert_eq!(
+            element
+                .get("LoopController.continue_forever")
+                .and_then(|p| p.as_bool()),
+            Some(false)
+        );
+        assert_eq!(
+            element.get("LoopController.loops").and_then(|p| p.as_i64()),
+            Some(-1)
+        );
+        assert_eq!(
+            element
+                .get("LoopController.labels")
+                .and_then(|p| p.as_collection())
+                .map(|c| c[0].as_str()),
+            Some(Some("first"))
+        );
+        // The attributes of the nested test element are saved back
+        let saved = prop.to_element();
+        assert_eq!(saved.attributes, xml.attributes);
+        assert_eq!(Property::parse(&saved), prop);
+    }
 }
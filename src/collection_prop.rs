@@ -1,9 +1,24 @@
-use crate::{Deserializer, ElementArgument, ElementHeader, HashMappable};
+use crate::{
+    Deserializer, ElementArgument, ElementHeader, ElementHttpFileArg, HashMappable, Property,
+    Serializer,
+};
+use anyhow::anyhow;
 use std::collections::HashMap;
-use xmltree::Element;
+use xmltree::{Element, XMLNode};
+
+fn collection_prop<T: Serializer>(name: &str, props: &[T]) -> Element {
+    let mut e = Element::new("collectionProp");
+    e.attributes.insert(String::from("name"), name.to_string());
+    e.children = props
+        .iter()
+        .map(|p| XMLNode::Element(p.to_element()))
+        .collect::<Vec<XMLNode>>();
+    e
+}
 
 /// `<collectionProp>`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CollectionProp<T> {
     pub props: Vec<T>,
 }
@@ -26,6 +41,12 @@ impl Deserializer for CollectionProp<ElementHeader> {
     }
 }
 
+impl Serializer for CollectionProp<ElementHeader> {
+    fn to_element(&self) -> Element {
+        collection_prop("HeaderManager.headers", &self.props)
+    }
+}
+
 impl HashMappable for CollectionProp<ElementHeader> {
     fn to_hash_map(&self) -> HashMap<String, String> {
         self.props
@@ -35,21 +56,27 @@ impl HashMappable for CollectionProp<ElementHeader> {
     }
 }
 
-impl Deserializer for CollectionProp<ElementArgument> {
-    fn parse(e: &Element) -> Self {
-        assert_eq!(e.name.as_str(), "collectionProp");
-        assert_eq!(
-            e.attributes.get("name").unwrap().as_str(),
-            "Arguments.arguments"
-        );
-        let props = e
-            .children
+/// The items of a `<collectionProp>`, failing on the first that isn't a `T`
+impl<T> TryFrom<&Property> for CollectionProp<T>
+where
+    T: for<'a> TryFrom<&'a Property, Error = anyhow::Error>,
+{
+    type Error = anyhow::Error;
+
+    fn try_from(p: &Property) -> anyhow::Result<Self> {
+        let props = p
+            .as_collection()
+            .ok_or_else(|| anyhow!("expected a collectionProp, found {p:?}"))?
             .iter()
-            .map(|x| x.as_element())
-            .filter(|x| x.is_some())
-            .map(|x| ElementArgument::parse(x.unwrap()))
-            .collect::<Vec<ElementArgument>>();
-        Self { props }
+            .map(T::try_from)
+            .collect::<anyhow::Result<Vec<T>>>()?;
+        Ok(Self { props })
+    }
+}
+
+impl Serializer for CollectionProp<ElementArgument> {
+    fn to_element(&self) -> Element {
+        collection_prop("Arguments.arguments", &self.props)
     }
 }
 
@@ -62,6 +89,12 @@ impl HashMappable for CollectionProp<ElementArgument> {
     }
 }
 
+impl Serializer for CollectionProp<ElementHttpFileArg> {
+    fn to_element(&self) -> Element {
+        collection_prop("HTTPFileArgs.files", &self.props)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{CollectionProp, Deserializer};
@@ -69,7 +102,7 @@ mod test {
 
     #[test]
     fn check_parse_1() {
-        use crate::ElementArgument;
+        use crate::{ElementArgument, Property};
 
         let xml = Element::parse(
             std::fs::read_to_string("./unittests_data/collection_prop/collection_prop_1.xml")
@@ -79,7 +112,7 @@ mod test {
         )
         .unwrap();
         assert_eq!(
-            CollectionProp::parse(&xml),
+            CollectionProp::try_from(&Property::parse(&xml)).unwrap(),
             CollectionProp {
                 props: vec![
                     ElementArgument {
@@ -1,38 +1,714 @@
-use crate::{CollectionProp, Deserializer, ElementHeader, HashMappable};
+use crate::element_prop::arguments_prop;
+use crate::{
+    test_element, CollectionProp, ElementArgument, ElementHeader, ElementStaticHost, FromJmx,
+    GooseCodegen, HashMappable, JmxElement, Property, PropertyBag, Serializer, StringProp,
+    ToProperty, Value,
+};
+use anyhow::anyhow;
 use std::collections::HashMap;
-use xmltree::Element;
+use xmltree::{Element, XMLNode};
 
 /// HTTP Header Manager
 /// `<HeaderManager>`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HeaderManager {
     pub test_name: String,
     pub enabled: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::sorted_map::serialize")
+    )]
     pub headers: HashMap<String, String>,
 }
 
-impl HeaderManager {
-    pub fn parse(e: &Element) -> Self {
-        assert_eq!(e.name.as_str(), "HeaderManager");
-        let test_name = e.attributes.get("testname").unwrap().clone();
-        let enabled = e
+impl FromJmx for HeaderManager {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("HeaderManager")?;
+        let headers = e
+            .get_collection("HeaderManager.headers")?
+            .iter()
+            .map(ElementHeader::try_from)
+            .collect::<anyhow::Result<Vec<ElementHeader>>>()?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            headers: CollectionProp { props: headers }.to_hash_map(),
+        })
+    }
+}
+
+impl Serializer for HeaderManager {
+    /// Headers are written sorted by name.
+    fn to_element(&self) -> Element {
+        let mut headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| ElementHeader {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect::<Vec<ElementHeader>>();
+        headers.sort_by(|a, b| a.name.cmp(&b.name));
+        test_element(
+            "HeaderManager",
+            "HeaderPanel",
+            &self.test_name,
+            self.enabled,
+            vec![CollectionProp { props: headers }.to_element()],
+        )
+    }
+}
+
+/// User Defined Variables
+/// `<Arguments>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Arguments {
+    pub test_name: String,
+    pub enabled: bool,
+    /// Kept in declaration order, later values may reference earlier ones
+    pub arguments: Vec<ElementArgument>,
+}
+
+impl FromJmx for Arguments {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("Arguments")?;
+        let arguments = e
+            .get_collection("Arguments.arguments")?
+            .iter()
+            .map(ElementArgument::try_from)
+            .collect::<anyhow::Result<Vec<ElementArgument>>>()?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            arguments,
+        })
+    }
+}
+
+impl Serializer for Arguments {
+    fn to_element(&self) -> Element {
+        let arguments = CollectionProp {
+            props: self
+                .arguments
+                .iter()
+                .map(|a| ElementArgument {
+                    name: a.name.clone(),
+                    value: a.value.clone(),
+                    metadata: a.metadata.clone(),
+                })
+                .collect::<Vec<ElementArgument>>(),
+        };
+        test_element(
+            "Arguments",
+            "ArgumentsPanel",
+            &self.test_name,
+            self.enabled,
+            vec![arguments.to_element()],
+        )
+    }
+}
+
+/// HTTP Request Defaults
+/// `<ConfigTestElement guiclass="HttpDefaultsGui">`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HttpDefaults {
+    pub test_name: String,
+    pub enabled: bool,
+    pub domain: String,
+    pub port: String,
+    pub protocol: String,
+    pub path: String,
+    pub content_encoding: String,
+    pub connect_timeout: String,
+    pub response_timeout: String,
+    pub implementation: String,
+    pub image_parser: bool,
+    pub concurrent_dwn: bool,
+    pub concurrent_pool: String,
+    pub embedded_url_re: String,
+    /// Added to the samplers' own arguments of other names
+    pub arguments: Vec<ElementArgument>,
+}
+
+impl FromJmx for HttpDefaults {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("ConfigTestElement")?;
+        if e.gui_class != "HttpDefaultsGui" {
+            return Err(anyhow!(
+                "expected HTTP Request Defaults, found {}",
+                e.gui_class
+            ));
+        }
+        let arguments = match e.get("HTTPsampler.Arguments") {
+            Some(_) => e
+                .get_element("HTTPsampler.Arguments")?
+                .get_collection("Arguments.arguments")?
+                .iter()
+                .map(ElementArgument::try_from)
+                .collect::<anyhow::Result<Vec<ElementArgument>>>()?,
+            None => vec![],
+        };
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            domain: e.get_str("HTTPSampler.domain")?,
+            port: e.get_str("HTTPSampler.port")?,
+            protocol: e.get_str("HTTPSampler.protocol")?,
+            path: e.get_str("HTTPSampler.path")?,
+            content_encoding: e.get_str("HTTPSampler.contentEncoding")?,
+            connect_timeout: e.get_str("HTTPSampler.connect_timeout")?,
+            response_timeout: e.get_str("HTTPSampler.response_timeout")?,
+            implementation: e.get_str("HTTPSampler.implementation")?,
+            image_parser: e.get_bool("HTTPSampler.image_parser")?,
+            concurrent_dwn: e.get_bool("HTTPSampler.concurrentDwn")?,
+            concurrent_pool: e.get_str("HTTPSampler.concurrentPool")?,
+            embedded_url_re: e.get_str("HTTPSampler.embedded_url_re")?,
+            arguments,
+        })
+    }
+}
+
+impl Serializer for HttpDefaults {
+    fn to_element(&self) -> Element {
+        let mut arguments = Element::new("collectionProp");
+        arguments
             .attributes
-            .get("enabled")
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
-        let headers = CollectionProp::<ElementHeader>::parse(e.children[0].as_element().unwrap())
-            .to_hash_map();
-        Self {
-            test_name,
-            enabled,
-            headers,
+            .insert(String::from("name"), String::from("Arguments.arguments"));
+        arguments.children = self
+            .arguments
+            .iter()
+            .map(|a| XMLNode::Element(a.to_http_element()))
+            .collect();
+        let mut props = vec![arguments_prop(
+            "HTTPsampler.Arguments",
+            "HTTPArgumentsPanel",
+            arguments,
+        )];
+        props.extend(
+            [
+                self.domain.to_property("HTTPSampler.domain"),
+                self.port.to_property("HTTPSampler.port"),
+                self.protocol.to_property("HTTPSampler.protocol"),
+                self.content_encoding
+                    .to_property("HTTPSampler.contentEncoding"),
+                self.path.to_property("HTTPSampler.path"),
+                self.implementation
+                    .to_property("HTTPSampler.implementation"),
+                self.image_parser.to_property("HTTPSampler.image_parser"),
+                self.concurrent_dwn.to_property("HTTPSampler.concurrentDwn"),
+                self.concurrent_pool
+                    .to_property("HTTPSampler.concurrentPool"),
+                self.embedded_url_re
+                    .to_property("HTTPSampler.embedded_url_re"),
+                self.connect_timeout
+                    .to_property("HTTPSampler.connect_timeout"),
+                self.response_timeout
+                    .to_property("HTTPSampler.response_timeout"),
+            ]
+            .iter()
+            .map(|p| p.to_element()),
+        );
+        test_element(
+            "ConfigTestElement",
+            "HttpDefaultsGui",
+            &self.test_name,
+            self.enabled,
+            props,
+        )
+    }
+}
+
+/// DNS Cache Manager
+/// `<DNSCacheManager>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DnsCacheManager {
+    pub test_name: String,
+    pub enabled: bool,
+    /// Name servers of the custom resolver
+    pub servers: Vec<String>,
+    /// Looked up before the servers by the custom resolver
+    pub hosts: Vec<ElementStaticHost>,
+    pub clear_each_iteration: bool,
+    /// Resolve with `servers` and `hosts` rather than the system's DNS
+    pub custom_resolver: bool,
+}
+
+impl FromJmx for DnsCacheManager {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("DNSCacheManager")?;
+        let servers = e
+            .get_collection("DNSCacheManager.servers")?
+            .iter()
+            .map(|p| {
+                p.as_str()
+                    .map(String::from)
+                    .ok_or_else(|| anyhow!("expected a stringProp, found {p:?}"))
+            })
+            .collect::<anyhow::Result<Vec<String>>>()?;
+        let hosts = e
+            .get_collection("DNSCacheManager.hosts")?
+            .iter()
+            .map(ElementStaticHost::try_from)
+            .collect::<anyhow::Result<Vec<ElementStaticHost>>>()?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            servers,
+            hosts,
+            clear_each_iteration: e.get_bool("DNSCacheManager.clearEachIteration")?,
+            custom_resolver: e.get_bool("DNSCacheManager.isCustomResolver")?,
+        })
+    }
+}
+
+impl Serializer for DnsCacheManager {
+    fn to_element(&self) -> Element {
+        let mut hosts = Element::new("collectionProp");
+        hosts
+            .attributes
+            .insert(String::from("name"), String::from("DNSCacheManager.hosts"));
+        hosts.children = self
+            .hosts
+            .iter()
+            .map(|h| XMLNode::Element(h.to_element()))
+            .collect();
+        test_element(
+            "DNSCacheManager",
+            "DNSCachePanel",
+            &self.test_name,
+            self.enabled,
+            vec![
+                string_collection("DNSCacheManager.servers", &self.servers).to_element(),
+                hosts,
+                self.clear_each_iteration
+                    .to_property("DNSCacheManager.clearEachIteration")
+                    .to_element(),
+                self.custom_resolver
+                    .to_property("DNSCacheManager.isCustomResolver")
+                    .to_element(),
+            ],
+        )
+    }
+}
+
+/// Keystore Configuration
+/// `<KeystoreConfig>`
+///
+/// The keystore itself is set with the `javax.net.ssl.keyStore` and
+/// `javax.net.ssl.keyStorePassword` properties.
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "KeystoreConfig")]
+pub struct KeystoreConfig {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+    /// The variable naming the alias of each thread's certificate, blank to
+    /// pick them by index
+    #[jmx(prop = "clientCertAliasVarName")]
+    pub client_cert_alias_var_name: String,
+    /// The first alias index threads use
+    #[jmx(prop = "startIndex", default = "0")]
+    pub start_index: Value<i64>,
+    /// The last alias index threads use
+    #[jmx(prop = "endIndex", default = "0")]
+    pub end_index: Value<i64>,
+    /// Load the keys at the start of the test rather than on first use
+    #[jmx(prop = "preload", default = "true")]
+    pub preload: bool,
+}
+
+/// User Parameters
+/// `<UserParameters>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserParameters {
+    pub test_name: String,
+    pub enabled: bool,
+    pub names: Vec<String>,
+    /// One column of values per user, in the order of `names`
+    pub thread_values: Vec<Vec<String>>,
+    /// Updates the variables once per iteration rather than before every
+    /// sampler in scope
+    pub per_iteration: bool,
+}
+
+impl FromJmx for UserParameters {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("UserParameters")?;
+        let strings = |props: &[Property]| {
+            props
+                .iter()
+                .map(|p| {
+                    p.as_str()
+                        .map(String::from)
+                        .ok_or_else(|| anyhow!("expected a stringProp, found {p:?}"))
+                })
+                .collect::<anyhow::Result<Vec<String>>>()
+        };
+        let names = strings(e.get_collection("UserParameters.names")?)?;
+        let thread_values = e
+            .get_collection("UserParameters.thread_values")?
+            .iter()
+            .map(|p| {
+                strings(
+                    p.as_collection()
+                        .ok_or_else(|| anyhow!("expected a collectionProp, found {p:?}"))?,
+                )
+            })
+            .collect::<anyhow::Result<Vec<Vec<String>>>>()?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            names,
+            thread_values,
+            per_iteration: e.get_bool("UserParameters.per_iteration")?,
+        })
+    }
+}
+
+/// JMeter names the strings of a collection after Java's `String.hashCode`.
+pub(crate) fn java_hash(s: &str) -> i32 {
+    s.encode_utf16()
+        .fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32))
+}
+
+pub(crate) fn string_collection(name: &str, values: &[String]) -> Property {
+    Property::Collection(
+        name.to_string(),
+        CollectionProp {
+            props: values
+                .iter()
+                .map(|v| {
+                    Property::String(StringProp {
+                        name: java_hash(v).to_string(),
+                        value: v.clone(),
+                    })
+                })
+                .collect::<Vec<Property>>(),
+        },
+    )
+}
+
+impl Serializer for UserParameters {
+    fn to_element(&self) -> Element {
+        let thread_values = Property::Collection(
+            String::from("UserParameters.thread_values"),
+            CollectionProp {
+                props: self
+                    .thread_values
+                    .iter()
+                    .map(|column| {
+                        let name = java_hash(&column.concat()).to_string();
+                        string_collection(&name, column)
+                    })
+                    .collect::<Vec<Property>>(),
+            },
+        );
+        test_element(
+            "UserParameters",
+            "UserParametersGui",
+            &self.test_name,
+            self.enabled,
+            vec![
+                string_collection("UserParameters.names", &self.names).to_element(),
+                thread_values.to_element(),
+                self.per_iteration
+                    .to_property("UserParameters.per_iteration")
+                    .to_element(),
+            ],
+        )
+    }
+}
+
+impl UserParameters {
+    /// Values for the `user`th user (0-based); JMeter wraps around when there
+    /// are more users than columns.
+    pub fn values_for(&self, user: usize) -> Vec<(String, String)> {
+        if self.thread_values.is_empty() {
+            return vec![];
+        }
+        let column = &self.thread_values[user % self.thread_values.len()];
+        self.names
+            .iter()
+            .cloned()
+            .zip(column.iter().cloned())
+            .collect::<Vec<(String, String)>>()
+    }
+}
+
+impl GooseCodegen for UserParameters {
+    /// Sets the variables to the values of the user's column, by its number
+    /// among its thread group's users.
+    fn to_goose(&self) -> String {
+        if self.thread_values.is_empty() {
+            return String::from("{}");
+        }
+        let columns = self
+            .thread_values
+            .iter()
+            .map(|column| format!("&{column:?}"))
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!(
+            r#"{{
+    let names: [&str; {}] = {:?};
+    let columns: [&[&str]; {}] = [{columns}];
+    for (name, value) in names.iter().zip(columns[thread_number(user) % columns.len()]) {{
+        let value = substitute(value, vars);
+        vars.insert(name.to_string(), value);
+    }}
+}}"#,
+            self.names.len(),
+            self.names,
+            self.thread_values.len()
+        )
+    }
+}
+
+/// Turns a `java.text.DecimalFormat` pattern such as `000` or `USER_000` into
+/// a `format!` string. An empty pattern, or one this can't express, prints
+/// the plain number.
+fn decimal_format(pattern: &str) -> String {
+    let start = pattern.find(['0', '#']);
+    let end = pattern.rfind(['0', '#']).map(|i| i + 1);
+    let (start, end) = match (start, end) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return String::from("{}"),
+    };
+    let digits = &pattern[start..end];
+    if !digits.chars().all(|c| c == '0' || c == '#') {
+        return String::from("{}");
+    }
+    let escape = |s: &str| s.replace('{', "{{").replace('}', "}}");
+    let width = digits.chars().filter(|c| *c == '0').count();
+    let number = if width > 1 {
+        format!("{{:0{width}}}")
+    } else {
+        String::from("{}")
+    };
+    format!(
+        "{}{number}{}",
+        escape(&pattern[..start]),
+        escape(&pattern[end..])
+    )
+}
+
+/// `value` as Rust code for an `i64`, `fallback` when an expression doesn't
+/// come to a number at run time. Expressions read the user's `vars` and the
+/// locked JMeter `props`.
+fn whole_number(value: &Value<i64>, fallback: &str) -> String {
+    match value {
+        Value::Literal(value) => value.to_string(),
+        Value::Expression(expression) => {
+            format!("whole_number(&substitute({expression:?}, vars), props).unwrap_or({fallback})")
+        }
+    }
+}
+
+/// Random Variable
+/// `<RandomVariableConfig>`
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "RandomVariableConfig")]
+pub struct RandomVariableConfig {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+    #[jmx(prop = "variableName")]
+    pub variable_name: String,
+    #[jmx(prop = "minimumValue")]
+    pub minimum_value: Value<i64>,
+    #[jmx(prop = "maximumValue")]
+    pub maximum_value: Value<i64>,
+    #[jmx(prop = "outputFormat")]
+    pub output_format: String,
+    /// `None` for an unseeded generator, as is a seed that isn't a number
+    #[jmx(prop = "randomSeed")]
+    pub random_seed: Option<Value<i64>>,
+    #[jmx(prop = "perThread")]
+    pub per_thread: bool,
+}
+
+impl RandomVariableConfig {
+    /// The type and initial value of the static holding the `StdRng` of a
+    /// seed shared by all users, `None` when there is none.
+    pub fn shared_state(&self) -> Option<&'static str> {
+        (self.random_seed.is_some() && !self.per_thread).then_some(
+            "std::sync::OnceLock<std::sync::Mutex<rand::rngs::StdRng>> = std::sync::OnceLock::new()",
+        )
+    }
+
+    /// Like [`GooseCodegen::to_goose`], with `state` naming the static of
+    /// [`Self::shared_state`], which the generated crate declares once for
+    /// every thread group.
+    ///
+    /// Unseeded values come from `thread_rng`. A seed shared by all users
+    /// seeds that `StdRng`; a per-user seed keeps a splitmix64 state in the
+    /// user's `vars` so every user replays the same sequence, as JMeter's
+    /// per-thread `Random(seed)` does.
+    pub fn to_goose_sharing(&self, state: &str) -> String {
+        let bounds = format!(
+            "    let (min, max): (i64, i64) = ({}, {});\n",
+            whole_number(&self.minimum_value, "0"),
+            whole_number(&self.maximum_value, "0")
+        );
+        let seed = match &self.random_seed {
+            None => None,
+            Some(Value::Literal(seed)) => Some(format!("Some({seed})")),
+            Some(Value::Expression(seed)) => {
+                Some(format!("whole_number(&substitute({seed:?}, vars), props)"))
+            }
+        };
+        let value = match (seed, self.per_thread) {
+            (None, _) => String::from(
+                "    let value: i64 = rand::Rng::gen_range(&mut rand::thread_rng(), min..=max.max(min));\n",
+            ),
+            (Some(seed), false) => format!(
+                r#"    let seed: Option<i64> = {seed};
+    let rng = {state}.get_or_init(|| {{
+        std::sync::Mutex::new(match seed {{
+            Some(seed) => rand::SeedableRng::seed_from_u64(seed as u64),
+            None => rand::SeedableRng::from_entropy(),
+        }})
+    }});
+    let value: i64 = rand::Rng::gen_range(&mut *rng.lock().unwrap(), min..=max.max(min));
+"#
+            ),
+            (Some(seed), true) => {
+                let state = format!("__random_{}", self.variable_name);
+                format!(
+                    r#"    let seed: Option<i64> = {seed};
+    let state = vars
+        .get({state:?})
+        .and_then(|s| s.parse::<u64>().ok())
+        .or(seed.map(|seed| seed as u64))
+        .unwrap_or_else(rand::random)
+        .wrapping_add(0x9E3779B97F4A7C15);
+    vars.insert({state:?}.to_string(), state.to_string());
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+    let value: i64 = min + (z % (max - min + 1).max(1) as u64) as i64;
+"#
+                )
+            }
+        };
+        format!(
+            "{{\n{bounds}{value}    vars.insert({:?}.to_string(), format!({:?}, value));\n}}",
+            self.variable_name,
+            decimal_format(&self.output_format)
+        )
+    }
+}
+
+/// Counter
+/// `<CounterConfig>`
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "CounterConfig", gui = "CounterConfigGui")]
+pub struct CounterConfig {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+    #[jmx(prop = "CounterConfig.name")]
+    pub name: String,
+    #[jmx(prop = "CounterConfig.start")]
+    pub start: Value<i64>,
+    /// `None` counts up to `i64::MAX` like JMeter's empty maximum
+    #[jmx(prop = "CounterConfig.end")]
+    pub end: Option<Value<i64>>,
+    #[jmx(prop = "CounterConfig.incr", default = "1")]
+    pub incr: Value<i64>,
+    #[jmx(prop = "CounterConfig.format")]
+    pub format: String,
+    #[jmx(prop = "CounterConfig.per_user")]
+    pub per_user: bool,
+    /// Older plans don't write the flag at all
+    #[jmx(prop = "CounterConfig.reset_on_tg_iteration")]
+    pub reset_on_tg_iteration: bool,
+}
+
+impl CounterConfig {
+    fn state_key(&self) -> String {
+        format!("__counter_{}", self.name)
+    }
+
+    /// The type and initial value of the static a counter shared by all
+    /// users counts in, `None` for a per-user one.
+    pub fn shared_state(&self) -> Option<&'static str> {
+        (!self.per_user)
+            .then_some("std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(0)")
+    }
+
+    /// Statement to emit at the start of every thread group iteration when
+    /// the per-user counter has to restart from `start`.
+    pub fn reset_to_goose(&self) -> Option<String> {
+        if self.per_user && self.reset_on_tg_iteration {
+            Some(format!("vars.remove({:?});", self.state_key()))
+        } else {
+            None
         }
     }
 }
 
+impl CounterConfig {
+    /// Like [`GooseCodegen::to_goose`], with `state` naming the static of
+    /// [`Self::shared_state`], which the generated crate declares once for
+    /// every thread group.
+    ///
+    /// A shared counter counts in that static atomic, a per-user one keeps
+    /// its current value in the user's `vars`. Both wrap back to `start`
+    /// after passing `end`.
+    pub fn to_goose_sharing(&self, state: &str) -> String {
+        let bounds = format!(
+            "    let (start, end, incr): (i64, i64, i64) = ({}, {}, {});\n",
+            whole_number(&self.start, "0"),
+            self.end
+                .as_ref()
+                .map_or(String::from("i64::MAX"), |end| whole_number(
+                    end, "i64::MAX"
+                )),
+            whole_number(&self.incr, "1")
+        );
+        let value = if self.per_user {
+            let state = self.state_key();
+            format!(
+                r#"    let value: i64 = match vars.get({state:?}).and_then(|v| v.parse::<i64>().ok()) {{
+        Some(previous) if previous.checked_add(incr).is_some_and(|next| next <= end) => previous + incr,
+        _ => start,
+    }};
+    vars.insert({state:?}.to_string(), value.to_string());
+"#
+            )
+        } else {
+            format!(
+                r#"    let n = {state}.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    // A counter that doesn't move stays at its start
+    let steps = end.saturating_sub(start).checked_div(incr).map_or(0, |steps| steps.max(0));
+    let value: i64 = start + n % steps.saturating_add(1) * incr;
+"#
+            )
+        };
+        format!(
+            "{{\n{bounds}{value}    vars.insert({:?}.to_string(), format!({:?}, value));\n}}",
+            self.name,
+            decimal_format(&self.format)
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use crate::{FromJmx, Serializer, Value};
     use std::collections::HashMap;
     use xmltree::Element;
 
@@ -48,7 +724,7 @@ mod test {
         )
         .unwrap();
         assert_eq!(
-            HeaderManager::parse(&xml),
+            HeaderManager::parse(&xml).unwrap(),
             HeaderManager {
                 test_name: String::from("HTTP Header Manager"),
                 enabled: true,
@@ -62,4 +738,310 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn check_parse_arguments() {
+        use crate::{Arguments, ElementArgument};
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/config_element/arguments.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            Arguments::parse(&xml).unwrap(),
+            Arguments {
+                test_name: String::from("User Defined Variables"),
+                enabled: true,
+                arguments: vec![
+                    ElementArgument {
+                        name: String::from("host"),
+                        value: String::from("example.github.com"),
+                        metadata: String::from("=")
+                    },
+                    ElementArgument {
+                        name: String::from("base"),
+                        value: String::from("https://${host}/api"),
+                        metadata: String::from("=")
+                    }
+                ]
+            }
+        )
+    }
+
+    #[test]
+    fn check_parse_http_defaults() {
+        use crate::{ElementArgument, HttpDefaults};
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/config_element/http_defaults.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let defaults = HttpDefaults::parse(&xml).unwrap();
+        assert_eq!(
+            defaults,
+            HttpDefaults {
+                test_name: String::from("HTTP Request Defaults"),
+                enabled: true,
+                domain: String::from("shop.example.com"),
+                port: String::new(),
+                protocol: String::from("https"),
+                path: String::new(),
+                content_encoding: String::from("UTF-8"),
+                connect_timeout: String::from("5000"),
+                response_timeout: String::from("${__P(timeout,30000)}"),
+                implementation: String::from("HttpClient4"),
+                image_parser: true,
+                concurrent_dwn: true,
+                concurrent_pool: String::from("4"),
+                embedded_url_re: String::from(r".*example\.com.*"),
+                arguments: vec![ElementArgument {
+                    name: String::from("lang"),
+                    value: String::from("en"),
+                    metadata: String::from("=")
+                }]
+            }
+        );
+        assert_eq!(
+            HttpDefaults::parse(&defaults.to_element()).unwrap(),
+            defaults
+        );
+    }
+
+    #[test]
+    fn check_parse_dns_cache_manager() {
+        use crate::{DnsCacheManager, ElementStaticHost};
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/config_element/dns_cache_manager.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let manager = DnsCacheManager::parse(&xml).unwrap();
+        assert_eq!(
+            manager,
+            DnsCacheManager {
+                test_name: String::from("DNS Cache Manager"),
+                enabled: true,
+                servers: vec![String::from("10.0.0.53")],
+                hosts: vec![
+                    ElementStaticHost {
+                        name: String::from("shop.example.com"),
+                        address: String::from("10.0.0.5, 10.0.0.6"),
+                    },
+                    ElementStaticHost {
+                        name: String::from("cdn.example.com"),
+                        address: String::from("localhost"),
+                    },
+                ],
+                clear_each_iteration: true,
+                custom_resolver: true,
+            }
+        );
+        assert_eq!(
+            DnsCacheManager::parse(&manager.to_element()).unwrap(),
+            manager
+        );
+    }
+
+    #[test]
+    fn check_parse_keystore_config() {
+        use crate::KeystoreConfig;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/config_element/keystore_config.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let keystore = KeystoreConfig::parse(&xml).unwrap();
+        assert_eq!(
+            keystore,
+            KeystoreConfig {
+                test_name: String::from("Keystore Configuration"),
+                enabled: true,
+                client_cert_alias_var_name: String::from("certAlias"),
+                start_index: Value::Literal(0),
+                end_index: Value::Expression(String::from("${__P(last_cert,9)}")),
+                preload: true,
+            }
+        );
+        assert_eq!(
+            KeystoreConfig::parse(&keystore.to_element()).unwrap(),
+            keystore
+        );
+    }
+
+    #[test]
+    fn check_parse_user_parameters() {
+        use crate::UserParameters;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/config_element/user_parameters.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let user_parameters = UserParameters::parse(&xml).unwrap();
+        assert_eq!(
+            UserParameters::parse(&user_parameters.to_element()).unwrap(),
+            user_parameters
+        );
+        assert_eq!(
+            user_parameters,
+            UserParameters {
+                test_name: String::from("User Parameters"),
+                enabled: true,
+                names: vec![String::from("user"), String::from("pass")],
+                thread_values: vec![
+                    vec![String::from("alice"), String::from("a1")],
+                    vec![String::from("bob"), String::from("b1")]
+                ],
+                per_iteration: false
+            }
+        );
+        assert_eq!(
+            user_parameters.values_for(2),
+            vec![
+                (String::from("user"), String::from("alice")),
+                (String::from("pass"), String::from("a1"))
+            ]
+        );
+    }
+
+    #[test]
+    fn check_parse_random_variable_config() {
+        use crate::RandomVariableConfig;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/config_element/random_variable_config.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let random = RandomVariableConfig::parse(&xml).unwrap();
+        assert_eq!(
+            RandomVariableConfig::parse(&random.to_element()).unwrap(),
+            random
+        );
+        assert_eq!(
+            random,
+            RandomVariableConfig {
+                test_name: String::from("Random Variable"),
+                enabled: true,
+                variable_name: String::from("rand"),
+                minimum_value: Value::Literal(1),
+                maximum_value: Value::Literal(100),
+                output_format: String::from("USER_000"),
+                random_seed: Some(Value::Literal(42)),
+                per_thread: true
+            }
+        );
+        let code = random.to_goose_sharing("RANDOM_0");
+        assert!(code.contains("let (min, max): (i64, i64) = (1, 100);"));
+        assert!(code.contains("let seed: Option<i64> = Some(42);"));
+        assert!(code.contains(r#".get("__random_rand")"#));
+        assert!(code.contains(r#"vars.insert("rand".to_string(), format!("USER_{:03}", value));"#));
+
+        // Bounds and seeds read at run time, and negative seeds
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/config_element/random_variable_config.xml")
+                .unwrap()
+                .replace(">100<", ">${__P(max,10)}<")
+                .replace(">1<", ">${min}<")
+                .replace(">42<", ">-42<")
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let random = RandomVariableConfig::parse(&xml).unwrap();
+        assert_eq!(
+            random.maximum_value,
+            Value::Expression(String::from("${__P(max,10)}"))
+        );
+        assert_eq!(
+            random.minimum_value,
+            Value::Expression(String::from("${min}"))
+        );
+        assert_eq!(random.random_seed, Some(Value::Literal(-42)));
+        assert!(random.to_goose_sharing("RANDOM_0").contains(
+            r#"let (min, max): (i64, i64) = (whole_number(&substitute("${min}", vars), props).unwrap_or(0), whole_number(&substitute("${__P(max,10)}", vars), props).unwrap_or(0));"#
+        ));
+    }
+
+    #[test]
+    fn check_parse_counter_config() {
+        use crate::CounterConfig;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/config_element/counter_config.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let counter = CounterConfig::parse(&xml).unwrap();
+        assert_eq!(
+            CounterConfig::parse(&counter.to_element()).unwrap(),
+            counter
+        );
+        assert_eq!(
+            counter,
+            CounterConfig {
+                test_name: String::from("Counter"),
+                enabled: true,
+                name: String::from("id"),
+                start: Value::Literal(1),
+                end: Some(Value::Literal(100)),
+                incr: Value::Literal(1),
+                format: String::from("000"),
+                per_user: false,
+                reset_on_tg_iteration: false
+            }
+        );
+        assert_eq!(
+            counter.to_goose_sharing("COUNTER_0"),
+            r#"{
+    let (start, end, incr): (i64, i64, i64) = (1, 100, 1);
+    let n = COUNTER_0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    // A counter that doesn't move stays at its start
+    let steps = end.saturating_sub(start).checked_div(incr).map_or(0, |steps| steps.max(0));
+    let value: i64 = start + n % steps.saturating_add(1) * incr;
+    vars.insert("id".to_string(), format!("{:03}", value));
+}"#
+        );
+        assert_eq!(counter.reset_to_goose(), None);
+
+        // A blank increment is JMeter's default of 1, a blank end no end
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/config_element/counter_config.xml")
+                .unwrap()
+                .replace(">100<", "><")
+                .replace(
+                    r#"<stringProp name="CounterConfig.incr">1<"#,
+                    r#"<stringProp name="CounterConfig.incr"><"#,
+                )
+                .replace(">1<", ">${first}<")
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let counter = CounterConfig::parse(&xml).unwrap();
+        assert_eq!(counter.start, Value::Expression(String::from("${first}")));
+        assert_eq!(counter.end, None);
+        assert_eq!(counter.incr, Value::Literal(1));
+        assert!(counter.to_goose_sharing("COUNTER_0").contains(
+            r#"let (start, end, incr): (i64, i64, i64) = (whole_number(&substitute("${first}", vars), props).unwrap_or(0), i64::MAX, 1);"#
+        ));
+    }
 }
@@ -0,0 +1,32 @@
+use crate::JmxElement;
+
+/// Simple Controller, which only groups its children
+/// `<GenericController>`
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "GenericController", gui = "LogicControllerGui")]
+pub struct SimpleController {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+}
+
+/// Transaction Controller
+/// `<TransactionController>`
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "TransactionController", gui = "TransactionControllerGui")]
+pub struct TransactionController {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+    /// Time the children's timers and processors as part of the transaction
+    #[jmx(prop = "TransactionController.includeTimers", default = "true")]
+    pub include_timers: bool,
+    /// Report one sample with the children as its sub-samples, rather than a
+    /// sample after theirs
+    #[jmx(prop = "TransactionController.parent", default = "false")]
+    pub parent: bool,
+}
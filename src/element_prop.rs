@@ -1,8 +1,61 @@
-use crate::{Deserializer, Pairable, StringProp};
-use xmltree::Element;
+use crate::{
+    BoolProp, Deserializer, ElementProp, FromJmx, FromProperty, JmxElement, Pairable, Property,
+    PropertyBag, Serializer, StringProp, ToProperty, Value,
+};
+use anyhow::{anyhow, bail};
+use std::path::{Path, PathBuf};
+use xmltree::{Element, XMLNode};
+
+/// `<elementProp name elementType>` holding `props`
+pub(crate) fn element_prop(name: &str, element_type: &str, props: Vec<Element>) -> Element {
+    let mut e = Element::new("elementProp");
+    e.attributes.insert(String::from("name"), name.to_string());
+    e.attributes
+        .insert(String::from("elementType"), element_type.to_string());
+    e.children = props.into_iter().map(XMLNode::Element).collect();
+    e
+}
+
+/// Fails unless `e` is an `<elementProp>` of one of `types`, the way
+/// [`JmxElement::expect_tag`] checks test elements.
+pub(crate) fn expect_element_type(e: &Element, types: &[&str]) -> anyhow::Result<()> {
+    let element_type = e.attributes.get("elementType").map_or("", String::as_str);
+    if e.name != "elementProp" || !types.contains(&element_type) {
+        bail!(
+            "expected <elementProp elementType=\"{}\">, found <{} elementType={element_type:?}>",
+            types.join("|"),
+            e.name
+        );
+    }
+    Ok(())
+}
+
+fn string_prop(name: &str, value: &str) -> Element {
+    StringProp {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+    .to_element()
+}
+
+/// The `<elementProp elementType="Arguments">` JMeter nests in test plans and
+/// HTTP samplers around an `Arguments.arguments` collection.
+pub(crate) fn arguments_prop(name: &str, gui_class: &str, arguments: Element) -> Element {
+    let mut e = element_prop(name, "Arguments", vec![arguments]);
+    for (name, value) in [
+        ("guiclass", gui_class),
+        ("testclass", "Arguments"),
+        ("testname", "User Defined Variables"),
+        ("enabled", "true"),
+    ] {
+        e.attributes.insert(name.to_string(), value.to_string());
+    }
+    e
+}
 
 /// `<elementProp elementType="Header">`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElementHeader {
     pub name: String,
     pub value: String,
@@ -42,8 +95,73 @@ impl Deserializer for ElementHeader {
     }
 }
 
+impl Serializer for ElementHeader {
+    fn to_element(&self) -> Element {
+        element_prop(
+            &self.name,
+            "Header",
+            vec![
+                string_prop("Header.name", &self.name),
+                string_prop("Header.value", &self.value),
+            ],
+        )
+    }
+}
+
+impl TryFrom<&Property> for ElementHeader {
+    type Error = anyhow::Error;
+
+    fn try_from(p: &Property) -> anyhow::Result<Self> {
+        let e = p
+            .as_element()
+            .ok_or_else(|| anyhow!("expected a Header elementProp, found {p:?}"))?;
+        Ok(Self {
+            name: e.get_str("Header.name")?,
+            value: e.get_str("Header.value")?,
+        })
+    }
+}
+
+/// `<elementProp elementType="StaticHost">`, a host entry of the DNS Cache
+/// Manager
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElementStaticHost {
+    pub name: String,
+    /// One or more addresses, separated by commas
+    pub address: String,
+}
+
+impl Serializer for ElementStaticHost {
+    fn to_element(&self) -> Element {
+        element_prop(
+            &self.name,
+            "StaticHost",
+            vec![
+                string_prop("StaticHost.Name", &self.name),
+                string_prop("StaticHost.Address", &self.address),
+            ],
+        )
+    }
+}
+
+impl TryFrom<&Property> for ElementStaticHost {
+    type Error = anyhow::Error;
+
+    fn try_from(p: &Property) -> anyhow::Result<Self> {
+        let e = p
+            .as_element()
+            .ok_or_else(|| anyhow!("expected a StaticHost elementProp, found {p:?}"))?;
+        Ok(Self {
+            name: e.get_str("StaticHost.Name")?,
+            address: e.get_str("StaticHost.Address")?,
+        })
+    }
+}
+
 /// `<elementProp elementType="Argument">`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElementArgument {
     pub name: String,
     pub value: String,
@@ -56,79 +174,268 @@ impl Pairable for ElementArgument {
     }
 }
 
-impl Deserializer for ElementArgument {
-    fn parse(e: &Element) -> Self {
-        assert_eq!(e.name.as_str(), "elementProp");
-        assert_eq!(
-            e.attributes.get("elementType").unwrap().as_str(),
-            "Argument"
-        );
-        assert_eq!(e.children.len(), 3);
-        let props = e
-            .children
-            .iter()
-            .map(|x| x.as_element())
-            .filter(|x| x.is_some())
-            .map(|x| StringProp::parse(x.unwrap()))
-            .collect::<Vec<StringProp>>();
-        let name = props
-            .iter()
-            .find(|p| p.name == "Argument.name")
-            .unwrap()
-            .value
-            .clone();
-        let value = props
-            .iter()
-            .find(|p| p.name == "Argument.value")
-            .unwrap()
-            .value
-            .clone();
-        let metadata = props
-            .iter()
-            .find(|p| p.name == "Argument.metadata")
-            .unwrap()
-            .value
-            .clone();
-        Self {
-            name,
-            value,
-            metadata,
+/// The raw body of an HTTP sampler is an argument without a name, which
+/// takes `prop_name`, the name of its `<elementProp>`.
+fn argument<B: PropertyBag + ?Sized>(bag: &B, prop_name: &str) -> anyhow::Result<ElementArgument> {
+    Ok(ElementArgument {
+        name: String::from_property(bag, "Argument.name", Some(prop_name))?,
+        value: bag.get_str("Argument.value")?,
+        metadata: String::from_property(bag, "Argument.metadata", Some("="))?,
+    })
+}
+
+impl FromJmx for ElementArgument {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        argument(e, "")
+    }
+
+    /// HTTP samplers use `HTTPArgument`, which adds encoding boolProps.
+    fn parse(e: &Element) -> anyhow::Result<Self> {
+        expect_element_type(e, &["Argument", "HTTPArgument"])?;
+        Self::try_from(&Property::parse(e))
+    }
+}
+
+impl Serializer for ElementArgument {
+    fn to_element(&self) -> Element {
+        element_prop(
+            &self.name,
+            "Argument",
+            vec![
+                string_prop("Argument.name", &self.name),
+                string_prop("Argument.value", &self.value),
+                string_prop("Argument.metadata", &self.metadata),
+            ],
+        )
+    }
+}
+
+impl ElementArgument {
+    /// The `elementType="HTTPArgument"` form HTTP samplers save, which adds
+    /// the encoding flags JMeter's GUI writes.
+    pub(crate) fn to_http_element(&self) -> Element {
+        element_prop(
+            &self.name,
+            "HTTPArgument",
+            vec![
+                BoolProp {
+                    name: String::from("HTTPArgument.always_encode"),
+                    value: false,
+                }
+                .to_element(),
+                string_prop("Argument.value", &self.value),
+                string_prop("Argument.metadata", &self.metadata),
+                BoolProp {
+                    name: String::from("HTTPArgument.use_equals"),
+                    value: true,
+                }
+                .to_element(),
+                string_prop("Argument.name", &self.name),
+            ],
+        )
+    }
+}
+
+impl TryFrom<&Property> for ElementArgument {
+    type Error = anyhow::Error;
+
+    fn try_from(p: &Property) -> anyhow::Result<Self> {
+        let e = p
+            .as_element()
+            .ok_or_else(|| anyhow!("expected an Argument elementProp, found {p:?}"))?;
+        argument(e, &e.name)
+    }
+}
+
+/// `<elementProp elementType="HTTPFileArg">`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElementHttpFileArg {
+    pub path: String,
+    pub param_name: String,
+    pub mime_type: String,
+}
+
+impl ElementHttpFileArg {
+    /// JMeter reads paths holding a `${...}` reference from wherever they
+    /// point when the sampler runs, so there is no fixture to copy.
+    pub fn is_read_at_run_time(&self) -> bool {
+        self.path.contains("${")
+    }
+
+    /// Where the file lives in the generated project, keeping the directory
+    /// layout of relative paths. Absolute paths go under `fixtures/absolute`,
+    /// a drive letter becoming a directory. Each `..` that climbs above the
+    /// .jmx directory becomes a `parent` directory. JMeter plans saved on
+    /// Windows use `\`. Paths naming no file are an error.
+    pub fn fixture_path(&self) -> std::io::Result<PathBuf> {
+        let path = self.path.replace('\\', "/");
+        let mut fixture = PathBuf::from("fixtures");
+        let (absolute, relative) = match path.as_bytes() {
+            [drive, b':', ..] if drive.is_ascii_alphabetic() => {
+                fixture.push("absolute");
+                fixture.push(&path[..1]);
+                (true, &path[2..])
+            }
+            [b'/', ..] => {
+                fixture.push("absolute");
+                (true, &path[1..])
+            }
+            _ => (false, path.as_str()),
+        };
+        let mut components: Vec<&str> = vec![];
+        for component in relative.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => match components.last() {
+                    Some(&last) if last != ".." => {
+                        components.pop();
+                    }
+                    // Nothing is above the root
+                    _ if absolute => {}
+                    _ => components.push(".."),
+                },
+                name => components.push(name),
+            }
         }
+        match (relative.rsplit('/').next(), components.last()) {
+            (Some(name), Some(&last)) if !matches!(name, "" | "." | "..") && last != ".." => {
+                fixture.extend(
+                    components
+                        .iter()
+                        .map(|c| if *c == ".." { "parent" } else { c }),
+                );
+                Ok(fixture)
+            }
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("upload file {:?} names no file", self.path),
+            )),
+        }
+    }
+
+    /// The file, resolved relative to the directory of the .jmx like JMeter
+    /// does.
+    pub fn source_path(&self, jmx_dir: &Path) -> PathBuf {
+        jmx_dir.join(self.path.replace('\\', "/"))
+    }
+
+    /// Copies the [`Self::source_path`] into `out_dir` at its
+    /// [`Self::fixture_path`].
+    pub fn copy_fixture(&self, jmx_dir: &Path, out_dir: &Path) -> std::io::Result<()> {
+        if self.is_read_at_run_time() {
+            return Ok(());
+        }
+        let target = out_dir.join(self.fixture_path()?);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(self.source_path(jmx_dir), target)?;
+        Ok(())
+    }
+}
+
+fn file_arg<B: PropertyBag + ?Sized>(bag: &B) -> anyhow::Result<ElementHttpFileArg> {
+    Ok(ElementHttpFileArg {
+        path: bag.get_str("File.path")?,
+        param_name: bag.get_str("File.paramname")?,
+        mime_type: bag.get_str("File.mimetype")?,
+    })
+}
+
+impl FromJmx for ElementHttpFileArg {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        file_arg(e)
+    }
+
+    fn parse(e: &Element) -> anyhow::Result<Self> {
+        expect_element_type(e, &["HTTPFileArg"])?;
+        Self::try_from(&Property::parse(e))
+    }
+}
+
+impl TryFrom<&Property> for ElementHttpFileArg {
+    type Error = anyhow::Error;
+
+    fn try_from(p: &Property) -> anyhow::Result<Self> {
+        let e = p
+            .as_element()
+            .ok_or_else(|| anyhow!("expected an HTTPFileArg elementProp, found {p:?}"))?;
+        file_arg(e)
+    }
+}
+
+impl Serializer for ElementHttpFileArg {
+    fn to_element(&self) -> Element {
+        element_prop(
+            &self.path,
+            "HTTPFileArg",
+            vec![
+                string_prop("File.mimetype", &self.mime_type),
+                string_prop("File.path", &self.path),
+                string_prop("File.paramname", &self.param_name),
+            ],
+        )
     }
 }
 
 /// `<elementProp elementType="LoopController">`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElementLoopController {
-    pub loops: i32,
+    pub loops: Value<i32>,
 }
 
-impl Deserializer for ElementLoopController {
-    fn parse(e: &Element) -> Self {
-        assert_eq!(e.name.as_str(), "elementProp");
-        assert_eq!(
-            e.attributes.get("elementType").unwrap().as_str(),
-            "LoopController"
+/// `LoopController.loops` may be an `<intProp>`, as newer JMeter saves it,
+/// a `<stringProp>` or an expression.
+fn loop_controller<B: PropertyBag + ?Sized>(bag: &B) -> anyhow::Result<ElementLoopController> {
+    Ok(ElementLoopController {
+        loops: Value::from_property(bag, "LoopController.loops", None)?,
+    })
+}
+
+impl FromJmx for ElementLoopController {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        loop_controller(e)
+    }
+
+    fn parse(e: &Element) -> anyhow::Result<Self> {
+        expect_element_type(e, &["LoopController"])?;
+        Self::try_from(&ElementProp::parse(e))
+    }
+}
+
+impl TryFrom<&ElementProp> for ElementLoopController {
+    type Error = anyhow::Error;
+
+    fn try_from(e: &ElementProp) -> anyhow::Result<Self> {
+        loop_controller(e)
+    }
+}
+
+impl Serializer for ElementLoopController {
+    fn to_element(&self) -> Element {
+        let mut e = element_prop(
+            "ThreadGroup.main_controller",
+            "LoopController",
+            vec![
+                BoolProp {
+                    name: String::from("LoopController.continue_forever"),
+                    value: false,
+                }
+                .to_element(),
+                self.loops.to_property("LoopController.loops").to_element(),
+            ],
         );
-        let children = e
-            .children
-            .iter()
-            .map(|c| c.as_element())
-            .filter(|c| c.is_some())
-            .map(|c| c.unwrap())
-            .collect::<Vec<&Element>>();
-        let loops = children
-            .iter()
-            .find(|c| c.attributes.get("name").unwrap() == "LoopController.loops")
-            .unwrap()
-            .children
-            .first()
-            .unwrap()
-            .as_text()
-            .unwrap()
-            .parse::<i32>()
-            .unwrap();
-        Self { loops }
+        for (name, value) in [
+            ("guiclass", "LoopControlPanel"),
+            ("testclass", "LoopController"),
+            ("testname", "Loop Controller"),
+            ("enabled", "true"),
+        ] {
+            e.attributes.insert(name.to_string(), value.to_string());
+        }
+        e
     }
 }
 
@@ -159,7 +466,7 @@ mod test {
 
     #[test]
     fn check_parse_element_argument() {
-        use crate::ElementArgument;
+        use crate::{ElementArgument, FromJmx};
 
         let xml = Element::parse(
             std::fs::read_to_string("./unittests_data/element_prop/element_argument.xml")
@@ -169,18 +476,101 @@ mod test {
         )
         .unwrap();
         assert_eq!(
-            ElementArgument::parse(&xml),
+            ElementArgument::parse(&xml).unwrap(),
             ElementArgument {
                 name: String::from("protocol"),
                 value: String::from("https"),
                 metadata: String::from("="),
             }
+        );
+
+        // A number saved as `<intProp>` is still text, a header is no argument
+        let mut xml = xml;
+        for child in xml.children.iter_mut().filter_map(|c| c.as_mut_element()) {
+            if child.attributes["name"] == "Argument.value" {
+                child.name = String::from("intProp");
+                child.children = vec![xmltree::XMLNode::Text(String::from("443"))];
+            }
+        }
+        assert_eq!(ElementArgument::parse(&xml).unwrap().value, "443");
+        xml.attributes
+            .insert(String::from("elementType"), String::from("Header"));
+        assert!(ElementArgument::parse(&xml)
+            .unwrap_err()
+            .to_string()
+            .contains(r#"found <elementProp elementType="Header">"#));
+    }
+
+    #[test]
+    fn check_parse_element_http_file_arg() {
+        use crate::{ElementHttpFileArg, FromJmx};
+        use std::path::Path;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/element_prop/element_http_file_arg.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
         )
+        .unwrap();
+        let file = ElementHttpFileArg::parse(&xml).unwrap();
+        assert_eq!(
+            file,
+            ElementHttpFileArg {
+                path: String::from("data/avatar.png"),
+                param_name: String::from("avatar"),
+                mime_type: String::from("image/png"),
+            }
+        );
+        assert_eq!(
+            file.fixture_path().unwrap(),
+            Path::new("fixtures/data/avatar.png")
+        );
+
+        let fixture = |path: &str| {
+            ElementHttpFileArg {
+                path: path.to_string(),
+                ..file.clone()
+            }
+            .fixture_path()
+        };
+        assert_eq!(
+            fixture("/home/qa/a.png").unwrap(),
+            Path::new("fixtures/absolute/home/qa/a.png")
+        );
+        assert_eq!(
+            fixture("/tmp/a.png").unwrap(),
+            Path::new("fixtures/absolute/tmp/a.png")
+        );
+        assert_eq!(
+            fixture(r"C:\x\y.png").unwrap(),
+            Path::new("fixtures/absolute/C/x/y.png")
+        );
+        assert_eq!(
+            fixture("./data/./a.png").unwrap(),
+            Path::new("fixtures/data/a.png")
+        );
+        // Climbing above the .jmx directory, and above the root
+        assert_eq!(
+            fixture("../shared/a.png").unwrap(),
+            Path::new("fixtures/parent/shared/a.png")
+        );
+        assert_eq!(
+            fixture("data/../../../a.png").unwrap(),
+            Path::new("fixtures/parent/parent/a.png")
+        );
+        assert_eq!(
+            fixture("C:\\..\\a.png").unwrap(),
+            Path::new("fixtures/absolute/C/a.png")
+        );
+        for path in ["data/..", "data/", "", "."] {
+            assert!(fixture(path).is_err(), "{path:?}");
+        }
     }
 
     #[test]
     fn check_parse_element_loop_controller() {
-        use crate::ElementLoopController;
+        use crate::{ElementLoopController, FromJmx, Value};
 
         let xml = Element::parse(
             std::fs::read_to_string("./unittests_data/element_prop/element_loop_controller.xml")
@@ -190,8 +580,18 @@ mod test {
         )
         .unwrap();
         assert_eq!(
-            ElementLoopController::parse(&xml),
-            ElementLoopController { loops: 1 }
+            ElementLoopController::parse(&xml).unwrap(),
+            ElementLoopController {
+                loops: Value::Literal(1)
+            }
+        );
+
+        let malformed =
+            |xml: &str| ElementLoopController::parse(&Element::parse(xml.as_bytes()).unwrap());
+        assert!(malformed(r#"<elementProp name="ThreadGroup.main_controller"/>"#).is_err());
+        assert!(malformed(
+            r#"<elementProp name="ThreadGroup.main_controller" elementType="LoopController"><collectionProp name="LoopController.loops"/></elementProp>"#
         )
+        .is_err());
     }
 }
@@ -0,0 +1,1870 @@
+//! A Goose load test crate generated from a [`LoadTest`].
+//!
+//! Every scenario gets an on-start transaction setting up the user's JMeter
+//! variables and an iteration transaction running its steps in order, each
+//! step in its own `async fn`. Response timeouts apply per request, while
+//! the on-start transaction gives each user a client with the connect
+//! timeout, the DNS Cache Manager's static hosts and the Keystore
+//! Configuration's client certificate, which reqwest only sets per client.
+//! Requests that don't follow redirects go out through Goose on a second
+//! client with the same settings and cookies. The keystore is named at run
+//! time with `-Djavax.net.ssl.keyStore=PATH`, as for JMeter.
+//!
+//! Requests retrieving embedded resources parse HTML responses for them and
+//! fetch them after the page, through the user so that Goose records them,
+//! or in parallel on the user's client when the sampler fetches
+//! concurrently.
+//!
+//! Scenarios are weighted by their thread groups' users. With
+//! `TestPlan.serialize_threadgroups` all users start at once, and each
+//! scenario's take turns so that only as many of them run as its thread
+//! group would in its phase.
+//!
+//! JMeter properties start out as [`LoadTest::properties`]. The generated
+//! binary takes JMeter's `-Jname=value` and `--addprop FILE` options besides
+//! Goose's own, so a plan reading `${__P(threads,10)}` can be run with other
+//! settings without converting it again.
+//!
+//! Plans with an Ultimate, Concurrency or Stepping Thread Group run as a
+//! Goose test plan of user counts over time, every thread group contributing
+//! its own schedule, instead of a single user count and hatch rate.
+//!
+//! The iterations of an Arrivals Thread Group are paced to start at its
+//! target rate. Goose's users are a closed model though, running one
+//! iteration after another, so the rate holds only while enough of them are
+//! free. The generated test warns when iterations start late.
+
+use crate::sampler::multipart_form;
+use crate::{
+    DataSource, GooseCodegen, Load, LoadTest, OnError, PostProcessor, Profile, Request, Scenario,
+    StaticHost, Step, Value, Wait,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// The std-only helpers of the prelude, compiled into this crate too for
+/// their unit tests. The file has no imports of its own, using the prelude's.
+const RUN_TIME: &str = include_str!("goose/run_time.rs");
+
+/// Support code at the top of every generated `main.rs`.
+const PRELUDE: &str = r#"#![allow(unused_variables, unused_mut, dead_code)]
+
+use goose::prelude::*;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+/// JMeter properties, shared by all users
+static PROPS: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(default_properties()));
+
+/// Java system properties, which JMeter functions don't read
+static SYSTEM_PROPS: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(default_system_properties()));
+
+/// A user's JMeter variables, its number among its thread group's users,
+/// and its client for the requests JMeter sends without following redirects
+struct Session {
+    vars: HashMap<String, String>,
+    thread: usize,
+    no_redirects: Option<reqwest::Client>,
+}
+
+/// The user's number among its thread group's users, from 0 in the order
+/// they start, as JMeter numbers a thread group's threads.
+fn thread_number(user: &GooseUser) -> usize {
+    user.get_session_data::<Session>().map_or(0, |session| session.thread)
+}
+
+/// Replaces `${name}` references to known variables.
+fn substitute(value: &str, vars: &HashMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) if vars.contains_key(&after[..end]) => {
+                result.push_str(&vars[&after[..end]]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                result.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// A timer delay or timeout in milliseconds.
+fn millis(value: &str, vars: &HashMap<String, String>) -> f64 {
+    substitute(value, vars).trim().parse().unwrap_or(0.0)
+}
+
+/// A whole number a config element reads at run time from `value`, its
+/// variables already substituted, `None` when it isn't one.
+fn whole_number(value: &str, props: &HashMap<String, String>) -> Option<i64> {
+    read_properties_in(value, props).trim().parse().ok()
+}
+
+/// A JMeter property, like `${__P(name,default)}`.
+fn property(name: &str, default: &str) -> String {
+    PROPS.lock().unwrap().get(name).cloned().unwrap_or_else(|| default.to_string())
+}
+
+/// A Java system property, like JMeter's `javax.net.ssl.keyStore`.
+fn system_property(name: &str, default: &str) -> String {
+    SYSTEM_PROPS.lock().unwrap().get(name).cloned().unwrap_or_else(|| default.to_string())
+}
+
+/// Replaces `${__P(name,default)}` and `${__property(name,,default)}` calls
+/// by the JMeter properties they read.
+fn read_properties(value: &str) -> String {
+    read_properties_in(value, &PROPS.lock().unwrap())
+}
+
+/// Applies JMeter's `-Jname=value`, `--jmeterproperty name=value` and
+/// `--addprop FILE` options in order, and system properties from
+/// `-Dname=value` and `--systemproperty name=value`, returning the other
+/// arguments.
+fn jmeter_options() -> Vec<String> {
+    const OPTIONS: [&str; 5] = ["-J", "--jmeterproperty", "-D", "--systemproperty", "--addprop"];
+    let mut props = PROPS.lock().unwrap();
+    let mut system_props = SYSTEM_PROPS.lock().unwrap();
+    let mut goose = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let (option, attached) = match arg.split_once('=') {
+            Some((option @ ("--jmeterproperty" | "--systemproperty" | "--addprop"), value)) => {
+                (option, Some(value))
+            }
+            _ if arg.starts_with("-J") && arg.len() > 2 => ("-J", Some(&arg[2..])),
+            _ if arg.starts_with("-D") && arg.len() > 2 => ("-D", Some(&arg[2..])),
+            _ => (arg.as_str(), None),
+        };
+        if !OPTIONS.contains(&option) {
+            goose.push(arg);
+            continue;
+        }
+        let Some(value) = attached.map(str::to_string).or_else(|| args.next()) else {
+            eprintln!("{option} needs a value");
+            std::process::exit(2);
+        };
+        if option == "--addprop" {
+            match std::fs::read_to_string(&value) {
+                Ok(text) => props.extend(parse_properties(&text)),
+                Err(e) => {
+                    eprintln!("{value}: {e}");
+                    std::process::exit(2);
+                }
+            }
+        } else {
+            let (name, value) = value.split_once('=').unwrap_or((&value, ""));
+            let props = match option {
+                "-D" | "--systemproperty" => &mut *system_props,
+                _ => &mut *props,
+            };
+            props.insert(name.to_string(), value.to_string());
+        }
+    }
+    goose
+}
+
+/// Goose's configuration from the arguments [`jmeter_options`] leaves,
+/// `None` when only help was asked for.
+fn configuration() -> Option<goose::config::GooseConfiguration> {
+    use gumdrop::Options;
+
+    let args = jmeter_options();
+    let configuration = goose::config::GooseConfiguration::parse_args_default(&args)
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        });
+    if configuration.help_requested() {
+        println!(
+            "{}\n\nJMeter properties:\n  -Jname=value, --jmeterproperty name=value\n  --addprop FILE     a .properties file\n\nSystem properties:\n  -Dname=value, --systemproperty name=value",
+            goose::config::GooseConfiguration::usage()
+        );
+        return None;
+    }
+    Some(configuration)
+}
+
+/// `configuration` running `iterations` per user unless its command line
+/// already limits the test, Goose 0.17 taking the command line's 0 over
+/// `GooseDefault::Iterations`.
+fn with_iterations(
+    mut configuration: goose::config::GooseConfiguration,
+    iterations: usize,
+) -> goose::config::GooseConfiguration {
+    let test_plan = std::env::args().any(|a| a.starts_with("--test-plan"));
+    if configuration.iterations == 0 && configuration.run_time.is_empty() && !test_plan {
+        configuration.iterations = iterations;
+    }
+    configuration
+}
+
+/// Iterations of a thread group paced to start at a target rate, shared by
+/// its users. Goose users run one iteration after another, so an iteration
+/// due while all of them are busy starts late.
+struct Pacing {
+    started: std::time::Instant,
+    arrivals: Mutex<usize>,
+    late: std::sync::atomic::AtomicBool,
+}
+
+impl Pacing {
+    fn new() -> Self {
+        Self {
+            started: std::time::Instant::now(),
+            arrivals: Mutex::new(0),
+            late: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    /// Waits until the next [`arrival`] is due, `false` once they are over.
+    async fn wait(&self, scenario: &str, rate: f64, ramp_up: f64, steps: usize, hold: f64) -> bool {
+        let n = {
+            let mut arrivals = self.arrivals.lock().unwrap();
+            *arrivals += 1;
+            *arrivals - 1
+        };
+        let Some(due) = arrival(n as f64, rate, ramp_up, steps, hold) else {
+            return false;
+        };
+        let due = self.started + std::time::Duration::from_secs_f64(due);
+        let now = std::time::Instant::now();
+        if due > now {
+            tokio::time::sleep_until(due.into()).await;
+        } else if now - due > std::time::Duration::from_secs(1)
+            && !self.late.swap(true, std::sync::atomic::Ordering::Relaxed)
+        {
+            log::warn!(
+                "{scenario}: iterations start late, all users being busy; \
+                 run with more --users to reach the target rate"
+            );
+        }
+        true
+    }
+}
+
+/// The [`test_plan`] rows of each thread group, shifted to run one after
+/// another as [`serial`] sets them up
+static PHASES: std::sync::OnceLock<Vec<Vec<[usize; 5]>>> = std::sync::OnceLock::new();
+
+/// When the thread groups started taking turns
+static STARTED: LazyLock<std::time::Instant> = LazyLock::new(std::time::Instant::now);
+
+/// Thread groups given as [`test_plan`] rows running one after another, as
+/// with `TestPlan.serialize_threadgroups`. Each starts when the rows of the
+/// one before have ended, or once they have all started when some are held
+/// until the end. Returns the users of all of them, which start at once and
+/// take [`turn`]s, and the run time, 0 when the last group has no end.
+fn serial(groups: Vec<Vec<[usize; 5]>>) -> (usize, usize) {
+    let mut start = 0;
+    let mut users = 0;
+    let mut phases = vec![];
+    for group in groups {
+        let rows = group
+            .iter()
+            .map(|&[users, delay, startup, hold, shutdown]| {
+                [users, start + delay, startup, hold, shutdown]
+            })
+            .collect::<Vec<_>>();
+        users += rows.iter().map(|row| row[0]).sum::<usize>();
+        start = if rows.iter().any(|row| row[3] == usize::MAX) {
+            rows.iter().map(|row| row[1] + row[2]).max()
+        } else {
+            rows.iter().map(|row| row.iter().skip(1).sum()).max()
+        }
+        .unwrap_or(start);
+        phases.push(rows);
+    }
+    let open = phases
+        .last()
+        .is_some_and(|rows| rows.iter().any(|row| row[3] == usize::MAX));
+    let _ = PHASES.set(phases);
+    LazyLock::force(&STARTED);
+    (users, if open { 0 } else { start })
+}
+
+/// A user's turn to run an iteration of its thread group, over when
+/// dropped.
+struct Turn(&'static std::sync::atomic::AtomicUsize);
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// A turn for one of the `busy` users of the `group`th of the [`serial`]
+/// thread groups, while fewer of them are busy than its rows have running.
+fn turn(group: usize, busy: &'static std::sync::atomic::AtomicUsize) -> Option<Turn> {
+    let rows = PHASES.get()?.get(group)?;
+    let running = running(rows, STARTED.elapsed().as_secs_f64());
+    busy.fetch_update(
+        std::sync::atomic::Ordering::SeqCst,
+        std::sync::atomic::Ordering::SeqCst,
+        |busy| (busy < running).then_some(busy + 1),
+    )
+    .ok()
+    .map(|_| Turn(busy))
+}
+
+/// The User-Agent of Goose's own clients
+const GOOSE_USER_AGENT: &str = "goose/0.17.2";
+
+/// How a user's clients differ from the one Goose gives it. reqwest only
+/// sets these per client.
+#[derive(Default)]
+struct ClientSettings {
+    static_hosts: &'static [(&'static str, &'static [&'static str])],
+    /// 0 for none
+    connect_timeout_ms: u64,
+    identity: Option<reqwest::Identity>,
+}
+
+impl ClientSettings {
+    /// A builder of a client like Goose's, with its User-Agent, gzip and 60
+    /// second timeout, keeping cookies in `jar`.
+    fn builder(&self, jar: &std::sync::Arc<reqwest::cookie::Jar>) -> reqwest::ClientBuilder {
+        let builder = reqwest::Client::builder()
+            .user_agent(GOOSE_USER_AGENT)
+            .cookie_provider(jar.clone())
+            .gzip(true)
+            .timeout(std::time::Duration::from_secs(60));
+        let builder = static_hosts(builder, self.static_hosts);
+        let builder = match self.connect_timeout_ms {
+            0 => builder,
+            timeout => builder.connect_timeout(std::time::Duration::from_millis(timeout)),
+        };
+        match &self.identity {
+            Some(identity) => builder.identity(identity.clone()),
+            None => builder,
+        }
+    }
+}
+
+/// Sends `request` on the user's client that doesn't follow redirects, for
+/// Goose to record like any other. Redirects count as successes, as in
+/// JMeter.
+async fn request_without_redirects(
+    user: &mut GooseUser,
+    request: GooseRequest<'_>,
+) -> Result<goose::goose::GooseResponse, Box<TransactionError>> {
+    let no_redirects = user
+        .get_session_data::<Session>()
+        .and_then(|session| session.no_redirects.clone())
+        .expect("a client without redirects");
+    let client = std::mem::replace(&mut user.client, no_redirects);
+    let goose = user.request(request).await;
+    user.client = client;
+    let mut goose = goose?;
+    if goose.request.status_code / 100 == 3 {
+        user.set_success(&mut goose.request)?;
+    }
+    Ok(goose)
+}
+
+/// Resolves each host name to its addresses, IPs or host names the system
+/// looks up now. A name none of whose addresses resolve is left to the
+/// system's DNS.
+fn static_hosts(builder: reqwest::ClientBuilder, hosts: &[(&str, &[&str])]) -> reqwest::ClientBuilder {
+    hosts.iter().fold(builder, |builder, (name, addresses)| {
+        // reqwest connects to the request's port, whatever the address's
+        let addrs = addresses
+            .iter()
+            .filter_map(|address| std::net::ToSocketAddrs::to_socket_addrs(&(*address, 0)).ok())
+            .flatten()
+            .collect::<Vec<_>>();
+        if addrs.is_empty() {
+            log::warn!("{name}: none of {addresses:?} resolves");
+            builder
+        } else {
+            builder.resolve_to_addrs(name, &addrs)
+        }
+    })
+}
+
+/// The client certificate of keystore `alias`, or of the one at `index` when
+/// `alias` is empty. `javax.net.ssl.keyStore` names a PKCS#12 (`.p12`,
+/// `.pfx`) or PEM file every user presents, or a directory of them, named
+/// after their aliases and sorted. PKCS#12 files are opened with
+/// `javax.net.ssl.keyStorePassword`.
+fn client_identity(alias: &str, index: usize) -> Result<Option<reqwest::Identity>, String> {
+    let keystore = system_property("javax.net.ssl.keyStore", "");
+    if keystore.is_empty() {
+        return Ok(None);
+    }
+    let mut path = std::path::PathBuf::from(&keystore);
+    if path.is_dir() {
+        let mut files = std::fs::read_dir(&path)
+            .map_err(|e| format!("{keystore}: {e}"))?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|file| {
+                let extension = file.extension().and_then(|e| e.to_str()).unwrap_or_default();
+                ["p12", "pfx", "pem"].contains(&extension.to_ascii_lowercase().as_str())
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        path = if alias.is_empty() {
+            files.get(index).cloned().ok_or_else(|| {
+                format!("{keystore}: no certificate at index {index} of {}", files.len())
+            })?
+        } else {
+            files
+                .into_iter()
+                .find(|file| file.file_stem().and_then(|s| s.to_str()) == Some(alias))
+                .ok_or_else(|| format!("{keystore}: no certificate for alias {alias:?}"))?
+        };
+    }
+    let bytes = std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let identity = match extension.to_ascii_lowercase().as_str() {
+        "p12" | "pfx" => reqwest::Identity::from_pkcs12_der(
+            &bytes,
+            &system_property("javax.net.ssl.keyStorePassword", ""),
+        ),
+        // The certificate chain and the PKCS#8 key, in one file
+        _ => reqwest::Identity::from_pkcs8_pem(&bytes, &bytes),
+    };
+    identity.map(Some).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The http(s) URLs of the resources an HTML response embeds, as JMeter's
+/// parser finds them, resolved against the page's URL or its `<base>`.
+/// Only those whose whole URL matches `filter` are kept.
+fn embedded_resources(headers: &str, body: &str, url: &str, filter: Option<&regex::Regex>) -> Vec<String> {
+    let html = headers.lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("content-type:") && line.contains("text/html")
+    });
+    let Ok(mut base) = reqwest::Url::parse(url) else {
+        return vec![];
+    };
+    if !html {
+        return vec![];
+    }
+    let document = scraper::Html::parse_document(body);
+    let select = |selector: &str| scraper::Selector::parse(selector).unwrap();
+    if let Some(href) = document.select(&select("base[href]")).next().and_then(|e| e.value().attr("href")) {
+        if let Ok(url) = base.join(href) {
+            base = url;
+        }
+    }
+    let sources = [
+        ("img[src], script[src], embed[src], frame[src], iframe[src], input[type=image][src]", "src"),
+        ("audio[src], video[src], source[src], bgsound[src]", "src"),
+        ("link[rel~=stylesheet][href], link[rel~=icon][href]", "href"),
+        ("body[background], table[background], td[background], tr[background]", "background"),
+        ("object[data]", "data"),
+        ("video[poster]", "poster"),
+    ]
+    .map(|(selector, attribute)| (select(selector), attribute));
+    let mut resources: Vec<String> = vec![];
+    // In document order, as JMeter fetches them
+    for element in document.select(&select("[src], [href], [background], [data], [poster]")) {
+        for (selector, attribute) in &sources {
+            let Some(value) = element.value().attr(attribute).filter(|_| selector.matches(&element)) else {
+                continue;
+            };
+            let Ok(url) = base.join(value.trim()) else {
+                continue;
+            };
+            let url = url.to_string();
+            if matches!(&url[..url.find(':').unwrap_or(0)], "http" | "https")
+                && filter.is_none_or(|filter| filter.is_match(&url))
+                && !resources.contains(&url)
+            {
+                resources.push(url);
+            }
+        }
+    }
+    resources
+}
+
+/// Fetches embedded `resources` one after another through the user, so that
+/// Goose records them, or `pool` at a time on the user's client, which Goose
+/// doesn't see.
+async fn fetch_embedded(user: &mut GooseUser, resources: Vec<String>, pool: Option<usize>) -> TransactionResult {
+    let Some(pool) = pool else {
+        for url in resources {
+            user.get(&url).await?;
+        }
+        return Ok(());
+    };
+    let permits = std::sync::Arc::new(tokio::sync::Semaphore::new(pool.max(1)));
+    let mut fetches = tokio::task::JoinSet::new();
+    for url in resources {
+        let (client, permits) = (user.client.clone(), permits.clone());
+        fetches.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            match client.get(&url).send().await {
+                Ok(response) => {
+                    let _ = response.bytes().await;
+                }
+                Err(err) => log::warn!("embedded resource {url} failed: {err}"),
+            }
+        });
+    }
+    while fetches.join_next().await.is_some() {}
+    Ok(())
+}
+
+/// `text` with the character references JMeter's unescaped scopes decode:
+/// the XML entities, `&nbsp;` and numeric references.
+fn unescape_html(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                reference => {
+                    let number = reference.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// The headers of `request` as Goose built it, one `name: value` line each.
+/// The client adds its own, cookies included, when sending it.
+fn request_headers(request: &goose::metrics::GooseRequestMetric) -> String {
+    request
+        .raw
+        .headers
+        .iter()
+        .filter_map(|header| {
+            let (name, value) = header.strip_prefix("(\"")?.strip_suffix("\")")?.split_once("\", \"")?;
+            Some(format!("{name}: {value}\n"))
+        })
+        .collect()
+}
+
+/// A standard normal deviate.
+fn gaussian() -> f64 {
+    let (u, v): (f64, f64) = (rand::random(), rand::random());
+    (-2.0 * (1.0 - u).ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+"#;
+
+/// A Rust identifier for `name`, unique among `used`.
+fn ident(name: &str, used: &mut HashSet<String>) -> String {
+    let mut ident = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .split('_')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_");
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert_str(0, "step_");
+    }
+    let mut unique = ident.clone();
+    let mut n = 2;
+    while !used.insert(unique.clone()) {
+        unique = format!("{ident}_{n}");
+        n += 1;
+    }
+    unique
+}
+
+/// Indents every non-empty line of `code` by `spaces`.
+fn indent(code: &str, spaces: usize) -> String {
+    code.lines()
+        .map(|l| {
+            if l.is_empty() {
+                String::from("\n")
+            } else {
+                format!("{:spaces$}{l}\n", "")
+            }
+        })
+        .collect()
+}
+
+/// A `usize` expression for a thread group setting, evaluating properties
+/// and the plan's variables at run time when it is not a literal.
+fn quantity<T: Display>(value: &Value<T>) -> String {
+    match (value, value.property()) {
+        (Value::Literal(value), _) => value.to_string(),
+        (_, Some((name, default))) => format!("count(&property({name:?}, {default:?}))"),
+        (Value::Expression(expression), None) => {
+            format!("count(&substitute({expression:?}, &global_variables()))")
+        }
+    }
+}
+
+/// Goose weights for the scenarios of `loads`, in proportion to their
+/// users, `None` when those are all the same. Goose takes no weight of 0.
+fn weights(loads: &[&Load]) -> Option<Vec<String>> {
+    let literals = loads
+        .iter()
+        .map(|l| l.users.literal().copied())
+        .collect::<Option<Vec<usize>>>();
+    match literals {
+        Some(users) if users.windows(2).all(|pair| pair[0] == pair[1]) => None,
+        Some(users) => {
+            let divisor = users.iter().fold(0, |a, &b| gcd(a, b)).max(1);
+            Some(
+                users
+                    .iter()
+                    .map(|users| (users / divisor).max(1).to_string())
+                    .collect(),
+            )
+        }
+        None => Some(
+            loads
+                .iter()
+                .map(|l| match l.users.literal() {
+                    Some(users) => users.max(&1).to_string(),
+                    None => format!("{}.max(1)", quantity(&l.users)),
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// The largest of `values`, [`quantity`] expressions.
+fn largest(values: &[String]) -> String {
+    match values {
+        [value] => value.clone(),
+        _ => format!("[{}].into_iter().max().unwrap_or(0)", values.join(", ")),
+    }
+}
+
+/// Code evaluating a timeout `expression` in a user's context, with the
+/// JMeter properties it reads.
+fn timeout_millis(expression: &str) -> String {
+    if expression.contains("${__") {
+        format!("millis(&read_properties({expression:?}), vars)")
+    } else {
+        format!("millis({expression:?}, vars)")
+    }
+}
+
+/// A `&[(&str, &[&str])]` of the host names and their addresses, leaving
+/// out those without any.
+fn static_hosts(hosts: &[StaticHost]) -> String {
+    format!(
+        "&[{}]",
+        hosts
+            .iter()
+            .filter(|host| !host.name.is_empty() && !host.addresses.is_empty())
+            .map(|host| format!("({:?}, &{:?})", host.name, host.addresses))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// A [`quantity`] of `unit_secs` second units in seconds.
+fn seconds(value: &Value<u64>, unit_secs: u64) -> String {
+    match (value.literal(), unit_secs) {
+        (Some(value), _) => (value * unit_secs).to_string(),
+        (None, 1) => quantity(value),
+        (None, unit_secs) => format!("{} * {unit_secs}", quantity(value)),
+    }
+}
+
+/// How long `load` runs when that is limited, as a literal when known and
+/// a [`quantity`] expression.
+fn run_time(load: &Load) -> Option<(Option<u64>, String)> {
+    match &load.arrivals {
+        Some(arrivals) => Some(
+            match (arrivals.ramp_up.literal(), arrivals.hold.literal()) {
+                (Some(ramp_up), Some(hold)) => {
+                    let secs = (ramp_up + hold) * arrivals.unit_secs;
+                    (Some(secs), secs.to_string())
+                }
+                _ => (
+                    None,
+                    format!(
+                        "{} + {}",
+                        seconds(&arrivals.ramp_up, arrivals.unit_secs),
+                        seconds(&arrivals.hold, arrivals.unit_secs)
+                    ),
+                ),
+            },
+        ),
+        None => load
+            .duration_secs
+            .as_ref()
+            .map(|duration| (duration.literal().copied(), quantity(duration))),
+    }
+}
+
+/// A `Vec<[usize; 5]>` expression for the `test_plan` rows of `load`. Other
+/// thread groups are a single row held until their run time ends, or until
+/// the other rows end without one.
+fn profile_rows(load: &Load) -> String {
+    let row = |values: [String; 5]| format!("[{}]", values.join(", "));
+    match &load.profile {
+        Some(Profile::Rows(rows)) => format!(
+            "vec![{}]",
+            rows.iter()
+                .map(|r| row([
+                    &r.users,
+                    &r.delay_secs,
+                    &r.startup_secs,
+                    &r.hold_secs,
+                    &r.shutdown_secs
+                ]
+                .map(quantity)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Some(Profile::Steps {
+            users,
+            ramp_up,
+            steps,
+            hold,
+            unit_secs,
+        }) => {
+            format!(
+                "concurrency({}, {}, {}, {})",
+                quantity(users),
+                seconds(ramp_up, *unit_secs),
+                quantity(steps),
+                seconds(hold, *unit_secs)
+            )
+        }
+        Some(Profile::Stepping {
+            users,
+            delay,
+            burst,
+            batch,
+            period,
+            ramp_up,
+            hold,
+            stop_batch,
+            stop_period,
+        }) => format!(
+            "stepping({})",
+            [
+                users,
+                delay,
+                burst,
+                batch,
+                period,
+                ramp_up,
+                hold,
+                stop_batch,
+                stop_period
+            ]
+            .map(quantity)
+            .join(", ")
+        ),
+        None => {
+            let ramp_up = quantity(&load.ramp_up_secs);
+            let hold = match (run_time(load), load.ramp_up_secs.literal()) {
+                (Some((Some(duration), _)), Some(ramp_up)) => {
+                    duration.saturating_sub(*ramp_up).to_string()
+                }
+                (Some((_, duration)), Some(0)) => duration,
+                (Some((_, duration)), _) => format!("usize::saturating_sub({duration}, {ramp_up})"),
+                (None, _) => String::from("usize::MAX"),
+            };
+            let delay = load.delay_secs.as_ref().map_or(String::from("0"), quantity);
+            format!(
+                "vec![{}]",
+                row([
+                    quantity(&load.users),
+                    delay,
+                    ramp_up,
+                    hold,
+                    String::from("0")
+                ])
+            )
+        }
+    }
+}
+
+/// The Users, HatchRate and RunTime defaults for the thread groups of
+/// `loads` starting together, after the setup giving their iterations.
+/// Lowering notes the ramp-ups, run times and loop counts this merges.
+fn load_defaults(loads: &[&Load]) -> (String, String) {
+    let (users, hatch_rate) = match (
+        loads
+            .iter()
+            .map(|l| l.users.literal())
+            .sum::<Option<usize>>(),
+        loads
+            .iter()
+            .map(|l| l.ramp_up_secs.literal())
+            .collect::<Option<Vec<_>>>(),
+    ) {
+        (Some(users), Some(ramp_ups)) => {
+            let ramp_up = ramp_ups.into_iter().max().copied().unwrap_or(0);
+            let hatch_rate = if ramp_up == 0 {
+                users as f64
+            } else {
+                users as f64 / ramp_up as f64
+            };
+            (users.to_string(), format!("{:?}", hatch_rate.to_string()))
+        }
+        _ => {
+            let users = loads
+                .iter()
+                .map(|l| quantity(&l.users))
+                .collect::<Vec<_>>()
+                .join(" + ");
+            let ramp_ups = loads
+                .iter()
+                .map(|l| quantity(&l.ramp_up_secs))
+                .collect::<Vec<_>>();
+            let hatch_rate = format!("hatch_rate({users}, {}).as_str()", largest(&ramp_ups));
+            (users, hatch_rate)
+        }
+    };
+    let mut defaults = format!(
+        "        .set_default(GooseDefault::Users, {users})?\n        .set_default(GooseDefault::HatchRate, {hatch_rate})?\n"
+    );
+    let durations = loads.iter().filter_map(|l| run_time(l)).collect::<Vec<_>>();
+    let duration = match durations
+        .iter()
+        .map(|(literal, _)| *literal)
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(literals) => literals.into_iter().max().map(|d| d.to_string()),
+        None => Some(largest(
+            &durations.into_iter().map(|(_, d)| d).collect::<Vec<_>>(),
+        )),
+    };
+    let mut setup = String::new();
+    let mut iterations = loads.iter().map(|l| &l.iterations).collect::<Vec<_>>();
+    iterations.dedup();
+    if let Some(duration) = duration {
+        defaults.push_str(&format!(
+            "        .set_default(GooseDefault::RunTime, {duration})?\n"
+        ));
+    } else if let [Some(iterations)] = iterations.as_slice() {
+        setup = format!(
+            "    let configuration = with_iterations(configuration, {});\n",
+            quantity(iterations)
+        );
+    }
+    (setup, defaults)
+}
+
+/// `snippet` in a block where `props` holds the locked JMeter properties.
+fn with_props(snippet: &str) -> String {
+    format!(
+        "{{\n    let props = &mut *PROPS.lock().unwrap();\n{}}}\n",
+        indent(snippet, 4)
+    )
+}
+
+/// `code` commented out line by line at its outermost indentation, for
+/// disabled elements.
+fn commented(code: &str) -> String {
+    let margin = code
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    code.lines()
+        .map(|l| {
+            if l.trim().is_empty() {
+                format!("{l}\n")
+            } else {
+                format!("{}// {}\n", &l[..margin], &l[margin..])
+            }
+        })
+        .collect()
+}
+
+/// `code` as is when `enabled`, else [`commented`].
+fn toggled(enabled: bool, code: &str) -> String {
+    if enabled {
+        code.to_string()
+    } else {
+        commented(code)
+    }
+}
+
+impl GooseCodegen for Wait {
+    /// The delay in milliseconds as an `f64` expression, like JMeter's timers.
+    fn to_goose(&self) -> String {
+        match self {
+            Wait::Constant(t) => format!("millis({:?}, vars)", t.delay),
+            Wait::Uniform(t) => format!(
+                "millis({:?}, vars) + rand::random::<f64>() * millis({:?}, vars)",
+                t.delay, t.range
+            ),
+            Wait::Gaussian(t) => format!(
+                "millis({:?}, vars) + (gaussian() * millis({:?}, vars)).abs()",
+                t.delay, t.range
+            ),
+        }
+    }
+}
+
+impl GooseCodegen for PostProcessor {
+    fn to_goose(&self) -> String {
+        match self {
+            PostProcessor::Boundary(e) => e.to_goose(),
+            PostProcessor::XPath2(e) => e.to_goose(),
+            PostProcessor::Html(e) => e.to_goose(),
+            PostProcessor::Script(e) => e.to_goose(),
+        }
+    }
+}
+
+/// The static a generator shared by all users keeps its state in, declared
+/// once however many thread groups copy the generator, and the number
+/// telling it apart.
+fn shared_state(source: &DataSource) -> Option<(usize, String)> {
+    let (n, name, label, state) = match source {
+        DataSource::Random(e, n) => (n, "RANDOM", &e.test_name, e.shared_state()?),
+        DataSource::Counter(e, n) => (n, "COUNTER", &e.test_name, e.shared_state()?),
+        DataSource::UserParameters(_) => return None,
+    };
+    Some((
+        *n,
+        format!("\n/// The state of {label:?}, shared by every thread group\nstatic {name}_{n}: {state};\n"),
+    ))
+}
+
+impl GooseCodegen for DataSource {
+    fn to_goose(&self) -> String {
+        match self {
+            DataSource::UserParameters(e) => e.to_goose(),
+            DataSource::Random(e, n) => e.to_goose_sharing(&format!("RANDOM_{n}")),
+            DataSource::Counter(e, n) => e.to_goose_sharing(&format!("COUNTER_{n}")),
+        }
+    }
+}
+
+impl GooseCodegen for Request {
+    /// The body of an `async fn(user: &mut GooseUser, vars: &mut
+    /// HashMap<String, String>) -> TransactionResult` sending the request.
+    fn to_goose(&self) -> String {
+        // A disabled request is commented out as a whole
+        let on = |enabled: bool| enabled || !self.enabled;
+        let mut code = String::new();
+        let sleep = |waits: &[&Wait]| {
+            let waits = waits
+                .iter()
+                .map(|w| w.to_goose())
+                .collect::<Vec<String>>()
+                .join("\n    + ");
+            format!(
+                "let wait = {waits};\ntokio::time::sleep(std::time::Duration::from_millis(wait as u64)).await;\n"
+            )
+        };
+        let (waits, disabled): (Vec<&Wait>, Vec<&Wait>) =
+            self.waits.iter().partition(|w| on(w.enabled()));
+        if !waits.is_empty() {
+            code.push_str(&sleep(&waits));
+        }
+        for wait in disabled {
+            code.push_str(&commented(&sleep(&[wait])));
+        }
+        for parameters in &self.user_parameters {
+            code.push_str(&toggled(
+                on(parameters.enabled),
+                &with_props(&parameters.to_goose()),
+            ));
+        }
+        for script in &self.pre_processors {
+            code.push_str(&toggled(
+                on(script.enabled),
+                &with_props(&script.to_goose()),
+            ));
+        }
+        let goose_method = match self.method.as_str() {
+            "GET" => Some("Get"),
+            "POST" => Some("Post"),
+            "PUT" => Some("Put"),
+            "PATCH" => Some("Patch"),
+            "DELETE" => Some("Delete"),
+            "HEAD" => Some("Head"),
+            _ => None,
+        };
+        let method = match self.method.as_str() {
+            "OPTIONS" | "TRACE" | "CONNECT" => format!("reqwest::Method::{}", self.method),
+            other => format!("reqwest::Method::from_bytes(b{other:?}).unwrap()"),
+        };
+        code.push_str(&format!("let url = substitute({:?}, vars);\n", self.url()));
+        if self.multipart {
+            code.push_str(&multipart_form(&self.arguments, &self.files));
+            code.push('\n');
+        }
+        let request_builder = match goose_method {
+            Some(goose_method) => {
+                format!("user\n    .get_request_builder(&GooseMethod::{goose_method}, &url)?")
+            }
+            None => format!("user.client.request({method}, &url)"),
+        };
+        if goose_method.is_none() {
+            code.push_str(&format!(
+                "// Goose's metrics have no {:?}, counting it as GET\n",
+                self.method
+            ));
+        }
+        let goose_method = goose_method.unwrap_or("Get");
+        let mut chain = vec![format!("let request_builder = {request_builder}")];
+        for header in &self.headers {
+            chain.push(format!(
+                "    {}.header({:?}, substitute({:?}, vars))",
+                if on(header.enabled) { "" } else { "// " },
+                header.name,
+                header.value
+            ));
+        }
+        // JMeter's HttpClient4 closes the connection the same way
+        if !self.keep_alive {
+            chain.push(String::from(r#"    .header("Connection", "close")"#));
+        }
+        if let Some(Value::Literal(timeout)) = &self.response_timeout_ms {
+            chain.push(format!(
+                "    .timeout(std::time::Duration::from_millis({timeout}))"
+            ));
+        }
+        if self.multipart {
+            chain.push(String::from("    .multipart(form)"));
+        } else if let Some(body) = &self.body {
+            chain.push(format!("    .body(substitute({body:?}, vars))"));
+        } else if !self.arguments.is_empty() {
+            let arguments = self
+                .arguments
+                .iter()
+                .map(|a| format!("({:?}, substitute({:?}, vars))", a.name, a.value))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let kind = match goose_method {
+                "Get" | "Head" | "Delete" => "query",
+                _ => "form",
+            };
+            chain.push(format!("    .{kind}(&[{arguments}])"));
+        }
+        // Disabled headers are comments, so the statement ends on the last
+        // call that isn't
+        if let Some(last) = chain.iter_mut().rev().find(|l| !l.trim().starts_with("//")) {
+            last.push(';');
+        }
+        code.push_str(&chain.join("\n"));
+        if let Some(Value::Expression(timeout)) = &self.response_timeout_ms {
+            code.push_str(&format!(
+                "\nlet request_builder = match {} as u64 {{\n    0 => request_builder,\n    timeout => request_builder.timeout(std::time::Duration::from_millis(timeout)),\n}};",
+                timeout_millis(timeout)
+            ));
+        }
+        code.push_str(&format!(
+            r#"
+let goose_request = GooseRequest::builder()
+    .method(GooseMethod::{goose_method})
+    .path(url.as_str())
+    .name({:?})
+    .set_request_builder(request_builder)
+    .build();
+let mut goose = {send}.await?;
+let (status, headers, body) = match goose.response {{
+    Ok(response) => {{
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| format!("{{}}: {{}}\n", name, value.to_str().unwrap_or_default()))
+            .collect::<String>();
+        (status, headers, response.text().await.unwrap_or_default())
+    }}
+    Err(_) => (0, String::new(), String::new()),
+}};
+let (headers, body, url) = (headers.as_str(), body.as_str(), url.as_str());
+"#,
+            self.name,
+            send = if self.follows_redirects() {
+                "user.request(goose_request)"
+            } else {
+                "request_without_redirects(user, goose_request)"
+            },
+        ));
+        if let Some(embedded) = &self.embedded {
+            let filter = if embedded.url_re.is_empty() {
+                String::from("None")
+            } else {
+                code.push_str(&format!(
+                    "static EMBEDDED_URL_RE: LazyLock<Option<regex::Regex>> =\n    LazyLock::new(|| regex::Regex::new({:?}).ok());\n",
+                    format!("^(?:{})$", embedded.url_re)
+                ));
+                String::from("EMBEDDED_URL_RE.as_ref()")
+            };
+            code.push_str(&format!(
+                "let resources = embedded_resources(headers, body, url, {filter});\nfetch_embedded(user, resources, {:?}).await?;\n",
+                embedded.pool
+            ));
+        }
+        if self
+            .assertions
+            .iter()
+            .any(|a| on(a.enabled) && a.assume_success)
+        {
+            code.push_str(
+                "if !goose.request.success {\n    user.set_success(&mut goose.request)?;\n}\n",
+            );
+        }
+        for post_processor in &self.post_processors {
+            code.push_str(&toggled(
+                on(post_processor.enabled()),
+                &with_props(&post_processor.to_goose()),
+            ));
+        }
+        for assertion in &self.assertions {
+            code.push_str(&toggled(on(assertion.enabled), &format!(
+                "if let Some(message) = {} {{\n    return user.set_failure(&message, &mut goose.request, None, Some(body));\n}}\n",
+                assertion.to_goose()
+            )));
+        }
+        code.push_str("Ok(())\n");
+        code
+    }
+}
+
+impl Scenario {
+    /// `group` is the scenario's index among those taking [`turn`]s, when
+    /// they run one after another.
+    fn to_goose(&self, used: &mut HashSet<String>, group: Option<usize>) -> (String, String) {
+        let name = ident(&self.name, used);
+        // A disabled scenario is commented out as a whole
+        let on = |enabled: bool| enabled || !self.enabled;
+        let mut functions = String::new();
+
+        let users = format!("{}_USERS", name.to_uppercase());
+        functions.push_str(&format!(
+            "\n/// {:?} users started so far, see `thread_number`\nstatic {users}: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);\n",
+            self.name
+        ));
+        let mut pacing = String::new();
+        if let Some(group) = group {
+            let busy = format!("{}_BUSY", name.to_uppercase());
+            functions.push_str(&format!(
+                "\n/// Busy {:?} users, see `turn`\nstatic {busy}: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);\n",
+                self.name
+            ));
+            pacing.push_str(&format!(
+                "let Some(_turn) = turn({group}, &{busy}) else {{\n    // Another thread group's turn, or enough of this one's users are busy\n    tokio::time::sleep(std::time::Duration::from_secs(1)).await;\n    return Ok(());\n}};\n"
+            ));
+        }
+        if let Some(arrivals) = &self.load.arrivals {
+            let pacer = format!("{}_PACING", name.to_uppercase());
+            functions.push_str(&format!(
+                "\n/// Starts of {:?} iterations\nstatic {pacer}: LazyLock<Pacing> = LazyLock::new(Pacing::new);\n",
+                self.name
+            ));
+            // Seconds as `f64`, the rate per second
+            let float = |seconds: String| match seconds.parse::<u64>() {
+                Ok(seconds) => format!("{seconds}.0"),
+                Err(_) => format!("({seconds}) as f64"),
+            };
+            let rate = match (arrivals.rate.literal(), arrivals.unit_secs) {
+                (Some(rate), unit_secs) => format!("{:?}", *rate as f64 / unit_secs as f64),
+                (None, 1) => float(quantity(&arrivals.rate)),
+                (None, unit_secs) => format!("{} / {unit_secs}.0", float(quantity(&arrivals.rate))),
+            };
+            pacing.push_str(&format!(
+                "if !{pacer}\n    .wait({:?}, {rate}, {}, {}, {})\n    .await\n{{\n    // The schedule is over, leaving the user idle until the test ends\n    tokio::time::sleep(std::time::Duration::from_secs(1)).await;\n    return Ok(());\n}}\n",
+                self.name,
+                float(seconds(&arrivals.ramp_up, arrivals.unit_secs)),
+                quantity(&arrivals.steps),
+                float(seconds(&arrivals.hold, arrivals.unit_secs)),
+            ));
+        }
+
+        let mut start =
+            format!("let thread = {users}.fetch_add(1, std::sync::atomic::Ordering::Relaxed);\n");
+        let mut iteration = String::new();
+        for source in &self.data_sources {
+            let code = toggled(on(source.enabled()), &with_props(&source.to_goose()));
+            if let DataSource::Counter(counter, _) = source {
+                if let Some(reset) = counter.reset_to_goose() {
+                    iteration.push_str(&toggled(on(counter.enabled), &format!("{reset}\n")));
+                }
+            }
+            iteration.push_str(&code);
+        }
+        // The users' own clients, when they need settings Goose's lacks or
+        // one that doesn't follow redirects, which must share its cookies
+        let mut settings = String::new();
+        if let Some(hosts) = self
+            .resolver
+            .as_ref()
+            .map(|resolver| static_hosts(&resolver.hosts))
+            .filter(|hosts| hosts != "&[]")
+        {
+            settings.push_str(&format!("    static_hosts: {hosts},\n"));
+        }
+        match &self.connect_timeout_ms {
+            Some(Value::Literal(timeout)) => {
+                settings.push_str(&format!("    connect_timeout_ms: {timeout},\n"))
+            }
+            Some(Value::Expression(timeout)) => settings.push_str(&format!(
+                "    connect_timeout_ms: {} as u64,\n",
+                timeout_millis(timeout)
+            )),
+            None => {}
+        }
+        if let Some(keystore) = &self.keystore {
+            let alias = match &keystore.alias_var {
+                Some(name) => format!("&substitute({:?}, vars)", format!("${{{name}}}")),
+                None => String::from("\"\""),
+            };
+            start.push_str(&format!(
+                "let (first, last) = ({}, {});\nlet index = first + thread % (last.saturating_sub(first) + 1);\n",
+                quantity(&keystore.start_index),
+                quantity(&keystore.end_index),
+            ));
+            settings.push_str(&format!(
+                "    identity: client_identity({alias}, index).unwrap_or_else(|e| {{\n        eprintln!(\"{{e}}\");\n        std::process::exit(2);\n    }}),\n"
+            ));
+        }
+        let no_redirects = self.requests().any(|r| !r.follows_redirects());
+        if !settings.is_empty() || no_redirects {
+            start.push_str(&format!(
+                "let settings = ClientSettings {{\n{settings}    ..Default::default()\n}};\nlet jar = std::sync::Arc::new(reqwest::cookie::Jar::default());\nuser.set_client_builder(settings.builder(&jar)).await?;\n"
+            ));
+        }
+        start.push_str(if no_redirects {
+            "let no_redirects = settings\n    .builder(&jar)\n    .redirect(reqwest::redirect::Policy::none())\n    .build()\n    .map_err(TransactionError::from)?;\nlet no_redirects = Some(no_redirects);\n"
+        } else {
+            "let no_redirects = None;\n"
+        });
+        functions.push_str(&format!(
+            r#"
+/// Sets up the variables a {:?} user starts with.
+async fn {name}_start(user: &mut GooseUser) -> TransactionResult {{
+    let vars = &mut global_variables();
+{}    user.set_session_data(Session {{
+        vars: std::mem::take(vars),
+        thread,
+        no_redirects,
+    }});
+    Ok(())
+}}
+
+/// One iteration of the {:?} thread group.
+async fn {name}_iteration(user: &mut GooseUser) -> TransactionResult {{
+{}    let mut vars = std::mem::take(&mut user.get_session_data_mut::<Session>().unwrap().vars);
+    let result = {name}_steps(user, &mut vars).await;
+    user.get_session_data_mut::<Session>().unwrap().vars = vars;
+    result
+}}
+"#,
+            self.name,
+            indent(&start, 4),
+            self.name,
+            indent(&pacing, 4)
+        ));
+
+        let mut steps = String::new();
+        for step in &self.steps {
+            let (label, body) = match step {
+                Step::Request(request) => (request.name.as_str(), request.to_goose()),
+                Step::Script(script) => (
+                    script.test_name.as_str(),
+                    format!("{}Ok(())\n", with_props(&script.to_goose())),
+                ),
+            };
+            let step_name = ident(&format!("{name}_{label}"), used);
+            functions.push('\n');
+            functions.push_str(&toggled(on(step.enabled()), &format!(
+                "/// {label:?}\nasync fn {step_name}(user: &mut GooseUser, vars: &mut HashMap<String, String>) -> TransactionResult {{\n{}}}\n",
+                indent(&body, 4)
+            )));
+            steps.push_str(&toggled(on(step.enabled()), &match self.on_error {
+                OnError::Continue => format!("let _ = {step_name}(user, vars).await;\n"),
+                OnError::StartNextIteration => format!("{step_name}(user, vars).await?;\n"),
+                // Goose users can't stop the test, so these end the iteration
+                OnError::StopUser | OnError::StopTest | OnError::StopTestNow => format!(
+                    "// TODO(jmeter-to-goose): JMeter stops on errors here\n{step_name}(user, vars).await?;\n"
+                ),
+            }));
+        }
+        functions.push_str(&format!(
+            r#"
+async fn {name}_steps(user: &mut GooseUser, vars: &mut HashMap<String, String>) -> TransactionResult {{
+{}{}    Ok(())
+}}
+"#,
+            indent(&iteration, 4),
+            indent(&steps, 4)
+        ));
+
+        let scenario = format!(
+            r#"scenario!({:?})
+    .register_transaction(transaction!({name}_start).set_on_start())
+    .register_transaction(transaction!({name}_iteration))"#,
+            self.name
+        );
+        if self.enabled {
+            (scenario, functions)
+        } else {
+            (scenario, commented(&functions))
+        }
+    }
+}
+
+impl LoadTest {
+    /// The `Cargo.toml` of the generated crate.
+    pub fn cargo_toml(&self) -> String {
+        let mut used = HashSet::new();
+        let name = ident(&self.name, &mut used).replace('_', "-");
+        format!(
+            r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+goose = "0.17"
+gumdrop = "0.8"
+log = "0.4"
+rand = "0.8"
+regex = "1"
+reqwest = {{ version = "0.11", features = ["cookies", "multipart", "native-tls"] }}
+scraper = "0.18"
+sxd-document = "0.3"
+sxd-xpath = "0.4"
+tokio = {{ version = "1", features = ["full"] }}
+"#
+        )
+    }
+
+    /// Writes the crate to `out_dir`, copying upload files relative to
+    /// `jmx_dir` into its fixtures. Upload files that name no file, or
+    /// different files that would share a fixture, are an error.
+    pub fn write_goose_crate(&self, jmx_dir: &Path, out_dir: &Path) -> std::io::Result<()> {
+        let mut fixtures: HashMap<PathBuf, (PathBuf, &str)> = HashMap::new();
+        for file in self
+            .scenarios
+            .iter()
+            .flat_map(|s| s.requests())
+            .flat_map(|r| &r.files)
+            .filter(|file| !file.is_read_at_run_time())
+        {
+            let fixture = file.fixture_path()?;
+            let source = file.source_path(jmx_dir);
+            let source = std::fs::canonicalize(&source).unwrap_or(source);
+            match fixtures.get(&fixture) {
+                Some((other, path)) if *other != source => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!(
+                            "upload files {path:?} and {:?} would both be {}",
+                            file.path,
+                            fixture.display()
+                        ),
+                    ));
+                }
+                _ => {
+                    fixtures.insert(fixture, (source, &file.path));
+                }
+            }
+        }
+        std::fs::create_dir_all(out_dir.join("src"))?;
+        std::fs::write(out_dir.join("Cargo.toml"), self.cargo_toml())?;
+        std::fs::write(out_dir.join("src/main.rs"), self.to_goose())?;
+        for file in self
+            .scenarios
+            .iter()
+            .flat_map(|s| s.requests())
+            .flat_map(|r| &r.files)
+        {
+            file.copy_fixture(jmx_dir, out_dir)?;
+        }
+        Ok(())
+    }
+
+    /// The base URL of the first request with a literal host, as Goose
+    /// insists on one even though every request uses its full URL.
+    fn host(&self) -> String {
+        self.scenarios
+            .iter()
+            .filter(|s| s.enabled)
+            .flat_map(|s| &s.steps)
+            .find_map(|step| match step {
+                Step::Request(r) if r.enabled && !r.host.is_empty() && !r.host.contains("${") => {
+                    Some(match r.port.as_str() {
+                        "" => format!("{}://{}", r.protocol, r.host),
+                        port => format!("{}://{}:{port}", r.protocol, r.host),
+                    })
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| String::from("http://localhost"))
+    }
+}
+
+impl GooseCodegen for LoadTest {
+    /// The generated crate's `main.rs`.
+    fn to_goose(&self) -> String {
+        let mut used = HashSet::from([
+            String::from("main"),
+            String::from("global_variables"),
+            String::from("default_properties"),
+            String::from("default_system_properties"),
+        ]);
+        let mut code = format!("//! Generated by jmeter-to-goose from {:?}.\n", self.name);
+        for (heading, elements) in [
+            ("Not converted", &self.unsupported),
+            ("Disabled in the plan", &self.skipped),
+            ("Approximated", &self.approximated),
+        ] {
+            if !elements.is_empty() {
+                code.push_str(&format!("//!\n//! {heading}:\n"));
+            }
+            // Names may span lines, each of which stays in the comment
+            for element in elements {
+                code.push_str(&format!(
+                    "//! - {}\n",
+                    element.lines().collect::<Vec<_>>().join("\n//!   ")
+                ));
+            }
+        }
+        code.push('\n');
+        code.push_str(PRELUDE);
+        code.push('\n');
+        code.push_str(RUN_TIME);
+
+        for (function, doc, properties) in [
+            ("default_properties", "JMeter properties", &self.properties),
+            (
+                "default_system_properties",
+                "System properties",
+                &self.system_properties,
+            ),
+        ] {
+            let mut properties = properties.iter().collect::<Vec<_>>();
+            properties.sort();
+            code.push_str(&format!(
+                "\n/// {doc} the test starts with, before command line overrides\nfn {function}() -> HashMap<String, String> {{\n    HashMap::from([\n{}    ])\n}}\n",
+                properties
+                    .iter()
+                    .map(|(name, value)| format!("        ({name:?}.to_string(), {value:?}.to_string()),\n"))
+                    .collect::<String>()
+            ));
+        }
+        let mut variables = self.variables.iter().collect::<Vec<_>>();
+        variables.sort();
+        code.push_str(&format!(
+            "\n/// The plan's User Defined Variables\nfn global_variables() -> HashMap<String, String> {{\n    HashMap::from([\n{}    ])\n}}\n",
+            variables
+                .iter()
+                .map(|(name, value)| if value.contains("${__P(") || value.contains("${__property(") {
+                    format!("        ({name:?}.to_string(), read_properties({value:?})),\n")
+                } else {
+                    format!("        ({name:?}.to_string(), {value:?}.to_string()),\n")
+                })
+                .collect::<String>()
+        ));
+
+        let states = self
+            .scenarios
+            .iter()
+            .flat_map(|s| &s.data_sources)
+            .filter(|source| source.enabled())
+            .filter_map(shared_state)
+            .collect::<BTreeMap<usize, String>>();
+        code.push_str(&states.into_values().collect::<String>());
+
+        let loads = self
+            .scenarios
+            .iter()
+            .filter(|s| s.enabled)
+            .map(|s| &s.load)
+            .collect::<Vec<_>>();
+        let serial = self.serialize_threadgroups && loads.len() > 1;
+        let mut weights = weights(&loads).into_iter().flatten();
+        let mut scenarios = vec![];
+        let mut group = 0;
+        for scenario in &self.scenarios {
+            let turns = (serial && scenario.enabled).then_some(group);
+            let (mut register, functions) = scenario.to_goose(&mut used, turns);
+            if scenario.enabled {
+                if let Some(weight) = weights.next() {
+                    register.push_str(&format!("\n    .set_weight({weight})?"));
+                }
+                group += 1;
+            }
+            scenarios.push(toggled(
+                scenario.enabled,
+                &format!(
+                    "        .register_scenario(\n{}        )\n",
+                    indent(&register, 12)
+                ),
+            ));
+            code.push_str(&functions);
+        }
+
+        let mut setup = String::new();
+        let mut defaults = format!(
+            "        .set_default(GooseDefault::Host, {:?})?\n",
+            self.host()
+        );
+        if serial {
+            setup = format!(
+                "    let (users, run_time) = serial(vec![{}]);\n",
+                loads
+                    .iter()
+                    .map(|l| profile_rows(l))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            defaults.push_str(
+                "        .set_default(GooseDefault::Users, users)?\n        .set_default(GooseDefault::HatchRate, users.to_string().as_str())?\n        .set_default(GooseDefault::RunTime, run_time)?\n",
+            );
+        } else if loads.iter().any(|l| l.profile.is_some()) {
+            defaults.push_str(&format!(
+                "        .set_default(\n            GooseDefault::TestPlan,\n            test_plan(&[{}].concat()).as_str(),\n        )?\n",
+                loads.iter().map(|l| profile_rows(l)).collect::<Vec<_>>().join(", ")
+            ));
+        } else {
+            let (iterations, load) = load_defaults(&loads);
+            setup = iterations;
+            defaults.push_str(&load);
+        }
+        code.push_str(&format!(
+            r#"
+#[tokio::main]
+async fn main() -> Result<(), GooseError> {{
+    let Some(configuration) = configuration() else {{
+        return Ok(());
+    }};
+{setup}    GooseAttack::initialize_with_config(configuration)?
+{}{defaults}        .execute()
+        .await?;
+    Ok(())
+}}
+"#,
+            scenarios.concat()
+        ));
+        code
+    }
+}
+
+/// [`RUN_TIME`] as Rust, for its unit tests
+#[cfg(test)]
+#[allow(dead_code)]
+mod run_time {
+    use std::collections::HashMap;
+
+    include!("goose/run_time.rs");
+
+    #[cfg(test)]
+    mod test {
+        use super::{arrival, read_properties_in, stepping, test_plan};
+        use std::collections::HashMap;
+
+        #[test]
+        fn check_read_properties() {
+            let props = HashMap::from([(String::from("threads"), String::from("5"))]);
+            let read = |value: &str| read_properties_in(value, &props);
+            assert_eq!(read("${__P(threads,10)}"), "5");
+            assert_eq!(read("${__P(missing,10)}"), "10");
+            assert_eq!(read("${__P(missing)}"), "1");
+            assert_eq!(read("${__property(threads)}"), "5");
+            assert_eq!(read("${__property(missing)}"), "missing");
+            assert_eq!(read("${__property(missing,,7)}"), "7");
+            assert_eq!(read("a${__P( threads )}b${__P(x,)}c"), "a5bc");
+            // other functions and variables are left for later
+            assert_eq!(
+                read("${__time()} ${users} ${__P(threads"),
+                "${__time()} ${users} ${__P(threads"
+            );
+        }
+
+        #[test]
+        fn check_test_plan() {
+            assert_eq!(test_plan(&[]), "0,0s");
+            // ramped up over 10s, held for 60s and stopped over 5s
+            assert_eq!(test_plan(&[[10, 0, 10, 60, 5]]), "10,10s;10,60s;0,5s");
+            // instant starts and stops take 0s
+            assert_eq!(test_plan(&[[5, 0, 0, 30, 0]]), "5,0s;5,30s;0,0s");
+            // rows add up, one held until the other ends
+            assert_eq!(
+                test_plan(&[[2, 0, 0, usize::MAX, 0], [3, 5, 0, 10, 0]]),
+                "2,0s;2,5s;5,0s;5,10s;0,0s"
+            );
+        }
+
+        #[test]
+        fn check_stepping() {
+            assert!(stepping(0, 0, 0, 1, 10, 0, 30, 0, 0).is_empty());
+            // 2 users every 10s, all stopped together after 30s
+            assert_eq!(
+                stepping(4, 0, 0, 2, 10, 0, 30, 0, 0),
+                [[2, 0, 0, 40, 0], [2, 10, 0, 30, 0]]
+            );
+            // stopped one every 5s, after a delay and a burst of 3 users
+            assert_eq!(
+                stepping(4, 5, 3, 2, 10, 0, 30, 1, 5),
+                [[3, 5, 0, 40, 10], [1, 15, 0, 45, 0]]
+            );
+        }
+
+        #[test]
+        fn check_arrival() {
+            assert_eq!(arrival(0.0, 0.0, 0.0, 0, 60.0), None);
+            assert_eq!(arrival(3.0, 2.0, 0.0, 0, 60.0), Some(1.5));
+            // a smooth ramp up to 2/s over 10s, starting 10 iterations
+            assert_eq!(arrival(5.0, 2.0, 10.0, 0, 60.0), Some(50f64.sqrt()));
+            assert_eq!(arrival(12.0, 2.0, 10.0, 0, 60.0), Some(11.0));
+            // 2/s then 4/s, 5s each
+            assert_eq!(arrival(4.0, 4.0, 10.0, 2, 5.0), Some(2.0));
+            assert_eq!(arrival(14.0, 4.0, 10.0, 2, 5.0), Some(6.0));
+            assert_eq!(arrival(30.0, 4.0, 10.0, 2, 5.0), Some(10.0));
+            // over once held for 5s
+            assert_eq!(arrival(50.0, 4.0, 10.0, 2, 5.0), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GooseCodegen, LoadTest, TestClass};
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use xmltree::Element;
+
+    fn lower(path: &Path) -> LoadTest {
+        let xml = Element::parse(std::fs::read_to_string(path).unwrap().trim().as_bytes()).unwrap();
+        LoadTest::lower(&TestClass::root(&xml).unwrap())
+    }
+
+    /// Writes the crate of `test` to `name` in the system's temp directory
+    /// and builds it offline, all crates sharing one target directory.
+    /// Returns the binary.
+    ///
+    /// This needs Goose and the generated crates' other dependencies in the
+    /// local cargo cache, so the tests using it are ignored unless run with
+    /// `cargo test -- --ignored`.
+    fn build(test: &LoadTest, jmx_dir: &Path, name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("jmeter-to-goose-crates");
+        let out_dir = dir.join(name);
+        test.write_goose_crate(jmx_dir, &out_dir).unwrap();
+        let output = Command::new(env!("CARGO"))
+            .args(["build", "--offline", "--quiet"])
+            .current_dir(&out_dir)
+            .env("CARGO_TARGET_DIR", dir.join("target"))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{name}:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let package = test.cargo_toml();
+        let package = package
+            .lines()
+            .find_map(|line| line.strip_prefix("name = "))
+            .unwrap()
+            .trim_matches('"');
+        dir.join("target/debug").join(package)
+    }
+
+    /// Runs `binary` until it exits, for at most two minutes.
+    fn run(binary: &Path) {
+        let mut child = Command::new(binary)
+            .args(["--no-telnet", "--no-websocket"])
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        for _ in 0..1200 {
+            if let Some(status) = child.try_wait().unwrap() {
+                assert!(status.success());
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        child.kill().unwrap();
+        panic!("{} didn't exit", binary.display());
+    }
+
+    #[test]
+    #[ignore = "builds and runs a generated crate, see `build`"]
+    fn check_requests_without_redirects() {
+        use std::io::{BufRead, BufReader, Write};
+
+        // Logging in redirects to /welcome, checking out to /cart. Only the
+        // requests and cookies are recorded.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let server_log = log.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+                let Some(Ok(request)) = lines.next() else {
+                    continue;
+                };
+                let cookie = lines
+                    .map_while(Result::ok)
+                    .take_while(|line| !line.is_empty())
+                    .find(|line| line.to_ascii_lowercase().starts_with("cookie:"))
+                    .unwrap_or_default();
+                let response = match request.split(' ').nth(1).unwrap() {
+                    "/login" => "302 Found\r\nLocation: /welcome\r\nSet-Cookie: session=42",
+                    "/checkout" => "302 Found\r\nLocation: /cart",
+                    _ => "200 OK",
+                };
+                server_log
+                    .lock()
+                    .unwrap()
+                    .push(format!("{request} {cookie}").trim().to_string());
+                write!(
+                    stream,
+                    "HTTP/1.1 {response}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+        });
+
+        let mut test = lower(Path::new("./unittests_data/ir/redirects.jmx"));
+        test.name = String::from("Redirects run");
+        for step in &mut test.scenarios[0].steps {
+            if let crate::Step::Request(request) = step {
+                request.port = port.to_string();
+            }
+        }
+        assert!(test
+            .to_goose()
+            .contains("let configuration = with_iterations(configuration, 1);"));
+        run(&build(
+            &test,
+            Path::new("./unittests_data/ir"),
+            "redirects_run",
+        ));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "GET /login HTTP/1.1",
+                "GET /welcome HTTP/1.1 cookie: session=42",
+                "GET /checkout HTTP/1.1 cookie: session=42",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_notes_stay_commented() {
+        let mut test = lower(Path::new("./unittests_data/ir/redirects.jmx"));
+        test.unsupported = vec![String::from("<Unknown> a\nfn injected() {}")];
+        assert!(test
+            .to_goose()
+            .contains("//! Not converted:\n//! - <Unknown> a\n//!   fn injected() {}\n"));
+    }
+
+    #[test]
+    fn check_system_properties_stay_apart() {
+        let mut test = lower(Path::new("./unittests_data/ir/redirects.jmx"));
+        test.properties
+            .insert(String::from("host"), String::from("a"));
+        test.system_properties.insert(
+            String::from("javax.net.ssl.keyStore"),
+            String::from("k.p12"),
+        );
+        let code = test.to_goose();
+        assert!(code.contains(
+            "fn default_properties() -> HashMap<String, String> {\n    HashMap::from([\n        (\"host\".to_string(), \"a\".to_string()),\n    ])\n}"
+        ));
+        assert!(code.contains(
+            "fn default_system_properties() -> HashMap<String, String> {\n    HashMap::from([\n        (\"javax.net.ssl.keyStore\".to_string(), \"k.p12\".to_string()),\n    ])\n}"
+        ));
+    }
+
+    #[test]
+    fn check_upload_fixtures_stay_in_the_crate() {
+        let mut test = lower(Path::new("./unittests_data/ir/redirects.jmx"));
+        let out_dir = std::env::temp_dir().join("jmeter-to-goose-upload-fixtures");
+        let mut write = |paths: &[&str]| {
+            let crate::Step::Request(request) = &mut test.scenarios[0].steps[0] else {
+                panic!("expected a request");
+            };
+            request.files = paths
+                .iter()
+                .map(|path| crate::ElementHttpFileArg {
+                    path: path.to_string(),
+                    param_name: String::from("file"),
+                    mime_type: String::new(),
+                })
+                .collect();
+            test.write_goose_crate(Path::new("./unittests_data/ir"), &out_dir)
+        };
+        let _ = std::fs::remove_dir_all(&out_dir);
+        let error = write(&["absolute/tmp/a.png", "/tmp/a.png"]).unwrap_err();
+        assert!(error.to_string().contains("would both be"));
+        let error = write(&["parent/a.png", "../a.png"]).unwrap_err();
+        assert!(error.to_string().contains("would both be"));
+        assert!(!out_dir.exists());
+
+        // Files above the .jmx directory are copied in too, once however
+        // they are spelled
+        write(&["../sampler/data/avatar.png", "../sampler/./data/avatar.png"]).unwrap();
+        assert_eq!(
+            std::fs::read(out_dir.join("fixtures/parent/sampler/data/avatar.png")).unwrap(),
+            std::fs::read("./unittests_data/sampler/data/avatar.png").unwrap()
+        );
+    }
+
+    #[test]
+    #[ignore = "builds generated crates, see `build`"]
+    fn check_generated_crates_build() {
+        for fixtures in ["./unittests_data/ir", "./unittests_data/test_class"] {
+            let mut plans = std::fs::read_dir(fixtures)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|e| e == "jmx"))
+                .collect::<Vec<_>>();
+            plans.sort();
+            for plan in plans {
+                let name = plan.file_stem().unwrap().to_str().unwrap();
+                build(&lower(&plan), plan.parent().unwrap(), name);
+            }
+        }
+    }
+}
@@ -0,0 +1,301 @@
+/// [`read_properties`] from `props`, for code already holding them.
+fn read_properties_in(value: &str, props: &HashMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${__") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 4..];
+        let call = after.find(")}").and_then(|end| {
+            let (function, args) = after[..end].split_once('(')?;
+            let args = args.split(',').map(str::trim).collect::<Vec<&str>>();
+            let default = match (function, args.as_slice()) {
+                ("P", [_]) => "1",
+                ("property", [name] | [name, _]) => *name,
+                ("P", [_, default]) | ("property", [_, _, default]) => *default,
+                _ => return None,
+            };
+            let value = props.get(args[0]).cloned();
+            Some((end, value.unwrap_or_else(|| default.to_string())))
+        });
+        match call {
+            Some((end, value)) => {
+                result.push_str(&value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                result.push_str("${__");
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// A Java `.properties` file: `#` and `!` comments, `key=value`,
+/// `key: value` or `key value` lines, trailing backslashes continuing a line.
+fn parse_properties(text: &str) -> HashMap<String, String> {
+    let unescape = |text: &str| {
+        let mut result = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('t') => result.push('\t'),
+                Some('n') => result.push('\n'),
+                Some('r') => result.push('\r'),
+                Some('f') => result.push('\u{c}'),
+                Some('u') => {
+                    let hex = chars.by_ref().take(4).collect::<String>();
+                    match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        Some(c) => result.push(c),
+                        None => result.push_str(&hex),
+                    }
+                }
+                Some(c) => result.push(c),
+                None => {}
+            }
+        }
+        result
+    };
+    let mut properties = HashMap::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let mut line = line.trim_start().to_string();
+        if line.is_empty() || line.starts_with(['#', '!']) {
+            continue;
+        }
+        while line.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1 {
+            line.pop();
+            match lines.next() {
+                Some(next) => line.push_str(next.trim_start()),
+                None => break,
+            }
+        }
+        let mut escaped = false;
+        let end = line
+            .char_indices()
+            .find(|(_, c)| {
+                let separator = !escaped && matches!(c, '=' | ':' | ' ' | '\t' | '\u{c}');
+                escaped = !escaped && *c == '\\';
+                separator
+            })
+            .map_or(line.len(), |(i, _)| i);
+        let value = line[end..].trim_start();
+        let value = value.strip_prefix(['=', ':']).unwrap_or(value).trim_start();
+        properties.insert(unescape(&line[..end]), unescape(value));
+    }
+    properties
+}
+
+/// A thread group setting read at run time. Negative and unreadable values
+/// count as 0, which Goose takes as no limit for iterations and run time.
+fn count(value: &str) -> usize {
+    value.trim().parse::<i64>().map_or(0, |v| v.max(0) as usize)
+}
+
+/// Goose's hatch rate for `users` started over `ramp_up` seconds.
+fn hatch_rate(users: usize, ramp_up: usize) -> String {
+    if ramp_up == 0 {
+        users.to_string()
+    } else {
+        (users as f64 / ramp_up as f64).to_string()
+    }
+}
+
+/// Goose's `--test-plan` for `rows` of `[users, delay, startup, hold,
+/// shutdown]` in seconds running side by side, like the Ultimate Thread
+/// Group's. A `hold` of `usize::MAX` lasts until the other rows end.
+fn test_plan(rows: &[[usize; 5]]) -> String {
+    let end = rows
+        .iter()
+        .filter(|row| row[3] != usize::MAX)
+        .map(|row| row.iter().skip(1).sum::<usize>())
+        .max()
+        .unwrap_or(0);
+    let rows = rows
+        .iter()
+        .map(|&[users, delay, startup, hold, shutdown]| {
+            let hold = match hold {
+                usize::MAX => end.saturating_sub(delay + startup),
+                hold => hold,
+            };
+            [users, delay, startup, hold, shutdown]
+        })
+        .collect::<Vec<_>>();
+    // Users just before `t`, or just after it, so that instant starts and
+    // stops become steps taking 0s
+    let users_at = |t: usize, after: bool| {
+        let before = |limit: usize| if after { t < limit } else { t <= limit };
+        let users = rows
+            .iter()
+            .map(|&[users, delay, startup, hold, shutdown]| {
+                let (users, stop) = (users as f64, delay + startup + hold);
+                if before(delay) {
+                    0.0
+                } else if before(delay + startup) {
+                    users * (t - delay) as f64 / startup as f64
+                } else if before(stop) {
+                    users
+                } else if before(stop + shutdown) {
+                    users * (stop + shutdown - t) as f64 / shutdown as f64
+                } else {
+                    0.0
+                }
+            })
+            .sum::<f64>();
+        users.round() as usize
+    };
+    let mut times = rows
+        .iter()
+        .flat_map(|&[_, delay, startup, hold, shutdown]| {
+            [delay, delay + startup, delay + startup + hold, delay + startup + hold + shutdown]
+        })
+        .collect::<Vec<usize>>();
+    times.sort();
+    times.dedup();
+    let mut steps = vec![];
+    let mut previous = 0;
+    for t in times {
+        let (before, after) = (users_at(t, false), users_at(t, true));
+        if t > previous {
+            steps.push(format!("{before},{}s", t - previous));
+        }
+        if after != before {
+            steps.push(format!("{after},0s"));
+        }
+        previous = t;
+    }
+    if steps.is_empty() {
+        return String::from("0,0s");
+    }
+    steps.join(";")
+}
+
+/// The [`test_plan`] rows for `users` reached in `steps` equal steps over
+/// `ramp_up` seconds, or smoothly for 0 steps, then held for `hold`
+/// seconds, like the Concurrency Thread Group.
+fn concurrency(users: usize, ramp_up: usize, steps: usize, hold: usize) -> Vec<[usize; 5]> {
+    if steps == 0 || ramp_up == 0 {
+        return vec![[users, 0, ramp_up, hold, 0]];
+    }
+    (0..steps)
+        .map(|step| {
+            let delay = ramp_up * step / steps;
+            let batch = users * (step + 1) / steps - users * step / steps;
+            [batch, delay, 0, ramp_up + hold - delay, 0]
+        })
+        .collect()
+}
+
+/// The [`test_plan`] rows for `users` started `batch` at a time after
+/// `delay`, `burst` of them first, each batch over `ramp_up` and `period`
+/// after the previous one, all held for `hold`, then stopped `stop_batch` at
+/// a time, `stop_period` apart, like the Stepping Thread Group. Batches of 0
+/// are all of the users.
+#[allow(clippy::too_many_arguments)]
+fn stepping(
+    users: usize,
+    delay: usize,
+    burst: usize,
+    batch: usize,
+    period: usize,
+    ramp_up: usize,
+    hold: usize,
+    stop_batch: usize,
+    stop_period: usize,
+) -> Vec<[usize; 5]> {
+    if users == 0 {
+        return vec![];
+    }
+    let batch = if batch == 0 { users } else { batch };
+    let stop_batch = if stop_batch == 0 { users } else { stop_batch };
+    let burst = if burst == 0 { batch.min(users) } else { burst.min(users) };
+    // Batches are numbered from the burst's 0, each starting its users
+    // evenly over `ramp_up`
+    let mut batches = vec![(0, burst, ramp_up)];
+    let mut started = burst;
+    while started < users {
+        let size = batch.min(users - started);
+        batches.push((started, size, ramp_up * size / batch));
+        started += size;
+    }
+    let last = batches.len() - 1;
+    let descent = delay + last * (period + ramp_up) + batches[last].2 + hold;
+    batches
+        .iter()
+        .enumerate()
+        .map(|(k, &(first, size, startup))| {
+            let start = delay + k * (period + ramp_up);
+            let stop = |user: usize| descent + stop_period * (user / stop_batch);
+            let (first_stop, last_stop) = (stop(first), stop(first + size - 1));
+            [
+                size,
+                start,
+                startup,
+                first_stop.saturating_sub(start + startup),
+                last_stop - first_stop,
+            ]
+        })
+        .collect()
+}
+
+/// When the `n`th iteration, counting from 0, is due in seconds from the
+/// start, for `rate` iterations a second reached in `steps` equal steps over
+/// `ramp_up` seconds, or smoothly for 0 steps, then held for `hold` seconds.
+/// `None` once they are over.
+fn arrival(n: f64, rate: f64, ramp_up: f64, steps: usize, hold: f64) -> Option<f64> {
+    if rate <= 0.0 {
+        return None;
+    }
+    let due = if ramp_up <= 0.0 {
+        n / rate
+    } else if steps == 0 {
+        let ramping = rate * ramp_up / 2.0;
+        if n < ramping {
+            (2.0 * ramp_up * n / rate).sqrt()
+        } else {
+            ramp_up + (n - ramping) / rate
+        }
+    } else {
+        let length = ramp_up / steps as f64;
+        let mut before = 0.0;
+        let mut due = None;
+        for step in 0..steps {
+            let step_rate = rate * (step + 1) as f64 / steps as f64;
+            if n < before + step_rate * length {
+                due = Some(step as f64 * length + (n - before) / step_rate);
+                break;
+            }
+            before += step_rate * length;
+        }
+        due.unwrap_or(ramp_up + (n - before) / rate)
+    };
+    (due < ramp_up + hold).then_some(due)
+}
+
+/// Users running `t` seconds into [`test_plan`] `rows`.
+fn running(rows: &[[usize; 5]], t: f64) -> usize {
+    let users = rows
+        .iter()
+        .map(|&[users, delay, startup, hold, shutdown]| {
+            let (users, delay, startup) = (users as f64, delay as f64, startup as f64);
+            let (stop, shutdown) = (delay + startup + hold as f64, shutdown as f64);
+            if t < delay {
+                0.0
+            } else if t < delay + startup {
+                users * (t - delay) / startup
+            } else if t < stop {
+                users
+            } else if t < stop + shutdown {
+                users * (stop + shutdown - t) / shutdown
+            } else {
+                0.0
+            }
+        })
+        .sum::<f64>();
+    users.round() as usize
+}
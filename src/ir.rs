@@ -0,0 +1,1825 @@
+//! A normalized load test, lowered from the JMeter tree.
+//!
+//! [`LoadTest::lower`] resolves JMeter's scoping once: header managers,
+//! timers, pre-/post-processors and assertions apply to every sampler at their
+//! level and below, so each [`Request`] carries the full list that applies to
+//! it, outermost first. HTTP Request Defaults in scope fill in what its
+//! sampler leaves blank, and the innermost DNS Cache Manager decides how it
+//! resolves host names. Backends never look at the `TestClass` tree.
+//! Elements that are already normalized, like extractors, timers and scripts,
+//! are carried as they are.
+//!
+//! Thread group settings referencing the plan's variables are resolved
+//! against them. What is still an expression then, typically a property
+//! read like `${__P(threads,10)}`, is left for backends to evaluate at run
+//! time.
+//!
+//! Disabled elements and their subtrees are left out and listed in
+//! [`LoadTest::skipped`]. [`LoadTest::lower_including_disabled`] keeps them
+//! instead, with `enabled` cleared on everything beneath a disabled element,
+//! for backends to write out commented.
+
+use crate::thread::{
+    ArrivalsThreadGroup, ConcurrencyThreadGroup, SteppingThreadGroup, ThreadGroup,
+    UltimateThreadGroup,
+};
+use crate::{
+    substitute, BoundaryExtractor, ConstantTimer, CounterConfig, ElementArgument,
+    ElementHttpFileArg, GaussianRandomTimer, HtmlExtractor, HttpDefaults, HttpSampler,
+    KeystoreConfig, RandomVariableConfig, ResponseAssertion, ScriptElement, ScriptKind, TestClass,
+    UniformRandomTimer, UserParameters, Value, XPath2Extractor,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoadTest {
+    pub name: String,
+    /// Global variables, their references to each other resolved
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::sorted_map::serialize")
+    )]
+    pub variables: HashMap<String, String>,
+    /// JMeter properties the test starts with, as from `user.properties`.
+    /// Lowering leaves them empty, see [`parse_properties`](crate::parse_properties).
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::sorted_map::serialize")
+    )]
+    pub properties: HashMap<String, String>,
+    /// Java system properties the test starts with, like
+    /// `javax.net.ssl.keyStore`. `${__P}` doesn't read these. Lowering leaves
+    /// them empty.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::sorted_map::serialize")
+    )]
+    pub system_properties: HashMap<String, String>,
+    /// One per thread group
+    pub scenarios: Vec<Scenario>,
+    /// Run the scenarios one after another, each starting when the one
+    /// before ends, rather than side by side
+    pub serialize_threadgroups: bool,
+    /// `<tag> testname` of every element that was not converted
+    pub unsupported: Vec<String>,
+    /// `<tag> testname` of every disabled element, leaving out their
+    /// subtrees
+    pub skipped: Vec<String>,
+    /// `<tag> testname: how` of every element that backends can only
+    /// approximate
+    pub approximated: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scenario {
+    pub name: String,
+    pub enabled: bool,
+    pub load: Load,
+    pub on_error: OnError,
+    /// Per-user variables and generators in scope, in document order
+    pub data_sources: Vec<DataSource>,
+    pub steps: Vec<Step>,
+    /// The users' connect timeout, the longest of the requests', which
+    /// reqwest only sets per client
+    pub connect_timeout_ms: Option<Value<u64>>,
+    /// The users' resolver, the first of the requests', which reqwest only
+    /// sets per client too
+    pub resolver: Option<Resolver>,
+    /// The plan's client certificates, which JMeter uses in every thread
+    /// group
+    pub keystore: Option<Keystore>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Load {
+    pub users: Value<usize>,
+    pub ramp_up_secs: Value<u64>,
+    /// `None` loops until the test ends, as does an expression evaluating
+    /// to a negative number
+    pub iterations: Option<Value<u64>>,
+    /// Only set when the thread group uses the scheduler
+    pub duration_secs: Option<Value<u64>>,
+    pub delay_secs: Option<Value<u64>>,
+    /// Users over time for thread groups that follow a schedule, which
+    /// `users` and `ramp_up_secs` only summarize
+    pub profile: Option<Profile>,
+    /// For thread groups starting iterations at a target rate, which `users`
+    /// then only bounds
+    pub arrivals: Option<Arrivals>,
+}
+
+/// The schedule of a plugin thread group
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Profile {
+    /// Rows running side by side, as in the Ultimate Thread Group
+    Rows(Vec<ProfileRow>),
+    /// `users` reached in `steps` equal steps over `ramp_up`, or smoothly
+    /// for 0 steps, then held for `hold`, as in the Concurrency Thread
+    /// Group. Times are in units of `unit_secs` seconds.
+    Steps {
+        users: Value<u64>,
+        ramp_up: Value<u64>,
+        steps: Value<u64>,
+        hold: Value<u64>,
+        unit_secs: u64,
+    },
+    /// `users` started `batch` at a time, or `burst` for the first batch,
+    /// each over `ramp_up` and `period` apart, then all held for `hold` and
+    /// stopped `stop_batch` at a time, `stop_period` apart, as in the
+    /// Stepping Thread Group. Times are in seconds.
+    Stepping {
+        users: Value<u64>,
+        delay: Value<u64>,
+        burst: Value<u64>,
+        batch: Value<u64>,
+        period: Value<u64>,
+        ramp_up: Value<u64>,
+        hold: Value<u64>,
+        stop_batch: Value<u64>,
+        stop_period: Value<u64>,
+    },
+}
+
+/// Iterations started at `rate` per `unit_secs` seconds however long they
+/// take, JMeter's open model, reached in `steps` equal steps over `ramp_up`,
+/// or smoothly for 0 steps, then held for `hold`, as in the Arrivals Thread
+/// Group. Times are in units of `unit_secs` seconds.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Arrivals {
+    pub rate: Value<u64>,
+    pub ramp_up: Value<u64>,
+    pub steps: Value<u64>,
+    pub hold: Value<u64>,
+    pub unit_secs: u64,
+}
+
+/// `users` started after `delay_secs` over `startup_secs`, held for
+/// `hold_secs`, then stopped over `shutdown_secs`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProfileRow {
+    pub users: Value<u64>,
+    pub delay_secs: Value<u64>,
+    pub startup_secs: Value<u64>,
+    pub hold_secs: Value<u64>,
+    pub shutdown_secs: Value<u64>,
+}
+
+/// What a user does after a failed sample, `ThreadGroup.on_sample_error`
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OnError {
+    Continue,
+    StartNextIteration,
+    StopUser,
+    StopTest,
+    StopTestNow,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DataSource {
+    /// Applied at the start of every iteration. Those that aren't
+    /// `per_iteration` are applied before every request in their scope
+    /// instead, see [`Request::user_parameters`].
+    UserParameters(UserParameters),
+    /// Applied at the start of every iteration. The number tells the plan's
+    /// generators apart, thread groups sharing the state of the same one.
+    Random(RandomVariableConfig, usize),
+    /// Applied at the start of every iteration, numbered like `Random`
+    Counter(CounterConfig, usize),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Step {
+    Request(Box<Request>),
+    /// A JSR223 or BeanShell sampler
+    Script(ScriptElement),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Request {
+    /// The sampler's label
+    pub name: String,
+    pub enabled: bool,
+    pub method: String,
+    pub protocol: String,
+    pub host: String,
+    pub port: String,
+    pub path: String,
+    /// Merged from every header manager in scope, inner ones overriding
+    pub headers: Vec<Header>,
+    /// Sent as the query of GET, HEAD and DELETE requests and as a form
+    /// otherwise
+    pub arguments: Vec<ElementArgument>,
+    /// A raw body, sent as it is instead of `arguments`
+    pub body: Option<String>,
+    pub files: Vec<ElementHttpFileArg>,
+    pub multipart: bool,
+    pub content_encoding: String,
+    /// Redirects are followed when either is set. JMeter only tells them
+    /// apart in its results
+    pub follow_redirects: bool,
+    pub auto_redirects: bool,
+    pub keep_alive: bool,
+    /// `None` for the system's, which 0 also means in JMeter
+    pub connect_timeout_ms: Option<Value<u64>>,
+    /// `None` to wait forever, which 0 also means in JMeter
+    pub response_timeout_ms: Option<Value<u64>>,
+    /// JMeter's HTTP client, `HttpClient4` or `Java`, blank for its default
+    pub implementation: String,
+    /// Fetching the resources HTML responses embed, `None` when they
+    /// aren't
+    pub embedded: Option<Embedded>,
+    /// The custom resolver of the innermost DNS Cache Manager in scope,
+    /// `None` for the system's
+    pub resolver: Option<Resolver>,
+    /// Timers in scope, all waited before sending
+    pub waits: Vec<Wait>,
+    /// User Parameters in scope updating their variables before every
+    /// request rather than once per iteration
+    pub user_parameters: Vec<UserParameters>,
+    pub pre_processors: Vec<ScriptElement>,
+    pub post_processors: Vec<PostProcessor>,
+    pub assertions: Vec<ResponseAssertion>,
+}
+
+/// How a sampler fetches the images, scripts, stylesheets and frames its
+/// HTML responses embed, JMeter's "Retrieve All Embedded Resources"
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Embedded {
+    /// Only resources whose whole URL matches it are fetched, blank for all
+    pub url_re: String,
+    /// How many are fetched at a time, `None` for one after another
+    pub pool: Option<usize>,
+}
+
+impl Request {
+    /// The URL as JMeter builds it, which is `path` alone when that is
+    /// already absolute. May reference variables.
+    pub fn url(&self) -> String {
+        if self.path.starts_with("http://") || self.path.starts_with("https://") {
+            return self.path.clone();
+        }
+        let port = if self.port.is_empty() {
+            String::new()
+        } else {
+            format!(":{}", self.port)
+        };
+        let slash = if self.path.starts_with('/') { "" } else { "/" };
+        format!(
+            "{}://{}{port}{slash}{}",
+            self.protocol, self.host, self.path
+        )
+    }
+    /// Whether redirects are followed, by the HTTP client or by JMeter
+    pub fn follows_redirects(&self) -> bool {
+        self.follow_redirects || self.auto_redirects
+    }
+}
+
+/// The client certificates of a Keystore Configuration. Each user presents
+/// the one `alias_var` names, or else the one at its index, counting from
+/// `start_index` to `end_index` and around again.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keystore {
+    pub alias_var: Option<String>,
+    pub start_index: Value<usize>,
+    pub end_index: Value<usize>,
+}
+
+/// How a DNS Cache Manager resolves host names in its custom resolver mode
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Resolver {
+    /// Resolved to fixed addresses, without asking `servers`
+    pub hosts: Vec<StaticHost>,
+    /// Name servers for the other host names
+    pub servers: Vec<String>,
+    /// Forget earlier lookups at the start of every iteration. reqwest
+    /// doesn't keep them in the first place, looking names up for every new
+    /// connection.
+    pub clear_each_iteration: bool,
+}
+
+/// A host name and the addresses, IPs or other host names, it resolves to
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StaticHost {
+    pub name: String,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+    /// Only `false` when disabled elements are kept
+    pub enabled: bool,
+}
+
+/// Timers, whose delays are milliseconds and may reference variables
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Wait {
+    Constant(ConstantTimer),
+    /// `delay` plus up to `range`
+    Uniform(UniformRandomTimer),
+    /// `delay` plus the absolute value of a normal deviate scaled by `range`
+    Gaussian(GaussianRandomTimer),
+}
+
+impl Wait {
+    pub fn enabled(&self) -> bool {
+        match self {
+            Wait::Constant(t) => t.enabled,
+            Wait::Uniform(t) => t.enabled,
+            Wait::Gaussian(t) => t.enabled,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PostProcessor {
+    Boundary(BoundaryExtractor),
+    XPath2(XPath2Extractor),
+    Html(HtmlExtractor),
+    Script(ScriptElement),
+}
+
+impl PostProcessor {
+    pub fn enabled(&self) -> bool {
+        match self {
+            PostProcessor::Boundary(e) => e.enabled,
+            PostProcessor::XPath2(e) => e.enabled,
+            PostProcessor::Html(e) => e.enabled,
+            PostProcessor::Script(e) => e.enabled,
+        }
+    }
+}
+
+impl DataSource {
+    pub fn enabled(&self) -> bool {
+        match self {
+            DataSource::UserParameters(e) => e.enabled,
+            DataSource::Random(e, _) => e.enabled,
+            DataSource::Counter(e, _) => e.enabled,
+        }
+    }
+}
+
+impl Scenario {
+    /// The requests among the steps
+    pub fn requests(&self) -> impl Iterator<Item = &Request> {
+        self.steps.iter().filter_map(|step| match step {
+            Step::Request(request) => Some(request.as_ref()),
+            Step::Script(_) => None,
+        })
+    }
+}
+
+impl Step {
+    pub fn enabled(&self) -> bool {
+        match self {
+            Step::Request(r) => r.enabled,
+            Step::Script(s) => s.enabled,
+        }
+    }
+}
+
+/// How disabled elements are treated while lowering
+#[derive(Clone, Copy)]
+struct Lowering {
+    include_disabled: bool,
+    /// Beneath a disabled element
+    disabled: bool,
+}
+
+impl Lowering {
+    /// The context for `node`'s subtree, `None` when it is left out.
+    fn enter(self, node: &TestClass) -> Option<Lowering> {
+        let disabled = self.disabled || !node.enabled();
+        (self.include_disabled || !disabled).then_some(Lowering { disabled, ..self })
+    }
+
+    /// Whether an element whose own flag is `enabled` ends up enabled.
+    fn enabled(self, enabled: bool) -> bool {
+        enabled && !self.disabled
+    }
+}
+
+/// The elements applying to the samplers of one level of the tree
+#[derive(Default, Clone)]
+struct Scope {
+    headers: Vec<Header>,
+    /// Enabled HTTP Request Defaults, outermost first
+    defaults: Vec<HttpDefaults>,
+    /// Of the innermost enabled DNS Cache Manager
+    resolver: Option<Resolver>,
+    waits: Vec<Wait>,
+    user_parameters: Vec<UserParameters>,
+    pre_processors: Vec<ScriptElement>,
+    post_processors: Vec<PostProcessor>,
+    assertions: Vec<ResponseAssertion>,
+}
+
+/// A copy of `element` with `enabled` cleared beneath a disabled element.
+macro_rules! lowered {
+    ($lowering:expr, $element:expr) => {{
+        let mut element = $element.clone();
+        element.enabled = $lowering.enabled(element.enabled);
+        element
+    }};
+}
+
+impl Scope {
+    /// This scope plus the scoped elements among `nodes`.
+    fn enter(&self, nodes: &[TestClass], lowering: Lowering) -> Scope {
+        let mut scope = self.clone();
+        for node in nodes {
+            let Some(lowering) = lowering.enter(node) else {
+                continue;
+            };
+            match node {
+                TestClass::HeaderManager(manager, ..) => {
+                    let enabled = lowering.enabled(manager.enabled);
+                    let mut headers = manager.headers.iter().collect::<Vec<_>>();
+                    headers.sort();
+                    for (name, value) in headers {
+                        if enabled {
+                            scope
+                                .headers
+                                .retain(|h| !h.enabled || !h.name.eq_ignore_ascii_case(name));
+                        }
+                        scope.headers.push(Header {
+                            name: name.clone(),
+                            value: value.clone(),
+                            enabled,
+                        });
+                    }
+                }
+                TestClass::HttpDefaults(defaults, ..) if lowering.enabled(defaults.enabled) => {
+                    scope.defaults.push(defaults.clone())
+                }
+                TestClass::DnsCacheManager(manager, ..) if lowering.enabled(manager.enabled) => {
+                    scope.resolver = manager.custom_resolver.then(|| Resolver {
+                        hosts: manager
+                            .hosts
+                            .iter()
+                            .map(|host| StaticHost {
+                                name: host.name.trim().to_string(),
+                                addresses: host
+                                    .address
+                                    .split(',')
+                                    .map(str::trim)
+                                    .filter(|address| !address.is_empty())
+                                    .map(String::from)
+                                    .collect(),
+                            })
+                            .collect(),
+                        servers: manager.servers.clone(),
+                        clear_each_iteration: manager.clear_each_iteration,
+                    })
+                }
+                TestClass::ConstantTimer(timer, ..) => {
+                    scope.waits.push(Wait::Constant(lowered!(lowering, timer)))
+                }
+                TestClass::UniformRandomTimer(timer, ..) => {
+                    scope.waits.push(Wait::Uniform(lowered!(lowering, timer)))
+                }
+                TestClass::GaussianRandomTimer(timer, ..) => {
+                    scope.waits.push(Wait::Gaussian(lowered!(lowering, timer)))
+                }
+                TestClass::UserParameters(parameters, ..) if !parameters.per_iteration => {
+                    scope.user_parameters.push(lowered!(lowering, parameters))
+                }
+                TestClass::Script(script, ..) if script.kind == ScriptKind::PreProcessor => {
+                    scope.pre_processors.push(lowered!(lowering, script))
+                }
+                TestClass::Script(script, ..) if script.kind == ScriptKind::PostProcessor => scope
+                    .post_processors
+                    .push(PostProcessor::Script(lowered!(lowering, script))),
+                TestClass::BoundaryExtractor(e, ..) => scope
+                    .post_processors
+                    .push(PostProcessor::Boundary(lowered!(lowering, e))),
+                TestClass::XPath2Extractor(e, ..) => scope
+                    .post_processors
+                    .push(PostProcessor::XPath2(lowered!(lowering, e))),
+                TestClass::HtmlExtractor(e, ..) => scope
+                    .post_processors
+                    .push(PostProcessor::Html(lowered!(lowering, e))),
+                TestClass::ResponseAssertion(a, ..) => scope.assertions.push(lowered!(lowering, a)),
+                _ => {}
+            }
+        }
+        scope
+    }
+}
+
+impl LoadTest {
+    /// The load test of the enabled elements of the plan.
+    pub fn lower(root: &TestClass) -> Self {
+        Self::lower_with(root, false)
+    }
+
+    /// The load test of the whole plan, disabled elements included.
+    pub fn lower_including_disabled(root: &TestClass) -> Self {
+        Self::lower_with(root, true)
+    }
+
+    fn lower_with(root: &TestClass, include_disabled: bool) -> Self {
+        let lowering = Lowering {
+            include_disabled,
+            disabled: false,
+        };
+        let (name, serialize_threadgroups) = match root {
+            TestClass::TestPlan(plan, ..) => (plan.test_name.clone(), plan.serialize_threadgroups),
+            _ => (String::new(), false),
+        };
+        let variables = root.global_variables();
+        let scope = Scope::default().enter(root.children(), lowering);
+        // Negative alias indexes count as 0
+        let index =
+            |index: &Value<i64>| resolved(index, &variables).map(|index| index.max(0) as usize);
+        let keystore = keystore_config(root).map(|config| Keystore {
+            alias_var: Some(config.client_cert_alias_var_name.trim().to_string())
+                .filter(|name| !name.is_empty()),
+            start_index: index(&config.start_index),
+            end_index: index(&config.end_index),
+        });
+        // Plan-level sources are copied into every thread group, keeping
+        // their number
+        let (mut plan_sources, mut generators) = (vec![], 0);
+        data_sources(
+            root.children(),
+            lowering,
+            &mut generators,
+            &mut plan_sources,
+        );
+        let scenarios: Vec<Scenario> = root
+            .children()
+            .iter()
+            .filter_map(|node| {
+                let (enabled, on_sample_error, load) = match node {
+                    TestClass::ThreadGroup(group, ..) => (
+                        group.enabled,
+                        &group.on_sample_error,
+                        Load::lower(group, &variables),
+                    ),
+                    TestClass::UltimateThreadGroup(group, ..) => (
+                        group.enabled,
+                        &group.on_sample_error,
+                        Load::lower_ultimate(group, &variables),
+                    ),
+                    TestClass::ConcurrencyThreadGroup(group, ..) => (
+                        group.enabled,
+                        &group.on_sample_error,
+                        Load::lower_concurrency(group, &variables),
+                    ),
+                    TestClass::SteppingThreadGroup(group, ..) => (
+                        group.enabled,
+                        &group.on_sample_error,
+                        Load::lower_stepping(group, &variables),
+                    ),
+                    TestClass::ArrivalsThreadGroup(group, ..) => (
+                        group.enabled,
+                        &group.on_sample_error,
+                        Load::lower_arrivals(group, &variables),
+                    ),
+                    _ => return None,
+                };
+                let lowering = lowering.enter(node)?;
+                let mut sources = plan_sources.clone();
+                data_sources(node.children(), lowering, &mut generators, &mut sources);
+                let mut steps = vec![];
+                lower_steps(node.children(), lowering, &scope, &mut steps);
+                Some(Scenario {
+                    name: node.thread_group_name()?.to_string(),
+                    enabled: lowering.enabled(enabled),
+                    load,
+                    on_error: match on_sample_error.as_str() {
+                        "startnextloop" => OnError::StartNextIteration,
+                        "stopthread" => OnError::StopUser,
+                        "stoptest" => OnError::StopTest,
+                        "stoptestnow" => OnError::StopTestNow,
+                        _ => OnError::Continue,
+                    },
+                    data_sources: sources,
+                    connect_timeout_ms: connect_timeout(&steps),
+                    resolver: steps.iter().find_map(|step| match step {
+                        Step::Request(request) if request.enabled => request.resolver.clone(),
+                        _ => None,
+                    }),
+                    keystore: keystore.clone(),
+                    steps,
+                })
+            })
+            .collect();
+        let (mut unsupported, mut skipped) = (vec![], vec![]);
+        survey(root, &mut unsupported, &mut skipped);
+        let mut approximated = scenarios
+            .iter()
+            .filter(|s| s.enabled && s.load.arrivals.is_some())
+            .map(|s| {
+                format!(
+                    "<com.blazemeter.jmeter.threads.arrivals.ArrivalsThreadGroup> {}: iterations \
+                     start at the target rate only while one of its {} users is free",
+                    s.name, s.load.users
+                )
+            })
+            .collect::<Vec<String>>();
+        for scenario in scenarios.iter().filter(|s| s.enabled) {
+            approximated.extend(scenario.requests().filter(|r| r.enabled).flat_map(|r| {
+                let label = format!("<HTTPSamplerProxy> {}", r.name);
+                let connect_timeout =
+                    (r.connect_timeout_ms != scenario.connect_timeout_ms).then(|| match &scenario
+                        .connect_timeout_ms
+                    {
+                        Some(timeout) => {
+                            format!("{label}: connects with its thread group's {timeout}ms timeout")
+                        }
+                        None => format!("{label}: connects without its thread group's timeout"),
+                    });
+                let implementation = (r.implementation == "Java").then(|| {
+                    format!("{label}: sent with reqwest rather than the Java implementation")
+                });
+                let embedded = r.embedded.as_ref().filter(|e| e.pool.is_some()).map(|_| {
+                    format!(
+                        "{label}: embedded resources fetched in parallel are left out of \
+                             Goose's metrics"
+                    )
+                });
+                let resolver = (r.resolver != scenario.resolver)
+                    .then(|| format!("{label}: resolves host names like its thread group's users"));
+                [connect_timeout, implementation, embedded, resolver]
+                    .into_iter()
+                    .flatten()
+            }));
+        }
+        name_servers(root, &mut approximated);
+        extraction_scopes(root, &mut approximated);
+        transactions(root, &mut approximated);
+        if let Some(config) = keystore_config(root).filter(|_| scenarios.iter().any(|s| s.enabled))
+        {
+            let label = format!("<KeystoreConfig> {}", config.test_name);
+            approximated.push(format!(
+                "{label}: aliases are the files of a javax.net.ssl.keyStore directory, sorted, \
+                 rather than the entries of a keystore"
+            ));
+            if let Some(alias_var) = keystore.as_ref().and_then(|k| k.alias_var.as_ref()) {
+                approximated.push(format!(
+                    "{label}: users read their alias from ${{{alias_var}}} once, when they start"
+                ));
+            }
+        }
+        let enabled = scenarios.iter().filter(|s| s.enabled).collect::<Vec<_>>();
+        if serialize_threadgroups {
+            if let [before @ .., _] = enabled.as_slice() {
+                approximated.extend(
+                    before
+                        .iter()
+                        .filter(|s| {
+                            s.load.profile.is_none()
+                                && s.load.arrivals.is_none()
+                                && s.load.duration_secs.is_none()
+                        })
+                        .map(|s| {
+                            format!(
+                                "<ThreadGroup> {}: has no scheduler duration, so the thread \
+                                 groups after it start once all its users have",
+                                s.name
+                            )
+                        }),
+                );
+            }
+        }
+        if (!serialize_threadgroups || enabled.len() == 1)
+            && enabled.iter().all(|s| s.load.profile.is_none())
+        {
+            shared_load(&enabled, &mut approximated);
+        }
+        Self {
+            name,
+            variables,
+            properties: HashMap::new(),
+            system_properties: HashMap::new(),
+            scenarios,
+            serialize_threadgroups,
+            unsupported,
+            skipped,
+            approximated,
+        }
+    }
+}
+
+impl Load {
+    fn lower(group: &ThreadGroup, variables: &HashMap<String, String>) -> Self {
+        let resolve = |value: &Value<i32>| resolved(value, variables);
+        let seconds = |value: &Option<Value<i32>>| {
+            value
+                .as_ref()
+                .filter(|_| group.scheduler)
+                .map(|v| resolve(v).map(|v| v.max(0) as u64))
+        };
+        Self {
+            users: resolve(&group.num_threads).map(|v| v.max(0) as usize),
+            ramp_up_secs: resolve(&group.ramp_time).map(|v| v.max(0) as u64),
+            iterations: iterations(resolve(&group.loops)),
+            duration_secs: seconds(&group.duration),
+            delay_secs: seconds(&group.delay),
+            profile: None,
+            arrivals: None,
+        }
+    }
+
+    /// `users` is the sum of the rows' users, the first row's expression when
+    /// one is not a literal.
+    fn lower_ultimate(group: &UltimateThreadGroup, variables: &HashMap<String, String>) -> Self {
+        let resolve = |value: &Value<u64>| resolved(value, variables);
+        let rows = group
+            .schedule
+            .iter()
+            .map(|row| ProfileRow {
+                users: resolve(&row.threads),
+                delay_secs: resolve(&row.initial_delay),
+                startup_secs: resolve(&row.startup_time),
+                hold_secs: resolve(&row.hold_load_for),
+                shutdown_secs: resolve(&row.shutdown_time),
+            })
+            .collect::<Vec<ProfileRow>>();
+        let users = match rows.iter().find(|row| row.users.literal().is_none()) {
+            Some(row) => row.users.clone().map(|v| v as usize),
+            None => Value::Literal(
+                rows.iter()
+                    .filter_map(|row| row.users.literal())
+                    .sum::<u64>() as usize,
+            ),
+        };
+        Self {
+            users,
+            ramp_up_secs: rows
+                .first()
+                .map_or(Value::Literal(0), |row| row.startup_secs.clone()),
+            iterations: iterations(resolved(&group.loops, variables)),
+            duration_secs: None,
+            delay_secs: None,
+            profile: Some(Profile::Rows(rows)),
+            arrivals: None,
+        }
+    }
+
+    fn lower_concurrency(
+        group: &ConcurrencyThreadGroup,
+        variables: &HashMap<String, String>,
+    ) -> Self {
+        let resolve = |value: &Value<u64>| resolved(value, variables);
+        let unit_secs = group.unit_secs();
+        Self {
+            users: resolve(&group.target_level).map(|v| v as usize),
+            ramp_up_secs: resolve(&group.ramp_up).map(|v| v * unit_secs),
+            iterations: group.iterations.as_ref().map(resolve),
+            duration_secs: None,
+            delay_secs: None,
+            profile: Some(Profile::Steps {
+                users: resolve(&group.target_level),
+                ramp_up: resolve(&group.ramp_up),
+                steps: resolve(&group.steps),
+                hold: resolve(&group.hold),
+                unit_secs,
+            }),
+            arrivals: None,
+        }
+    }
+
+    /// `users` and `ramp_up_secs` are the group's total users and the time
+    /// until the last one has started.
+    fn lower_stepping(group: &SteppingThreadGroup, variables: &HashMap<String, String>) -> Self {
+        let resolve = |value: &Value<u64>| resolved(value, variables);
+        let users = resolve(&group.num_threads);
+        let burst = resolve(&group.start_users_burst);
+        let batch = resolve(&group.start_users_count);
+        let period = resolve(&group.start_users_period);
+        let ramp_up = resolve(&group.ramp_up);
+        let ramp_up_secs = match [&users, &burst, &batch, &period, &ramp_up].map(Value::literal) {
+            [Some(&users), Some(&burst), Some(&batch), Some(&period), Some(&ramp_up)] => {
+                let batch = if batch == 0 { users } else { batch };
+                let burst = if burst == 0 { batch } else { burst.min(users) };
+                let batches = users.saturating_sub(burst).div_ceil(batch.max(1));
+                Value::Literal(batches * (period + ramp_up) + ramp_up)
+            }
+            _ => ramp_up.clone(),
+        };
+        Self {
+            users: users.clone().map(|v| v as usize),
+            ramp_up_secs,
+            iterations: iterations(resolved(&group.loops, variables)),
+            duration_secs: None,
+            delay_secs: None,
+            profile: Some(Profile::Stepping {
+                users,
+                delay: resolve(&group.initial_delay),
+                burst,
+                batch,
+                period,
+                ramp_up,
+                hold: resolve(&group.flight_time),
+                stop_batch: resolve(&group.stop_users_count),
+                stop_period: resolve(&group.stop_users_period),
+            }),
+            arrivals: None,
+        }
+    }
+
+    /// `users` is the group's concurrency limit. Without one it is enough
+    /// users for iterations of up to 10s at the target rate, or the rate
+    /// itself when that is an expression.
+    fn lower_arrivals(group: &ArrivalsThreadGroup, variables: &HashMap<String, String>) -> Self {
+        let resolve = |value: &Value<u64>| resolved(value, variables);
+        let unit_secs = group.unit_secs();
+        let rate = resolve(&group.target_level);
+        let users = match &group.concurrency_limit {
+            Some(limit) => resolve(limit).map(|v| v as usize),
+            None => rate
+                .clone()
+                .map(|rate| (rate * 10).div_ceil(unit_secs).max(1) as usize),
+        };
+        Self {
+            users,
+            ramp_up_secs: Value::Literal(0),
+            iterations: group.iterations.as_ref().map(resolve),
+            duration_secs: None,
+            delay_secs: None,
+            profile: None,
+            arrivals: Some(Arrivals {
+                rate,
+                ramp_up: resolve(&group.ramp_up),
+                steps: resolve(&group.steps),
+                hold: resolve(&group.hold),
+                unit_secs,
+            }),
+        }
+    }
+}
+
+/// JMeter's loop count as iterations, `None` looping forever.
+fn iterations(loops: Value<i32>) -> Option<Value<u64>> {
+    match loops {
+        Value::Literal(loops) => u64::try_from(loops).ok().map(Value::Literal),
+        Value::Expression(expression) => Some(Value::Expression(expression)),
+    }
+}
+
+impl Request {
+    /// Blank settings of the sampler are taken from the HTTP Request
+    /// Defaults in scope, the innermost first, as JMeter does.
+    fn lower(sampler: &HttpSampler, lowering: Lowering, scope: Scope) -> Self {
+        let defaulted = |value: &str, default: fn(&HttpDefaults) -> &str| {
+            std::iter::once(value)
+                .chain(scope.defaults.iter().rev().map(default))
+                .find(|value| !value.is_empty())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let or = |value: String, default: &str| {
+            if value.is_empty() {
+                default.to_string()
+            } else {
+                value
+            }
+        };
+        // 0 is no timeout, as is what isn't a number
+        let timeout = |value: String| {
+            value
+                .parse::<Value<u64>>()
+                .ok()
+                .filter(|timeout| timeout.literal() != Some(&0))
+        };
+        let mut arguments = sampler.arguments.clone();
+        for defaults in scope.defaults.iter().rev() {
+            for argument in &defaults.arguments {
+                if !arguments.iter().any(|a| a.name == argument.name) {
+                    arguments.push(argument.clone());
+                }
+            }
+        }
+        Self {
+            name: sampler.test_name.clone(),
+            enabled: lowering.enabled(sampler.enabled),
+            method: or(sampler.method.clone(), "GET"),
+            protocol: or(defaulted(&sampler.protocol, |d| &d.protocol), "http"),
+            host: defaulted(&sampler.domain, |d| &d.domain),
+            port: defaulted(&sampler.port, |d| &d.port),
+            path: defaulted(&sampler.path, |d| &d.path),
+            headers: scope.headers,
+            arguments: if sampler.post_body_raw {
+                vec![]
+            } else {
+                arguments
+            },
+            body: sampler.raw_body(),
+            files: sampler.files.clone(),
+            multipart: sampler.is_multipart(),
+            content_encoding: defaulted(&sampler.content_encoding, |d| &d.content_encoding),
+            follow_redirects: sampler.follow_redirects,
+            auto_redirects: sampler.auto_redirects,
+            keep_alive: sampler.use_keepalive,
+            connect_timeout_ms: timeout(defaulted(&sampler.connect_timeout, |d| {
+                &d.connect_timeout
+            })),
+            response_timeout_ms: timeout(defaulted(&sampler.response_timeout, |d| {
+                &d.response_timeout
+            })),
+            implementation: defaulted(&sampler.implementation, |d| &d.implementation),
+            embedded: (sampler.image_parser || scope.defaults.iter().any(|d| d.image_parser)).then(
+                || Embedded {
+                    url_re: defaulted(&sampler.embedded_url_re, |d| &d.embedded_url_re),
+                    pool: (sampler.concurrent_dwn
+                        || scope.defaults.iter().any(|d| d.concurrent_dwn))
+                    .then(|| {
+                        defaulted(&sampler.concurrent_pool, |d| &d.concurrent_pool)
+                            .trim()
+                            .parse()
+                            .unwrap_or(6)
+                    }),
+                },
+            ),
+            resolver: scope.resolver,
+            waits: scope.waits,
+            user_parameters: scope.user_parameters,
+            pre_processors: scope.pre_processors,
+            post_processors: scope.post_processors,
+            assertions: scope.assertions,
+        }
+    }
+}
+
+/// The longest connect timeout of the enabled requests among `steps`,
+/// `None` when one of them has none. Which expression is longest isn't
+/// known, so the first is taken.
+fn connect_timeout(steps: &[Step]) -> Option<Value<u64>> {
+    let timeouts = steps
+        .iter()
+        .filter_map(|step| match step {
+            Step::Request(request) if request.enabled => Some(request.connect_timeout_ms.as_ref()),
+            _ => None,
+        })
+        .collect::<Option<Vec<&Value<u64>>>>()?;
+    timeouts
+        .iter()
+        .find(|timeout| timeout.literal().is_none())
+        .or_else(|| timeouts.iter().max_by_key(|timeout| timeout.literal()))
+        .map(|timeout| (*timeout).clone())
+}
+
+/// `value` with the plan's variables substituted, a literal when that makes
+/// it one.
+fn resolved<T: FromStr + Clone>(value: &Value<T>, variables: &HashMap<String, String>) -> Value<T> {
+    match value {
+        Value::Expression(expression) => {
+            let expression = substitute(expression, variables);
+            match expression.trim().parse::<T>() {
+                Ok(literal) => Value::Literal(literal),
+                Err(_) => Value::Expression(expression),
+            }
+        }
+        literal => literal.clone(),
+    }
+}
+
+/// Collects the data sources among `nodes` and below, leaving out thread
+/// groups, which collect their own. Generators take their number from
+/// `generators`, the count so far.
+fn data_sources(
+    nodes: &[TestClass],
+    lowering: Lowering,
+    generators: &mut usize,
+    sources: &mut Vec<DataSource>,
+) {
+    for node in nodes {
+        let Some(lowering) = lowering.enter(node) else {
+            continue;
+        };
+        match node {
+            _ if node.thread_group_name().is_some() => continue,
+            TestClass::UserParameters(p, ..) if p.per_iteration => {
+                sources.push(DataSource::UserParameters(lowered!(lowering, p)))
+            }
+            TestClass::RandomVariableConfig(c, ..) => {
+                sources.push(DataSource::Random(lowered!(lowering, c), *generators));
+                *generators += 1;
+            }
+            TestClass::CounterConfig(c, ..) => {
+                sources.push(DataSource::Counter(lowered!(lowering, c), *generators));
+                *generators += 1;
+            }
+            _ => {}
+        }
+        data_sources(node.children(), lowering, generators, sources);
+    }
+}
+
+fn lower_steps(nodes: &[TestClass], lowering: Lowering, outer: &Scope, steps: &mut Vec<Step>) {
+    let scope = outer.enter(nodes, lowering);
+    for node in nodes {
+        let Some(lowering) = lowering.enter(node) else {
+            continue;
+        };
+        match node {
+            TestClass::HttpSampler(sampler, children, _) => steps.push(Step::Request(Box::new(
+                Request::lower(sampler, lowering, scope.enter(children, lowering)),
+            ))),
+            TestClass::Script(script, ..) if script.kind == ScriptKind::Sampler => {
+                steps.push(Step::Script(lowered!(lowering, script)))
+            }
+            // Both run their children once in order, scoping the elements
+            // among them to their samplers
+            TestClass::SimpleController(_, children, _)
+            | TestClass::TransactionController(_, children, _) => {
+                lower_steps(children, lowering, &scope, steps)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The first enabled Keystore Configuration, which JMeter applies to the
+/// whole test wherever it is.
+fn keystore_config(node: &TestClass) -> Option<&KeystoreConfig> {
+    if !node.enabled() {
+        return None;
+    }
+    match node {
+        TestClass::KeystoreConfig(config, ..) => Some(config),
+        _ => node.children().iter().find_map(keystore_config),
+    }
+}
+
+/// Notes the enabled DNS Cache Managers resolving with their own name
+/// servers, which the system's take the place of.
+fn name_servers(node: &TestClass, approximated: &mut Vec<String>) {
+    if !node.enabled() {
+        return;
+    }
+    if let TestClass::DnsCacheManager(manager, ..) = node {
+        if manager.custom_resolver && !manager.servers.is_empty() {
+            approximated.push(format!(
+                "<DNSCacheManager> {}: resolves host names without a static host with the \
+                 system's DNS rather than {}",
+                manager.test_name,
+                manager.servers.join(", ")
+            ));
+        }
+    }
+    node.children()
+        .iter()
+        .for_each(|c| name_servers(c, approximated));
+}
+
+/// Notes the extractors reading what Goose's responses only approximate.
+fn extraction_scopes(node: &TestClass, approximated: &mut Vec<String>) {
+    if !node.enabled() {
+        return;
+    }
+    match node {
+        TestClass::BoundaryExtractor(e, ..) if e.use_headers == "as_document" => {
+            approximated.push(format!(
+                "<BoundaryExtractor> {}: searches the body as text rather than the text Tika \
+                 extracts from documents",
+                e.test_name
+            ))
+        }
+        TestClass::BoundaryExtractor(e, ..) if e.use_headers == "request_headers" => approximated
+            .push(format!(
+                "<BoundaryExtractor> {}: searches the request headers Goose built, without \
+                 those the client adds such as cookies",
+                e.test_name
+            )),
+        TestClass::XPath2Extractor(e, ..) if e.fragment => approximated.push(format!(
+            "<XPath2Extractor> {}: extracts the text of matching nodes rather than XML fragments",
+            e.test_name
+        )),
+        _ => {}
+    }
+    node.children()
+        .iter()
+        .for_each(|c| extraction_scopes(c, approximated));
+}
+
+/// Notes what the thread groups of `scenarios` lose by starting together
+/// under one set of Goose defaults: a single hatch rate over the longest
+/// ramp-up, the longest run time, and iterations only when all groups loop
+/// the same number of times without a run time.
+fn shared_load(scenarios: &[&Scenario], approximated: &mut Vec<String>) {
+    let differ = |key: &dyn Fn(&Load) -> String| {
+        scenarios
+            .windows(2)
+            .any(|pair| key(&pair[0].load) != key(&pair[1].load))
+    };
+    // Seconds when literal, for thread groups that use the scheduler
+    let run_time = |load: &Load| match (&load.arrivals, &load.duration_secs) {
+        (Some(a), _) => Some(
+            a.ramp_up
+                .literal()
+                .zip(a.hold.literal())
+                .map(|(ramp_up, hold)| (ramp_up + hold) * a.unit_secs),
+        ),
+        (None, duration) => duration.as_ref().map(|d| d.literal().copied()),
+    };
+    let run_times = scenarios
+        .iter()
+        .filter_map(|s| run_time(&s.load))
+        .collect::<Vec<_>>();
+    let timed = !run_times.is_empty();
+    let scheduled_groups = run_times.len();
+    let longest = run_times
+        .into_iter()
+        .collect::<Option<Vec<_>>>()
+        .and_then(|secs| secs.into_iter().max());
+    let ramp_ups_differ = differ(&|l| l.ramp_up_secs.to_string());
+    let longest_ramp_up = scenarios
+        .iter()
+        .map(|s| s.load.ramp_up_secs.literal().copied())
+        .collect::<Option<Vec<_>>>()
+        .and_then(|secs| secs.into_iter().max());
+    let iterations_differ = differ(&|l| format!("{:?}", l.iterations));
+    for scenario in scenarios {
+        let label = format!("<ThreadGroup> {}", scenario.name);
+        let load = &scenario.load;
+        if ramp_ups_differ
+            && (longest_ramp_up.is_none() || load.ramp_up_secs.literal() < longest_ramp_up.as_ref())
+        {
+            approximated.push(format!(
+                "{label}: its users start at one hatch rate for all thread groups, over the \
+                 longest ramp-up rather than its {}s",
+                load.ramp_up_secs
+            ));
+        }
+        match run_time(load) {
+            None if timed => approximated.push(format!(
+                "{label}: stops when the longest scheduled thread group does"
+            )),
+            Some(secs) if scheduled_groups > 1 && (longest.is_none() || secs < longest) => {
+                approximated.push(format!(
+                    "{label}: runs as long as the longest scheduled thread group"
+                ))
+            }
+            _ => {}
+        }
+        if let Some(iterations) = &load.iterations {
+            if timed {
+                approximated.push(format!(
+                    "{label}: loops until the run time ends rather than {iterations} times"
+                ));
+            } else if iterations_differ {
+                approximated.push(format!(
+                    "{label}: loops until stopped rather than {iterations} times, as the thread \
+                     groups loop different numbers of times"
+                ));
+            }
+        }
+    }
+}
+
+/// Notes the enabled Transaction Controllers, whose samplers are lowered in
+/// their place.
+fn transactions(node: &TestClass, approximated: &mut Vec<String>) {
+    if !node.enabled() {
+        return;
+    }
+    if let TestClass::TransactionController(controller, ..) = node {
+        approximated.push(format!(
+            "<TransactionController> {}: its samplers are reported one by one, without a \
+             sample for the whole transaction",
+            controller.test_name
+        ));
+    }
+    node.children()
+        .iter()
+        .for_each(|c| transactions(c, approximated));
+}
+
+/// Lists the unknown elements and the disabled ones, not looking beneath
+/// the latter.
+fn survey(node: &TestClass, unsupported: &mut Vec<String>, skipped: &mut Vec<String>) {
+    let label = || {
+        let e = node.to_element();
+        let name = e.attributes.get("testname").cloned().unwrap_or_default();
+        format!("<{}> {name}", e.name)
+    };
+    if !node.enabled() {
+        skipped.push(label());
+        return;
+    }
+    if let TestClass::Unknown(..) = node {
+        unsupported.push(label());
+    }
+    node.children()
+        .iter()
+        .for_each(|c| survey(c, unsupported, skipped));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        parse_properties, DataSource, Embedded, GooseCodegen, Header, Keystore, LoadTest, OnError,
+        Profile, Request, Resolver, Scenario, StaticHost, Step, TestClass, Value, Wait,
+    };
+    use xmltree::Element;
+
+    fn lower(path: &str) -> LoadTest {
+        let xml = Element::parse(std::fs::read_to_string(path).unwrap().trim().as_bytes()).unwrap();
+        LoadTest::lower(&TestClass::root(&xml).unwrap())
+    }
+
+    #[test]
+    fn check_lower_user_variables() {
+        let test = lower("./unittests_data/test_class/user_variables.jmx");
+        assert_eq!(test.name, "Test Plan");
+        assert_eq!(test.variables["protocol"], "https");
+        let names = test
+            .scenarios
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, ["Users", "Admins"]);
+        let users = &test.scenarios[0];
+        assert_eq!(users.load.users, Value::Literal(2));
+        assert_eq!(users.load.ramp_up_secs, Value::Literal(1));
+        assert_eq!(users.load.iterations, Some(Value::Literal(1)));
+        assert_eq!(users.load.duration_secs, None);
+        assert_eq!(users.on_error, OnError::Continue);
+        // Its User Parameters update before every sampler, of which it has
+        // none
+        assert!(users.data_sources.is_empty());
+        assert!(test.scenarios[1].data_sources.is_empty());
+        assert!(test.unsupported.is_empty());
+    }
+
+    #[test]
+    fn check_lower_shared_generators() {
+        let test = lower("./unittests_data/ir/shared_generators.jmx");
+        let numbers = |scenario: &Scenario| {
+            scenario
+                .data_sources
+                .iter()
+                .map(|source| match source {
+                    DataSource::Random(e, n) => format!("{} {n}", e.test_name),
+                    DataSource::Counter(e, n) => format!("{} {n}", e.test_name),
+                    DataSource::UserParameters(e) => e.test_name.clone(),
+                })
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(numbers(&test.scenarios[0]), ["Order id 0", "Coupon 1"]);
+        assert_eq!(
+            numbers(&test.scenarios[1]),
+            ["Order id 0", "Coupon 1", "Page 2"]
+        );
+
+        // One counter and generator for both thread groups
+        let main = test.to_goose();
+        for (state, uses) in [
+            ("static COUNTER_0: std::sync::atomic::AtomicI64", 1),
+            ("COUNTER_0.fetch_add(", 2),
+            ("static RANDOM_1: std::sync::OnceLock<", 1),
+            ("RANDOM_1.get_or_init(", 2),
+            ("static COUNTER_2: std::sync::atomic::AtomicI64", 1),
+            ("COUNTER_2.fetch_add(", 1),
+        ] {
+            assert_eq!(main.matches(state).count(), uses, "{state}");
+        }
+    }
+
+    #[test]
+    fn check_lower_user_parameters() {
+        let test = lower("./unittests_data/ir/user_parameters.jmx");
+        let buyers = &test.scenarios[0];
+        let [DataSource::UserParameters(basket)] = buyers.data_sources.as_slice() else {
+            panic!("expected the per-iteration User Parameters");
+        };
+        assert_eq!(basket.test_name, "Basket");
+        let requests = buyers.requests().collect::<Vec<_>>();
+        assert!(requests[0].user_parameters.is_empty());
+        assert_eq!(requests[1].user_parameters[0].test_name, "Coupon");
+
+        let main = test.to_goose();
+        // Once per iteration, before the steps
+        assert!(main.contains(
+            "    {\n        let props = &mut *PROPS.lock().unwrap();\n        {\n            let names: [&str; 1] = [\"sku\"];"
+        ));
+        assert!(main.contains("let _ = buyers_log_in(user, vars).await;"));
+        assert!(
+            main.find("let names: [&str; 1] = [\"sku\"];")
+                < main.find("let _ = buyers_log_in(user, vars).await;")
+        );
+        // Before every request in scope
+        assert!(main.contains(
+            "async fn buyers_start_checkout(user: &mut GooseUser, vars: &mut HashMap<String, String>) -> TransactionResult {\n    {\n        let props = &mut *PROPS.lock().unwrap();\n        {\n            let names: [&str; 1] = [\"coupon\"];"
+        ));
+        assert!(!main.contains("let user_index"));
+        // Users take the columns in turn within their thread group
+        assert!(main.contains("columns[thread_number(user) % columns.len()]"));
+        assert!(!main.contains("weighted_users_index"));
+    }
+
+    #[test]
+    fn check_lower_scoped() {
+        let test = lower("./unittests_data/ir/scoped.jmx");
+        let browse = &test.scenarios[0];
+        assert_eq!(browse.load.iterations, None);
+        assert_eq!(browse.load.duration_secs, Some(Value::Literal(60)));
+        assert_eq!(browse.on_error, OnError::StartNextIteration);
+        let Step::Request(home) = &browse.steps[0] else {
+            panic!("expected a request");
+        };
+        assert_eq!(home.url(), "https://${host}/");
+        assert_eq!(
+            home.headers,
+            [Header {
+                name: String::from("Accept"),
+                value: String::from("text/html"),
+                enabled: true,
+            }]
+        );
+        let [Wait::Constant(pause)] = home.waits.as_slice() else {
+            panic!("expected the constant timer");
+        };
+        assert_eq!(pause.delay, "200");
+        assert_eq!(home.assertions.len(), 1);
+        assert_eq!(test.unsupported, ["<ResultCollector> View Results Tree"]);
+
+        let main = test.to_goose();
+        assert!(main.contains("async fn browse_home(user: &mut GooseUser"));
+        assert!(main.contains(".set_default(GooseDefault::RunTime, 60)?"));
+        assert!(main.contains("//! - <ResultCollector> View Results Tree"));
+        assert!(test.cargo_toml().contains("name = \"shop\""));
+    }
+
+    #[test]
+    fn check_lower_properties() {
+        let mut test = lower("./unittests_data/ir/properties.jmx");
+        assert!(test.properties.is_empty());
+        test.properties = parse_properties("threads=50\n");
+        let load = &test.scenarios[0].load;
+        assert_eq!(
+            load.users,
+            Value::Expression(String::from("${__P(threads,10)}"))
+        );
+        assert_eq!(load.ramp_up_secs, Value::Literal(4));
+        assert_eq!(
+            load.iterations,
+            Some(Value::Expression(String::from("${__P(loops,-1)}")))
+        );
+
+        let main = test.to_goose();
+        let users = r#"count(&property("threads", "10"))"#;
+        assert!(main.contains(&format!(".set_default(GooseDefault::Users, {users})?")));
+        assert!(main.contains(&format!(
+            ".set_default(GooseDefault::HatchRate, hatch_rate({users}, 4).as_str())?"
+        )));
+        assert!(!main.contains("with_iterations(configuration"));
+        assert!(main.contains(
+            r#".set_default(GooseDefault::RunTime, count(&property("duration", "60")))?"#
+        ));
+        assert!(main.contains(r#"("threads".to_string(), "50".to_string()),"#));
+        assert!(main.contains(r#"("threads".to_string(), read_properties("${__P(threads,10)}")),"#));
+    }
+
+    #[test]
+    fn check_lower_plugin_thread_groups() {
+        let test = lower("./unittests_data/ir/plugins.jmx");
+        let [spike, steady] = test.scenarios.as_slice() else {
+            panic!("expected 2 scenarios, found {:?}", test.scenarios);
+        };
+        assert_eq!(spike.name, "Spike");
+        assert_eq!(spike.load.users, Value::Literal(35));
+        assert_eq!(spike.load.iterations, None);
+        let Some(Profile::Rows(rows)) = &spike.load.profile else {
+            panic!("expected rows, found {:?}", spike.load.profile);
+        };
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1].delay_secs, Value::Literal(30));
+        assert_eq!(steady.name, "Steady");
+        assert_eq!(steady.load.users, Value::Literal(40));
+        assert_eq!(
+            steady.load.profile,
+            Some(Profile::Steps {
+                users: Value::Literal(40),
+                ramp_up: Value::Literal(2),
+                steps: Value::Literal(4),
+                hold: Value::Literal(10),
+                unit_secs: 60,
+            })
+        );
+
+        let main = test.to_goose();
+        assert!(main.contains("test_plan(&[vec![[10, 0, 0, 60, 0], [20, 30, 10, 20, 10], [5, 50, 5, 5, 5]], concurrency(40, 120, 4, 600)].concat()).as_str()"));
+        assert!(!main.contains("GooseDefault::Users"));
+    }
+
+    #[test]
+    fn check_lower_open_model() {
+        let test = lower("./unittests_data/ir/open_model.jmx");
+        let [staircase, arrivals] = test.scenarios.as_slice() else {
+            panic!("expected 2 scenarios, found {:?}", test.scenarios);
+        };
+        assert_eq!(staircase.load.users, Value::Literal(100));
+        assert_eq!(staircase.load.ramp_up_secs, Value::Literal(320));
+        assert!(matches!(
+            staircase.load.profile,
+            Some(Profile::Stepping {
+                batch: Value::Literal(10),
+                ..
+            })
+        ));
+        assert_eq!(arrivals.load.users, Value::Literal(20));
+        assert_eq!(arrivals.load.profile, None);
+        let Some(pacing) = &arrivals.load.arrivals else {
+            panic!("expected arrivals, found {:?}", arrivals.load);
+        };
+        assert_eq!(pacing.rate, Value::Literal(120));
+        assert_eq!(pacing.unit_secs, 60);
+        assert_eq!(test.approximated.len(), 1);
+        assert!(test.approximated[0].contains("Checkout arrivals"));
+
+        let main = test.to_goose();
+        assert!(main.contains(
+            r#"test_plan(&[stepping(100, 0, 0, 10, 30, 5, 60, 5, 1), vec![[20, 0, 0, 60 + count(&property("hold", "5")) * 60, 0]]].concat())"#
+        ));
+        assert!(main.contains(
+            r#".wait("Checkout arrivals", 2.0, 60.0, 0, (count(&property("hold", "5")) * 60) as f64)"#
+        ));
+        assert!(main.contains("//! Approximated:"));
+    }
+
+    #[test]
+    fn check_lower_serialized_thread_groups() {
+        let mut test = lower("./unittests_data/ir/serial.jmx");
+        assert!(test.serialize_threadgroups);
+        assert!(test.approximated.is_empty());
+
+        let main = test.to_goose();
+        assert!(main.contains(
+            "let (users, run_time) = serial(vec![vec![[10, 0, 10, 50, 0]], vec![[30, 0, 15, usize::MAX, 0]]]);"
+        ));
+        assert!(main.contains("let Some(_turn) = turn(0, &WARM_UP_BUSY) else {"));
+        assert!(main.contains("let Some(_turn) = turn(1, &PEAK_BUSY) else {"));
+        assert!(main.contains(".set_weight(1)?"));
+        assert!(main.contains(".set_weight(3)?"));
+        assert!(main.contains(".set_default(GooseDefault::RunTime, run_time)?"));
+
+        // Side by side, the groups only get weights
+        test.serialize_threadgroups = false;
+        let main = test.to_goose();
+        assert!(!main.contains("= serial(vec!["));
+        assert!(!main.contains("turn(0, &WARM_UP_BUSY)"));
+        assert!(main.contains(".set_weight(3)?"));
+        assert!(main.contains(".set_default(GooseDefault::Users, 40)?"));
+
+        // Without its scheduler, when the first group ends is unknown
+        let jmx = std::fs::read_to_string("./unittests_data/ir/serial.jmx")
+            .unwrap()
+            .replace(
+                r#"<boolProp name="ThreadGroup.scheduler">true</boolProp>"#,
+                r#"<boolProp name="ThreadGroup.scheduler">false</boolProp>"#,
+            );
+        let test = LoadTest::lower(
+            &TestClass::root(&Element::parse(jmx.trim().as_bytes()).unwrap()).unwrap(),
+        );
+        assert_eq!(test.approximated.len(), 1);
+        assert!(test.approximated[0].starts_with("<ThreadGroup> Warm up: "));
+    }
+
+    #[test]
+    fn check_lower_side_by_side_thread_groups() {
+        // Peak loops 5 times, without a scheduler
+        let jmx = std::fs::read_to_string("./unittests_data/ir/serial.jmx")
+            .unwrap()
+            .replace(
+                r#"<boolProp name="TestPlan.serialize_threadgroups">true</boolProp>"#,
+                r#"<boolProp name="TestPlan.serialize_threadgroups">false</boolProp>"#,
+            );
+        let (warm_up, peak) = jmx.split_at(jmx.find(r#"testname="Peak""#).unwrap());
+        let jmx = warm_up.to_string()
+            + &peak.replacen(
+                r#"<intProp name="LoopController.loops">-1</intProp>"#,
+                r#"<intProp name="LoopController.loops">5</intProp>"#,
+                1,
+            );
+        let lower = |jmx: &str| {
+            LoadTest::lower(
+                &TestClass::root(&Element::parse(jmx.trim().as_bytes()).unwrap()).unwrap(),
+            )
+        };
+        let test = lower(&jmx);
+        assert_eq!(
+            test.approximated,
+            vec![
+                "<ThreadGroup> Warm up: its users start at one hatch rate for all thread groups, \
+                 over the longest ramp-up rather than its 10s",
+                "<ThreadGroup> Peak: stops when the longest scheduled thread group does",
+                "<ThreadGroup> Peak: loops until the run time ends rather than 5 times",
+            ]
+        );
+        assert!(test
+            .to_goose()
+            .contains(".set_default(GooseDefault::RunTime, 60)?"));
+
+        // Without Warm up's scheduler, only Peak has a loop count
+        let test = lower(&jmx.replace(
+            r#"<boolProp name="ThreadGroup.scheduler">true</boolProp>"#,
+            r#"<boolProp name="ThreadGroup.scheduler">false</boolProp>"#,
+        ));
+        assert_eq!(
+            test.approximated[1..],
+            ["<ThreadGroup> Peak: loops until stopped rather than 5 times, as the thread groups \
+              loop different numbers of times"]
+        );
+        assert!(!test.to_goose().contains("with_iterations(configuration"));
+    }
+
+    #[test]
+    fn check_lower_requests() {
+        let test = lower("./unittests_data/ir/requests.jmx");
+        let Step::Request(update) = &test.scenarios[0].steps[2] else {
+            panic!("expected a request");
+        };
+        assert_eq!(update.method, "PUT");
+        assert!(update.arguments.is_empty());
+        assert_eq!(update.body.as_deref(), Some(r#"{"name": "${user}"}"#));
+
+        let main = test.to_goose();
+        assert!(main.contains(
+            r#".query(&[("q", substitute("${term}", vars)), ("page", substitute("1", vars))])"#
+        ));
+        assert!(main.contains(r#".form(&[("user", substitute("${user}", vars))"#));
+        assert!(main.contains(".get_request_builder(&GooseMethod::Put, &url)?"));
+        assert!(main.contains(r#".body(substitute("{\"name\": \"${user}\"}", vars))"#));
+        assert!(main.contains(".name(\"Update profile\")"));
+        assert!(main.contains("user.client.request(reqwest::Method::OPTIONS, &url)"));
+        // On the user's client that doesn't follow redirects, sharing cookies
+        assert!(
+            main.contains("let mut goose = request_without_redirects(user, goose_request).await?;")
+        );
+        assert!(main.contains(".redirect(reqwest::redirect::Policy::none())"));
+    }
+
+    #[test]
+    fn check_lower_timeouts() {
+        let test = lower("./unittests_data/ir/timeouts.jmx");
+        let shoppers = &test.scenarios[0];
+        assert_eq!(shoppers.connect_timeout_ms, Some(Value::Literal(5000)));
+        let Step::Request(home) = &shoppers.steps[0] else {
+            panic!("expected a request");
+        };
+        // Blank settings come from the defaults
+        assert_eq!(home.url(), "https://shop.example.com/");
+        assert_eq!(home.arguments[0].name, "lang");
+        assert_eq!(home.connect_timeout_ms, Some(Value::Literal(5000)));
+        assert_eq!(
+            home.response_timeout_ms,
+            Some(Value::Expression(String::from("${__P(timeout,30000)}")))
+        );
+        assert_eq!(home.implementation, "HttpClient4");
+        let Step::Request(report) = &shoppers.steps[1] else {
+            panic!("expected a request");
+        };
+        assert_eq!(report.host, "reports.example.com");
+        assert_eq!(report.connect_timeout_ms, Some(Value::Literal(2000)));
+        assert_eq!(report.response_timeout_ms, Some(Value::Literal(120000)));
+        assert_eq!(
+            test.approximated,
+            vec![
+                "<HTTPSamplerProxy> Slow report: connects with its thread group's 5000ms timeout",
+                "<HTTPSamplerProxy> Slow report: sent with reqwest rather than the Java implementation",
+            ]
+        );
+
+        let main = test.to_goose();
+        assert!(main.contains(
+            "let settings = ClientSettings {\n        connect_timeout_ms: 5000,\n        ..Default::default()\n    };"
+        ));
+        assert!(main
+            .contains(r#"match millis(&read_properties("${__P(timeout,30000)}"), vars) as u64 {"#));
+        assert!(main.contains(
+            ".header(\"Connection\", \"close\")\n        .timeout(std::time::Duration::from_millis(120000))"
+        ));
+    }
+
+    #[test]
+    fn check_lower_dns() {
+        let test = lower("./unittests_data/ir/dns.jmx");
+        let shoppers = &test.scenarios[0];
+        let staging = Resolver {
+            hosts: vec![StaticHost {
+                name: String::from("shop.example.com"),
+                addresses: vec![String::from("10.0.0.5"), String::from("10.0.0.6")],
+            }],
+            servers: vec![String::from("10.0.0.53")],
+            clear_each_iteration: true,
+        };
+        assert_eq!(shoppers.resolver, Some(staging.clone()));
+        let requests = shoppers.requests().collect::<Vec<_>>();
+        assert_eq!(requests[0].resolver, Some(staging));
+        // The innermost manager resolves with the system's DNS
+        assert_eq!(requests[1].resolver, None);
+        assert_eq!(
+            test.approximated,
+            vec![
+                "<HTTPSamplerProxy> Partner offers: resolves host names like its thread group's users",
+                "<DNSCacheManager> Staging hosts: resolves host names without a static host with \
+                 the system's DNS rather than 10.0.0.53",
+            ]
+        );
+
+        let main = test.to_goose();
+        assert!(
+            main.contains(r#"static_hosts: &[("shop.example.com", &["10.0.0.5", "10.0.0.6"])],"#)
+        );
+        assert!(main.contains("user.set_client_builder(settings.builder(&jar)).await?;"));
+    }
+
+    #[test]
+    fn check_lower_keystore() {
+        let test = lower("./unittests_data/ir/keystore.jmx");
+        assert_eq!(
+            test.scenarios[0].keystore,
+            Some(Keystore {
+                alias_var: None,
+                start_index: Value::Literal(2),
+                end_index: Value::Expression(String::from("${__P(last_cert,9)}")),
+            })
+        );
+        assert_eq!(
+            test.approximated,
+            vec![
+                "<KeystoreConfig> Partner certificates: aliases are the files of a \
+                 javax.net.ssl.keyStore directory, sorted, rather than the entries of a keystore",
+            ]
+        );
+
+        let main = test.to_goose();
+        assert!(main.contains(
+            r#"let (first, last) = (2, count(&property("last_cert", "9")));
+    let index = first + thread % (last.saturating_sub(first) + 1);
+    let settings = ClientSettings {
+        identity: client_identity("", index).unwrap_or_else(|e| {"#
+        ));
+        // The redirect is sent with the same certificate
+        assert!(main.contains(
+            "let no_redirects = settings\n        .builder(&jar)\n        .redirect(reqwest::redirect::Policy::none())"
+        ));
+    }
+
+    #[test]
+    fn check_lower_embedded_resources() {
+        let test = lower("./unittests_data/ir/embedded.jmx");
+        let requests = test.scenarios[0].requests().collect::<Vec<_>>();
+        assert_eq!(
+            requests[0].embedded,
+            Some(Embedded {
+                url_re: String::from(r".*shop\.example\.com.*"),
+                pool: None,
+            })
+        );
+        assert_eq!(
+            requests[1].embedded,
+            Some(Embedded {
+                url_re: String::new(),
+                pool: Some(4),
+            })
+        );
+        assert_eq!(
+            test.approximated,
+            vec!["<HTTPSamplerProxy> Catalog: embedded resources fetched in parallel are left out of Goose's metrics"]
+        );
+
+        let main = test.to_goose();
+        assert!(main.contains(
+            r#"LazyLock::new(|| regex::Regex::new("^(?:.*shop\\.example\\.com.*)$").ok());"#
+        ));
+        assert!(main.contains(
+            "let resources = embedded_resources(headers, body, url, EMBEDDED_URL_RE.as_ref());\n    fetch_embedded(user, resources, None).await?;"
+        ));
+        assert!(main.contains(
+            "let resources = embedded_resources(headers, body, url, None);\n    fetch_embedded(user, resources, Some(4)).await?;"
+        ));
+    }
+
+    #[test]
+    fn check_lower_controllers() {
+        let test = lower("./unittests_data/ir/controllers.jmx");
+        let requests = test.scenarios[0].requests().collect::<Vec<_>>();
+        let names = requests
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect::<Vec<&str>>();
+        // The Loop Controller is left out with its sampler
+        assert_eq!(names, ["Home", "Catalog", "Cart", "Pay", "Log out"]);
+        // Elements in a controller only apply to its samplers
+        let accepts = |r: &Request| r.headers.iter().any(|h| h.name == "Accept");
+        assert!(accepts(requests[0]) && accepts(requests[1]));
+        assert!(!accepts(requests[2]) && !accepts(requests[4]));
+        let waits = requests
+            .iter()
+            .map(|r| r.waits.len())
+            .collect::<Vec<usize>>();
+        assert_eq!(waits, [0, 0, 1, 1, 0]);
+        assert_eq!(test.unsupported, ["<LoopController> Poll"]);
+        assert_eq!(
+            test.approximated,
+            ["<TransactionController> Checkout: its samplers are reported one by one, without a \
+              sample for the whole transaction"]
+        );
+    }
+
+    #[test]
+    fn check_lower_extraction_scopes() {
+        let test = lower("./unittests_data/ir/extractors.jmx");
+        assert_eq!(
+            test.approximated,
+            vec![
+                "<BoundaryExtractor> Invoice: searches the body as text rather than the text \
+                 Tika extracts from documents",
+                "<BoundaryExtractor> Accept: searches the request headers Goose built, without \
+                 those the client adds such as cookies",
+                "<XPath2Extractor> Items: extracts the text of matching nodes rather than XML \
+                 fragments",
+            ]
+        );
+
+        let main = test.to_goose();
+        for source in [
+            "body",
+            "unescape_html(body)",
+            "headers",
+            "request_headers(&goose.request)",
+            "url",
+            "status.to_string()",
+            "reqwest::StatusCode::from_u16(status)",
+        ] {
+            assert!(main.contains(&format!("let source = {source}")));
+        }
+    }
+
+    #[test]
+    fn check_lower_disabled() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/ir/disabled.jmx")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let root = TestClass::root(&xml).unwrap();
+        let skipped = [
+            "<HeaderManager> Defaults",
+            "<HTTPSamplerProxy> Checkout",
+            "<ThreadGroup> Admin",
+        ];
+
+        let test = LoadTest::lower(&root);
+        assert_eq!(test.skipped, skipped);
+        assert_eq!(test.unsupported, ["<ResultCollector> View Results Tree"]);
+        assert_eq!(test.scenarios.len(), 1);
+        let Step::Request(home) = &test.scenarios[0].steps[0] else {
+            panic!("expected a request");
+        };
+        assert!(home.headers.is_empty());
+        assert_eq!(test.scenarios[0].steps.len(), 1);
+        let main = test.to_goose();
+        assert!(main.contains("//! - <HTTPSamplerProxy> Checkout"));
+        assert!(!main.contains("fn browse_checkout"));
+
+        let test = LoadTest::lower_including_disabled(&root);
+        assert_eq!(test.skipped, skipped);
+        let [browse, admin] = test.scenarios.as_slice() else {
+            panic!("expected both thread groups");
+        };
+        assert!(browse.enabled && !admin.enabled);
+        assert!(browse.steps[0].enabled() && !browse.steps[1].enabled());
+        // Everything beneath a disabled element counts as disabled
+        assert!(!admin.steps[0].enabled());
+        let Step::Request(home) = &browse.steps[0] else {
+            panic!("expected a request");
+        };
+        assert!(!home.headers[0].enabled);
+        let main = test.to_goose();
+        assert!(main.contains("\n        // .header(\"Accept\", substitute(\"text/html\", vars))"));
+        assert!(main.contains("\n// async fn browse_checkout("));
+        assert!(main.contains("\n    // browse_checkout(user, vars).await?;"));
+        assert!(main.contains("\n        // .register_scenario("));
+        assert!(main.contains(".set_default(GooseDefault::Users, 10)?"));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::TestClass;
+use anyhow::bail;
+use std::io::Write;
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+/// The `<jmeterTestPlan>` document
+///
+/// Besides the `TestPlan` the top-level `<hashTree>` can hold more pairs,
+/// like the `WorkBench` JMeter 2.x saved next to it, and some plugins write
+/// elements of their own beside the `<hashTree>`. All of them are kept and
+/// written back.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JmeterTestPlan {
+    /// Version of the file format
+    pub version: String,
+    /// Version of the property format
+    pub properties: String,
+    /// JMeter release that saved the plan, e.g. `5.5` or `2.13 r1665067`,
+    /// see [`JmeterTestPlan::jmeter_version`]
+    pub jmeter: String,
+    /// The nodes of the top-level `<hashTree>`
+    pub nodes: Vec<TestClass>,
+    /// Other elements under `<jmeterTestPlan>`, kept verbatim
+    #[cfg_attr(feature = "serde", serde(with = "crate::xml_serde::many"))]
+    pub extra: Vec<Element>,
+}
+
+impl JmeterTestPlan {
+    /// A document holding `test_plan`, as saved by JMeter 5.5.
+    pub fn new(test_plan: TestClass) -> Self {
+        Self {
+            version: String::from("1.2"),
+            properties: String::from("5.0"),
+            jmeter: String::from("5.5"),
+            nodes: vec![test_plan],
+            extra: vec![],
+        }
+    }
+
+    /// Parses the document element. Comments, processing instructions and
+    /// text between elements are ignored.
+    pub fn parse(e: &Element) -> anyhow::Result<Self> {
+        if e.name != "jmeterTestPlan" {
+            bail!("expected <jmeterTestPlan>, found <{}>", e.name);
+        }
+        let attribute = |name: &str| e.attributes.get(name).cloned().unwrap_or_default();
+        let mut nodes = None;
+        let mut extra = vec![];
+        for child in e.children.iter().filter_map(XMLNode::as_element) {
+            match child.name.as_str() {
+                "hashTree" if nodes.is_none() => nodes = Some(TestClass::parse_nodes(child)?),
+                _ => extra.push(child.clone()),
+            }
+        }
+        let Some(nodes) = nodes else {
+            bail!("expected <hashTree> in <jmeterTestPlan>");
+        };
+        Ok(Self {
+            version: attribute("version"),
+            properties: attribute("properties"),
+            jmeter: attribute("jmeter"),
+            nodes,
+            extra,
+        })
+    }
+
+    /// Index of the `TestPlan` node, or of the first node of a plan without
+    /// one.
+    fn test_plan_index(&self) -> usize {
+        self.nodes
+            .iter()
+            .position(|n| matches!(n, TestClass::TestPlan(..)))
+            .unwrap_or(0)
+    }
+
+    /// The `TestPlan` node, or the first node of a plan without one.
+    pub fn test_plan(&self) -> Option<&TestClass> {
+        self.nodes.get(self.test_plan_index())
+    }
+
+    pub fn test_plan_mut(&mut self) -> Option<&mut TestClass> {
+        let index = self.test_plan_index();
+        self.nodes.get_mut(index)
+    }
+
+    /// Takes the node [`JmeterTestPlan::test_plan`] returns.
+    pub fn into_test_plan(mut self) -> anyhow::Result<TestClass> {
+        if self.nodes.is_empty() {
+            bail!("no test plan in <jmeterTestPlan>");
+        }
+        let index = self.test_plan_index();
+        Ok(self.nodes.swap_remove(index))
+    }
+
+    /// `(major, minor)` of the JMeter release that saved the plan, for
+    /// parsing that depends on it. `None` when the attribute is missing or
+    /// unreadable.
+    pub fn jmeter_version(&self) -> Option<(u32, u32)> {
+        let release = self.jmeter.split_whitespace().next()?;
+        let mut parts = release.split('.').map(|p| {
+            p.chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+                .parse::<u32>()
+        });
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().and_then(Result::ok).unwrap_or(0);
+        Some((major, minor))
+    }
+
+    /// The whole document, the counterpart of [`JmeterTestPlan::parse`].
+    pub fn to_document(&self) -> Element {
+        document(
+            [
+                ("version", &self.version),
+                ("properties", &self.properties),
+                ("jmeter", &self.jmeter),
+            ],
+            &self.nodes.iter().collect::<Vec<&TestClass>>(),
+            &self.extra,
+        )
+    }
+
+    /// Writes [`JmeterTestPlan::to_document`] indented the way JMeter saves
+    /// it.
+    pub fn write_jmx<W: Write>(&self, w: W) -> Result<(), xmltree::Error> {
+        write_document(self.to_document(), w)
+    }
+}
+
+/// A `<jmeterTestPlan>` with the given attributes, leaving out empty ones.
+pub(crate) fn document(
+    attributes: [(&str, &str); 3],
+    nodes: &[&TestClass],
+    extra: &[Element],
+) -> Element {
+    let mut root = Element::new("jmeterTestPlan");
+    for (name, value) in attributes.into_iter().filter(|(_, v)| !v.is_empty()) {
+        root.attributes.insert(name.to_string(), value.to_string());
+    }
+    let mut hash_tree = Element::new("hashTree");
+    for node in nodes {
+        hash_tree.children.push(XMLNode::Element(node.to_element()));
+        hash_tree.children.push(XMLNode::Element(node.hash_tree()));
+    }
+    root.children.push(XMLNode::Element(hash_tree));
+    root.children
+        .extend(extra.iter().cloned().map(XMLNode::Element));
+    root
+}
+
+pub(crate) fn write_document<W: Write>(
+    mut document: Element,
+    mut w: W,
+) -> Result<(), xmltree::Error> {
+    /// JMeter writes `<stringProp name=".."></stringProp>`, not `<stringProp/>`.
+    fn open_empty_props(e: &mut Element) {
+        if e.children.is_empty() && e.name.ends_with("Prop") {
+            e.children.push(XMLNode::Text(String::new()));
+        }
+        for child in e.children.iter_mut() {
+            if let XMLNode::Element(child) = child {
+                open_empty_props(child);
+            }
+        }
+    }
+
+    open_empty_props(&mut document);
+    let config = EmitterConfig::new()
+        .perform_indent(true)
+        .indent_string("  ")
+        .pad_self_closing(false);
+    document.write_with_config(&mut w, config)?;
+    writeln!(w)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{JmeterTestPlan, TestClass};
+    use std::fs::{read_to_string, File};
+    use xmltree::Element;
+
+    #[test]
+    fn check_parse_workbench() {
+        let path = "./unittests_data/jmeter_test_plan/workbench.jmx";
+        let xml = Element::parse(read_to_string(path).unwrap().as_bytes()).unwrap();
+        let document = JmeterTestPlan::parse(&xml).unwrap();
+        assert_eq!(
+            JmeterTestPlan::from_reader(File::open(path).unwrap()).unwrap(),
+            document
+        );
+        assert_eq!(document.version, "1.2");
+        assert_eq!(document.jmeter_version(), Some((2, 13)));
+        assert_eq!(document.nodes.len(), 2);
+        assert!(matches!(
+            document.test_plan(),
+            Some(TestClass::TestPlan(plan, ..)) if plan.test_name == "Legacy plan"
+        ));
+        assert_eq!(document.extra.len(), 1);
+        assert_eq!(document.extra[0].name, "pluginSettings");
+        assert_eq!(
+            TestClass::root(&xml).unwrap(),
+            document.into_test_plan().unwrap()
+        );
+
+        let document = JmeterTestPlan::parse(&xml).unwrap();
+        let mut written = vec![];
+        document.write_jmx(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written
+            .contains(r#"<jmeterTestPlan version="1.2" properties="2.8" jmeter="2.13 r1665067">"#));
+        assert!(written.contains(r#"<WorkBench guiclass="WorkBenchGui""#));
+        assert!(written.contains(r#"<pluginSettings name="example">"#));
+        // Comments are dropped, but the plan survives another round trip
+        assert!(!written.contains("<!--"));
+        let reparsed = JmeterTestPlan::parse(&Element::parse(written.as_bytes()).unwrap()).unwrap();
+        let mut rewritten = vec![];
+        reparsed.write_jmx(&mut rewritten).unwrap();
+        assert_eq!(String::from_utf8(rewritten).unwrap(), written);
+    }
+
+    #[test]
+    fn check_jmeter_version() {
+        let mut document = JmeterTestPlan::new(TestClass::Unknown(
+            Element::new("TestPlan"),
+            Element::new("hashTree"),
+        ));
+        assert_eq!(document.jmeter_version(), Some((5, 5)));
+        document.jmeter = String::from("5.6.3");
+        assert_eq!(document.jmeter_version(), Some((5, 6)));
+        document.jmeter = String::new();
+        assert_eq!(document.jmeter_version(), None);
+    }
+}
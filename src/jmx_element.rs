@@ -0,0 +1,590 @@
+use crate::{BoolProp, Deserializer, ElementProp, Property, StringProp};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use xmltree::{Element, XMLNode};
+
+/// Typed property lookup shared by [`JmxElement`] and nested [`ElementProp`]s.
+///
+/// A missing property reads as JMeter's own default (`""`, `false`, `0`, an
+/// empty collection), a property of the wrong kind or an unparsable number is
+/// an error.
+pub trait PropertyBag {
+    fn get(&self, name: &str) -> Option<&Property>;
+
+    /// Any scalar property as its string form, like JMeter's
+    /// `getPropertyAsString`.
+    fn get_str(&self, name: &str) -> Result<String> {
+        match self.get(name) {
+            None => Ok(String::new()),
+            Some(p) => p
+                .as_text()
+                .ok_or_else(|| anyhow!("{name}: expected a string, found {p:?}")),
+        }
+    }
+
+    fn get_bool(&self, name: &str) -> Result<bool> {
+        match self.get(name) {
+            None => Ok(false),
+            Some(p) => p
+                .as_bool()
+                .ok_or_else(|| anyhow!("{name}: expected a bool, found {p:?}")),
+        }
+    }
+
+    fn get_i64(&self, name: &str) -> Result<i64> {
+        match self.get(name) {
+            None => Ok(0),
+            Some(Property::String(p)) if p.value.trim().is_empty() => Ok(0),
+            Some(p) => p
+                .as_i64()
+                .ok_or_else(|| anyhow!("{name}: expected an integer, found {p:?}")),
+        }
+    }
+
+    fn get_collection(&self, name: &str) -> Result<&[Property]> {
+        match self.get(name) {
+            None => Ok(&[]),
+            Some(p) => p
+                .as_collection()
+                .ok_or_else(|| anyhow!("{name}: expected a collectionProp, found {p:?}")),
+        }
+    }
+
+    /// Nested elements have no meaningful default, so a missing one is an error.
+    fn get_element(&self, name: &str) -> Result<&ElementProp> {
+        match self.get(name) {
+            None => Err(anyhow!("{name}: missing elementProp")),
+            Some(p) => p
+                .as_element()
+                .ok_or_else(|| anyhow!("{name}: expected an elementProp, found {p:?}")),
+        }
+    }
+}
+
+impl PropertyBag for ElementProp {
+    fn get(&self, name: &str) -> Option<&Property> {
+        ElementProp::get(self, name)
+    }
+}
+
+/// Converts one property into a field for `#[derive(JmxElement)]`. A
+/// missing property takes `default` when one is given and JMeter's own
+/// default otherwise.
+pub trait FromProperty: Sized {
+    fn from_property<B: PropertyBag + ?Sized>(
+        bag: &B,
+        name: &str,
+        default: Option<&str>,
+    ) -> Result<Self>;
+}
+
+impl FromProperty for String {
+    fn from_property<B: PropertyBag + ?Sized>(
+        bag: &B,
+        name: &str,
+        default: Option<&str>,
+    ) -> Result<Self> {
+        match (bag.get(name), default) {
+            (None, Some(default)) => Ok(default.to_string()),
+            _ => bag.get_str(name),
+        }
+    }
+}
+
+impl FromProperty for bool {
+    fn from_property<B: PropertyBag + ?Sized>(
+        bag: &B,
+        name: &str,
+        default: Option<&str>,
+    ) -> Result<Self> {
+        match (bag.get(name), default) {
+            (None, Some(default)) => Ok(default.parse::<bool>()?),
+            _ => bag.get_bool(name),
+        }
+    }
+}
+
+macro_rules! integer_from_property {
+    ($($t:ty),*) => {$(
+        impl FromProperty for $t {
+            fn from_property<B: PropertyBag + ?Sized>(
+                bag: &B,
+                name: &str,
+                default: Option<&str>,
+            ) -> Result<Self> {
+                // JMeter falls back on its default for blank numbers too
+                match (bag.get(name), default) {
+                    (None, Some(default)) => Ok(default.parse::<$t>()?),
+                    (Some(Property::String(p)), Some(default)) if p.value.trim().is_empty() => {
+                        Ok(default.parse::<$t>()?)
+                    }
+                    _ => Ok(<$t>::try_from(bag.get_i64(name)?)?),
+                }
+            }
+        }
+    )*};
+}
+
+integer_from_property!(i32, i64, u16, u32, u64, usize);
+
+impl FromProperty for f64 {
+    fn from_property<B: PropertyBag + ?Sized>(
+        bag: &B,
+        name: &str,
+        default: Option<&str>,
+    ) -> Result<Self> {
+        match (bag.get(name), default) {
+            (None, Some(default)) => Ok(default.parse::<f64>()?),
+            (None, None) => Ok(0.0),
+            (Some(p), _) => p
+                .as_f64()
+                .ok_or_else(|| anyhow!("{name}: expected a number, found {p:?}")),
+        }
+    }
+}
+
+/// A missing or blank property is `None`.
+impl<T: FromProperty> FromProperty for Option<T> {
+    fn from_property<B: PropertyBag + ?Sized>(
+        bag: &B,
+        name: &str,
+        default: Option<&str>,
+    ) -> Result<Self> {
+        match bag.get(name) {
+            None if default.is_none() => Ok(None),
+            Some(Property::String(p)) if p.value.trim().is_empty() => Ok(None),
+            _ => T::from_property(bag, name, default).map(Some),
+        }
+    }
+}
+
+/// Counterpart of [`FromProperty`] for serializing derived elements. JMeter
+/// saves numbers on most elements as `<stringProp>`, and so does this.
+pub trait ToProperty {
+    fn to_property(&self, name: &str) -> Property;
+}
+
+impl ToProperty for String {
+    fn to_property(&self, name: &str) -> Property {
+        Property::String(StringProp {
+            name: name.to_string(),
+            value: self.clone(),
+        })
+    }
+}
+
+impl ToProperty for bool {
+    fn to_property(&self, name: &str) -> Property {
+        Property::Bool(BoolProp {
+            name: name.to_string(),
+            value: *self,
+        })
+    }
+}
+
+macro_rules! number_to_property {
+    ($($t:ty),*) => {$(
+        impl ToProperty for $t {
+            fn to_property(&self, name: &str) -> Property {
+                self.to_string().to_property(name)
+            }
+        }
+    )*};
+}
+
+number_to_property!(i32, i64, u16, u32, u64, usize, f64);
+
+/// `None` is saved as an empty `<stringProp>`.
+impl<T: ToProperty> ToProperty for Option<T> {
+    fn to_property(&self, name: &str) -> Property {
+        match self {
+            Some(value) => value.to_property(name),
+            None => String::new().to_property(name),
+        }
+    }
+}
+
+/// A property that is either a literal or a JMeter expression such as
+/// `${__P(threads,10)}` or `${users}`, which only has a value at run time.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value<T> {
+    Literal(T),
+    Expression(String),
+}
+
+impl<T> Value<T> {
+    pub fn literal(&self) -> Option<&T> {
+        match self {
+            Value::Literal(value) => Some(value),
+            Value::Expression(_) => None,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Value<U> {
+        match self {
+            Value::Literal(value) => Value::Literal(f(value)),
+            Value::Expression(expression) => Value::Expression(expression),
+        }
+    }
+
+    /// `(name, default)` when the whole expression reads a JMeter property,
+    /// `${__P(name,default)}` or `${__property(name,,default)}`. Without
+    /// one, the default is what JMeter falls back to, `1` for `__P` and the
+    /// name for `__property`.
+    pub fn property(&self) -> Option<(&str, &str)> {
+        let Value::Expression(expression) = self else {
+            return None;
+        };
+        let call = expression.trim().strip_prefix("${__")?.strip_suffix(")}")?;
+        let (function, args) = call.split_once('(')?;
+        let args = args.split(',').map(str::trim).collect::<Vec<&str>>();
+        let default = match (function, args.as_slice()) {
+            ("P", [_]) => "1",
+            ("property", [name] | [name, _]) => name,
+            ("P", [_, default]) | ("property", [_, _, default]) => default,
+            _ => return None,
+        };
+        Some((args[0], default)).filter(|(name, _)| !name.is_empty())
+    }
+}
+
+impl<T> From<T> for Value<T> {
+    fn from(value: T) -> Self {
+        Value::Literal(value)
+    }
+}
+
+impl<T: std::fmt::Display> std::fmt::Display for Value<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Literal(value) => value.fmt(f),
+            Value::Expression(expression) => f.write_str(expression),
+        }
+    }
+}
+
+/// Text holding a `${...}` reference is an expression, anything else is
+/// parsed as `T`.
+impl<T: std::str::FromStr> std::str::FromStr for Value<T> {
+    type Err = T::Err;
+
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        if text.contains("${") {
+            Ok(Value::Expression(text.to_string()))
+        } else {
+            text.trim().parse().map(Value::Literal)
+        }
+    }
+}
+
+/// Text holding a `${...}` reference is an expression whatever the property
+/// kind, anything else is read as `T`.
+impl<T: FromProperty> FromProperty for Value<T> {
+    fn from_property<B: PropertyBag + ?Sized>(
+        bag: &B,
+        name: &str,
+        default: Option<&str>,
+    ) -> Result<Self> {
+        match bag.get(name).and_then(Property::as_text) {
+            Some(text) if text.contains("${") => Ok(Value::Expression(text)),
+            _ => T::from_property(bag, name, default).map(Value::Literal),
+        }
+    }
+}
+
+/// Expressions are saved as `<stringProp>`, as JMeter does.
+impl<T: ToProperty> ToProperty for Value<T> {
+    fn to_property(&self, name: &str) -> Property {
+        match self {
+            Value::Literal(value) => value.to_property(name),
+            Value::Expression(expression) => expression.to_property(name),
+        }
+    }
+}
+
+/// Builds `<tag guiclass testclass testname enabled>` around `props`, the
+/// way JMeter saves a test element.
+pub fn test_element(
+    tag: &str,
+    gui_class: &str,
+    test_name: &str,
+    enabled: bool,
+    props: Vec<Element>,
+) -> Element {
+    let mut e = Element::new(tag);
+    for (name, value) in [
+        ("guiclass", gui_class),
+        ("testclass", tag),
+        ("testname", test_name),
+        ("enabled", if enabled { "true" } else { "false" }),
+    ] {
+        e.attributes.insert(name.to_string(), value.to_string());
+    }
+    e.children = props.into_iter().map(XMLNode::Element).collect();
+    e
+}
+
+/// Writes the serialized `element` over `source`, the element it was read
+/// from, so that only what changed differs from the original file.
+///
+/// `testname` and `enabled` are taken from `element`, every other attribute
+/// from `source`. Properties keep their original form and position unless
+/// their value changed, nested `<elementProp>`s are merged the same way and
+/// properties the model doesn't know are kept as they are. New properties
+/// holding JMeter's default are not added, as JMeter itself omits them.
+pub fn merge_element(source: &Element, element: &Element) -> Element {
+    let mut merged = merge_props(source, element);
+    for name in ["testname", "enabled"] {
+        let original = source.attributes.get(name).map(String::as_str);
+        match (element.attributes.get(name), original) {
+            (Some(value), None) if name == "enabled" && value == "true" => {}
+            (Some(value), _) if original != Some(value.as_str()) => {
+                merged.attributes.insert(name.to_string(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    merged
+}
+
+/// Named props match the prop of the same name. Unnamed ones, like props
+/// JMeter saves by reflection, match by position among the unnamed props of
+/// their tag.
+fn merge_props(source: &Element, element: &Element) -> Element {
+    let mut merged = source.clone();
+    let mut unnamed = HashMap::<&str, usize>::new();
+    for prop in element.children.iter().filter_map(|c| c.as_element()) {
+        let index = match prop.attributes.get("name") {
+            Some(name) => merged.children.iter().position(|c| {
+                c.as_element()
+                    .is_some_and(|c| c.attributes.get("name") == Some(name))
+            }),
+            None => {
+                let nth = unnamed.entry(prop.name.as_str()).or_default();
+                let index = merged
+                    .children
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| {
+                        c.as_element().is_some_and(|c| {
+                            c.name == prop.name && !c.attributes.contains_key("name")
+                        })
+                    })
+                    .nth(*nth)
+                    .map(|(i, _)| i);
+                *nth += 1;
+                index
+            }
+        };
+        match index {
+            Some(i) => {
+                let original = merged.children[i].as_element().unwrap();
+                if original.name == "elementProp" && prop.name == "elementProp" {
+                    merged.children[i] = XMLNode::Element(merge_props(original, prop));
+                } else if !same_value(&Property::parse(original), &Property::parse(prop)) {
+                    merged.children[i] = XMLNode::Element(prop.clone());
+                }
+            }
+            None if !is_default(&Property::parse(prop)) => {
+                merged.children.push(XMLNode::Element(prop.clone()))
+            }
+            None => {}
+        }
+    }
+    merged
+}
+
+/// Scalars compare by their text, so `<intProp>5</intProp>` equals
+/// `<stringProp>5</stringProp>`, and collections regardless of order and of
+/// their items' names, since JMeter names those after hash codes and some
+/// models keep them in maps.
+fn same_value(a: &Property, b: &Property) -> bool {
+    match (a, b) {
+        (Property::Collection(_, a), Property::Collection(_, b)) => {
+            a.props.len() == b.props.len()
+                && a.props
+                    .iter()
+                    .all(|p| b.props.iter().any(|q| same_value(p, q)))
+        }
+        (Property::Element(a), Property::Element(b)) => {
+            a.props.len() == b.props.len()
+                && a.props
+                    .iter()
+                    .all(|p| b.get(p.name()).is_some_and(|q| same_value(p, q)))
+        }
+        _ => match (a.as_text(), b.as_text()) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        },
+    }
+}
+
+fn is_default(p: &Property) -> bool {
+    match p.as_text() {
+        Some(text) => text.is_empty() || text == "false",
+        None => p.as_collection().is_some_and(|c| c.is_empty()),
+    }
+}
+
+/// Any JMeter test element: the tag, its common attributes and its properties
+/// indexed by name.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JmxElement {
+    pub tag: String,
+    pub gui_class: String,
+    pub test_class: String,
+    pub test_name: String,
+    pub enabled: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::sorted_map::serialize")
+    )]
+    pub props: HashMap<String, Property>,
+}
+
+impl JmxElement {
+    /// Fails unless this is a `<tag>` element.
+    pub fn expect_tag(&self, tag: &str) -> Result<()> {
+        if self.tag == tag {
+            Ok(())
+        } else {
+            Err(anyhow!("expected <{tag}>, found <{}>", self.tag))
+        }
+    }
+}
+
+impl PropertyBag for JmxElement {
+    fn get(&self, name: &str) -> Option<&Property> {
+        self.props.get(name)
+    }
+}
+
+impl Deserializer for JmxElement {
+    fn parse(e: &Element) -> Self {
+        let attribute = |name: &str| e.attributes.get(name).cloned().unwrap_or_default();
+        let props = Property::parse_children(e)
+            .into_iter()
+            .map(|p| (p.name().to_string(), p))
+            .collect::<HashMap<String, Property>>();
+        Self {
+            tag: e.name.clone(),
+            gui_class: attribute("guiclass"),
+            test_class: attribute("testclass"),
+            test_name: attribute("testname"),
+            // JMeter treats a missing flag as enabled
+            enabled: e
+                .attributes
+                .get("enabled")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+            props,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Deserializer, JmxElement, PropertyBag};
+    use xmltree::Element;
+
+    #[test]
+    fn check_merge_unnamed_props() {
+        let parse = |xml: &str| Element::parse(xml.as_bytes()).unwrap();
+        let source = parse(
+            r#"<Custom guiclass="CustomGui" testclass="Custom" testname="Custom" enabled="true"><stringProp name="a">1</stringProp><entry>one</entry><entry>two</entry><stringProp name="b">kept</stringProp></Custom>"#,
+        );
+        let element = parse(
+            r#"<Custom guiclass="CustomGui" testclass="Custom" testname="Custom" enabled="true"><stringProp name="a">2</stringProp><entry>one</entry><entry>three</entry></Custom>"#,
+        );
+        assert_eq!(
+            super::merge_element(&source, &element),
+            parse(
+                r#"<Custom guiclass="CustomGui" testclass="Custom" testname="Custom" enabled="true"><stringProp name="a">2</stringProp><entry>one</entry><entry>three</entry><stringProp name="b">kept</stringProp></Custom>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn check_parse() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/test_plan/test_plan.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let jmx = JmxElement::parse(&xml);
+        assert_eq!(jmx.tag, "TestPlan");
+        assert_eq!(jmx.gui_class, "TestPlanGui");
+        assert_eq!(jmx.test_class, "TestPlan");
+        assert_eq!(jmx.test_name, "Test Plan");
+        assert!(jmx.enabled);
+        assert_eq!(jmx.get_str("TestPlan.comments").unwrap(), "");
+        assert!(jmx.get_bool("TestPlan.tearDown_on_shutdown").unwrap());
+        assert_eq!(
+            jmx.get_element("TestPlan.user_defined_variables")
+                .unwrap()
+                .get_collection("Arguments.arguments")
+                .unwrap()
+                .len(),
+            2
+        );
+        // missing properties fall back to JMeter defaults
+        assert_eq!(jmx.get_str("TestPlan.missing").unwrap(), "");
+        assert!(!jmx.get_bool("TestPlan.missing").unwrap());
+        assert_eq!(jmx.get_i64("TestPlan.missing").unwrap(), 0);
+        assert!(jmx.get_collection("TestPlan.missing").unwrap().is_empty());
+        // wrong kinds are errors
+        assert!(jmx.get_i64("TestPlan.user_defined_variables").is_err());
+        assert!(jmx.get_collection("TestPlan.comments").is_err());
+    }
+
+    #[test]
+    fn check_derive() {
+        use crate::FromJmx;
+
+        #[derive(Debug, PartialEq, JmxElement)]
+        #[jmx(tag = "TestPlan")]
+        struct Plan {
+            #[jmx(test_name)]
+            name: String,
+            #[jmx(prop = "TestPlan.functional_mode")]
+            functional_mode: bool,
+            #[jmx(prop = "TestPlan.missing", default = "7")]
+            missing: u32,
+            #[jmx(prop = "TestPlan.comments")]
+            comments: Option<String>,
+        }
+
+        #[derive(Debug, JmxElement)]
+        #[jmx(tag = "TestPlan")]
+        struct Broken {
+            #[jmx(prop = "TestPlan.serialize_threadgroups")]
+            _threads: i32,
+        }
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/test_plan/test_plan.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            Plan::parse(&xml).unwrap(),
+            Plan {
+                name: String::from("Test Plan"),
+                functional_mode: false,
+                missing: 7,
+                comments: None,
+            }
+        );
+        let err = Broken::from_jmx(&JmxElement::parse(&xml)).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Broken._threads: TestPlan.serialize_threadgroups: expected an integer, found Bool(BoolProp { name: \"TestPlan.serialize_threadgroups\", value: false })"
+        );
+    }
+}
@@ -1,23 +1,100 @@
+//! Reads JMeter `.jmx` test plans into a typed model, writes them back the
+//! way JMeter saves them, and converts them into Goose load tests.
+//!
+//! # Serde schema
+//!
+//! With the `serde` feature, [`JmeterTestPlan`], [`TestClass`] and
+//! [`LoadTest`] use serde's default representations, which the `export` and
+//! `import` commands write and read as JSON or YAML:
+//!
+//! - A [`JmeterTestPlan`] is a map of `version`, `properties`, `jmeter`,
+//!   `nodes` and `extra`, the last a list of XML strings.
+//! - Each node is `{"<Variant>": [<element>, [<children>...]]}`, and an
+//!   element the model doesn't know is `{"Unknown": ["<element xml>",
+//!   "<hashTree xml>"]}`. The XML a node was parsed from isn't exported.
+//! - Elements are maps of their public fields by name. Enums are externally
+//!   tagged, so a [`Value`] is `{"Literal": 10}` or
+//!   `{"Expression": "${__P(threads)}"}` and a [`Property`] is
+//!   `{"String": {"name": ..., "value": ...}}`, except that a
+//!   [`Property::Raw`] holds its XML as a string.
+//! - Maps, like a plan's variables or a header manager's headers, are written
+//!   with their keys sorted, so exporting a plan twice gives the same output.
+//! - Unset `Option` fields are `null`.
+
+extern crate self as jmeter_to_goose;
+
+mod assertion;
 mod basic_prop;
 mod collection_prop;
 mod config_element;
+mod controller;
 mod element_prop;
+mod goose;
+mod ir;
+mod jmeter_test_plan;
+mod jmx_element;
+mod post_processor;
+mod properties;
+mod sampler;
+mod script;
+#[cfg(feature = "serde")]
+mod sorted_map;
+mod stream;
 mod test_plan;
 mod thread;
+mod timer;
+#[cfg(feature = "serde")]
+mod xml_serde;
 
-use crate::thread::ThreadGroup;
+use crate::thread::{
+    ArrivalsThreadGroup, ConcurrencyThreadGroup, SteppingThreadGroup, ThreadGroup,
+    UltimateThreadGroup,
+};
+#[doc(hidden)]
+pub use anyhow;
+use anyhow::{bail, Context};
+pub use assertion::*;
 pub use basic_prop::*;
 pub use collection_prop::*;
 pub use config_element::*;
+pub use controller::*;
 pub use element_prop::*;
+pub use ir::*;
+pub use jmeter_test_plan::*;
+pub use jmeter_to_goose_derive::JmxElement;
+pub use jmx_element::*;
+pub use post_processor::*;
+pub use properties::*;
+pub use sampler::*;
+pub use script::*;
 use std::collections::HashMap;
+use std::io::Write;
 pub use test_plan::*;
-use xmltree::Element;
+pub use timer::*;
+#[doc(hidden)]
+pub use xmltree;
+use xmltree::{Element, XMLNode};
 
 pub trait Deserializer {
     fn parse(e: &Element) -> Self;
 }
 
+/// Counterpart of [`Deserializer`], building the XML JMeter saves for a value.
+pub trait Serializer {
+    fn to_element(&self) -> Element;
+}
+
+/// Builds a test element from its [`JmxElement`] property bag.
+pub trait FromJmx: Sized {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self>;
+
+    /// Builds the test element from its XML, with the tag as the error's
+    /// context.
+    fn parse(e: &Element) -> anyhow::Result<Self> {
+        Self::from_jmx(&JmxElement::parse(e)).with_context(|| format!("<{}>", e.name))
+    }
+}
+
 pub trait Pairable {
     fn to_pair(&self) -> (&str, &str);
 }
@@ -26,58 +103,831 @@ pub trait HashMappable {
     fn to_hash_map(&self) -> HashMap<String, String>;
 }
 
+/// Renders an element as Rust statements for the generated Goose crate.
+///
+/// Snippets run inside a Goose transaction where `vars` and `props`
+/// (`&mut HashMap<String, String>`) hold the user's JMeter variables and the
+/// JMeter properties. Post-processors also see the previous sample as
+/// `body: &str`, `headers: &str` and `status: u16`.
+pub trait GooseCodegen {
+    fn to_goose(&self) -> String;
+}
+
 /// TestClass
 ///
+/// Every known element keeps the `Element` it was read from, `None` when built
+/// in code, so that writing it back only changes what was modified (see
+/// [`merge_element`]). Unknown elements keep their element and `<hashTree>`
+/// and are written back verbatim.
+///
+/// With the `serde` feature the tree serializes as described in the
+/// [crate docs](crate#serde-schema). Source elements are not part of it, so an
+/// imported plan is written back from its model alone.
+///
 /// # Examples
 ///
-/// ```
+/// ```no_run
 /// use std::fs::read_to_string;
 /// use xmltree::Element;
 /// use jmeter_to_goose::TestClass;
 ///
-/// # fn main() -> std::io::Result<()> {
+/// # fn main() -> anyhow::Result<()> {
 /// let xml = read_to_string("test.xml")?;
-/// let doc = Element::parse(xml.as_bytes()).unwrap();
+/// let doc = Element::parse(xml.as_bytes())?;
 ///
-/// let root = TestClass::root(&doc);
+/// let root = TestClass::root(&doc)?;
 /// println!("{:?}", root);
 /// #
 /// #     Ok(())
 /// # }
 /// ```
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TestClass {
-    TestPlan(TestPlan, Vec<TestClass>),
-    ThreadGroup(ThreadGroup, Vec<TestClass>),
-    HeaderManager(HeaderManager, Vec<TestClass>),
-    Unknown,
+    TestPlan(
+        TestPlan,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    ThreadGroup(
+        ThreadGroup,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    UltimateThreadGroup(
+        UltimateThreadGroup,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    ConcurrencyThreadGroup(
+        ConcurrencyThreadGroup,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    SteppingThreadGroup(
+        SteppingThreadGroup,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    ArrivalsThreadGroup(
+        ArrivalsThreadGroup,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    HeaderManager(
+        HeaderManager,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    HttpDefaults(
+        HttpDefaults,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    DnsCacheManager(
+        DnsCacheManager,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    KeystoreConfig(
+        KeystoreConfig,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    Arguments(
+        Arguments,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    UserParameters(
+        UserParameters,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    RandomVariableConfig(
+        RandomVariableConfig,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    CounterConfig(
+        CounterConfig,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    SimpleController(
+        SimpleController,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    TransactionController(
+        TransactionController,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    HttpSampler(
+        HttpSampler,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    BoundaryExtractor(
+        BoundaryExtractor,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    XPath2Extractor(
+        XPath2Extractor,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    HtmlExtractor(
+        HtmlExtractor,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    Script(
+        ScriptElement,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    ConstantTimer(
+        ConstantTimer,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    UniformRandomTimer(
+        UniformRandomTimer,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    GaussianRandomTimer(
+        GaussianRandomTimer,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    ResponseAssertion(
+        ResponseAssertion,
+        Vec<TestClass>,
+        #[cfg_attr(feature = "serde", serde(skip))] Option<Element>,
+    ),
+    Unknown(
+        #[cfg_attr(feature = "serde", serde(with = "xml_serde"))] Element,
+        #[cfg_attr(feature = "serde", serde(with = "xml_serde"))] Element,
+    ),
 }
 
+/// See [`TestClass::node_parser`]
+pub(crate) type NodeParser = fn(&Element, Vec<TestClass>) -> anyhow::Result<TestClass>;
+
 impl TestClass {
-    pub fn root(ele: &Element) -> Self {
-        assert_eq!(ele.children.len(), 1);
-
-        let ele = ele.children.first().unwrap().as_element().unwrap();
-        assert_eq!(ele.children.len(), 2);
-
-        let element = ele.children[0].as_element().unwrap();
-        let sub = ele.children[1].as_element().unwrap();
-        Self::parse(element, sub)
-    }
-    pub fn parse(e: &Element, hash_tree: &Element) -> Self {
-        assert_eq!(hash_tree.name, String::from("hashTree"));
-        assert_eq!(hash_tree.children.len() % 2, 0);
-        let mut subs: Vec<TestClass> = vec![];
-        for i in (0..hash_tree.children.len()).step_by(2) {
-            let ele = hash_tree.children[i].as_element().unwrap();
-            let sub = hash_tree.children[i + 1].as_element().unwrap();
-            subs.push(TestClass::parse(ele, sub))
-        }
-        match e.name.as_str() {
-            "TestPlan" => TestClass::TestPlan(TestPlan::parse(e), subs),
-            "ThreadGroup" => TestClass::ThreadGroup(ThreadGroup::parse(e), subs),
-            "HeaderManager" => TestClass::HeaderManager(HeaderManager::parse(e), subs),
-            _ => TestClass::Unknown,
+    /// The test plan of a `<jmeterTestPlan>` document, see
+    /// [`JmeterTestPlan::test_plan`].
+    pub fn root(ele: &Element) -> anyhow::Result<Self> {
+        JmeterTestPlan::parse(ele)?.into_test_plan()
+    }
+
+    pub fn parse(e: &Element, hash_tree: &Element) -> anyhow::Result<Self> {
+        if hash_tree.name != "hashTree" {
+            bail!("expected <hashTree> after <{}>", e.name);
+        }
+        match Self::node_parser(e) {
+            Some(parser) => parser(e, Self::parse_nodes(hash_tree)?),
+            None => Ok(TestClass::Unknown(e.clone(), hash_tree.clone())),
+        }
+    }
+
+    /// The element/`<hashTree>` pairs of a `<hashTree>`, skipping anything
+    /// that isn't an element.
+    pub(crate) fn parse_nodes(hash_tree: &Element) -> anyhow::Result<Vec<TestClass>> {
+        let mut elements = hash_tree.children.iter().filter_map(XMLNode::as_element);
+        let mut nodes = vec![];
+        while let Some(e) = elements.next() {
+            match elements.next() {
+                Some(sub) if sub.name == "hashTree" => nodes.push(TestClass::parse(e, sub)?),
+                _ => bail!("expected <hashTree> after <{}>", e.name),
+            }
+        }
+        Ok(nodes)
+    }
+
+    /// Builds the node for an element the model knows from the element and
+    /// its parsed children, `None` for elements kept as `Unknown`. Only the
+    /// element's tag and attributes are looked at.
+    pub(crate) fn node_parser(e: &Element) -> Option<NodeParser> {
+        let parser: NodeParser = match e.name.as_str() {
+            "TestPlan" => |e, subs| {
+                Ok(TestClass::TestPlan(
+                    TestPlan::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "ThreadGroup" => |e, subs| {
+                Ok(TestClass::ThreadGroup(
+                    ThreadGroup::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "kg.apc.jmeter.threads.UltimateThreadGroup" => |e, subs| {
+                Ok(TestClass::UltimateThreadGroup(
+                    UltimateThreadGroup::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "com.blazemeter.jmeter.threads.concurrency.ConcurrencyThreadGroup" => |e, subs| {
+                Ok(TestClass::ConcurrencyThreadGroup(
+                    ConcurrencyThreadGroup::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "kg.apc.jmeter.threads.SteppingThreadGroup" => |e, subs| {
+                Ok(TestClass::SteppingThreadGroup(
+                    SteppingThreadGroup::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "com.blazemeter.jmeter.threads.arrivals.ArrivalsThreadGroup" => |e, subs| {
+                Ok(TestClass::ArrivalsThreadGroup(
+                    ArrivalsThreadGroup::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "HeaderManager" => |e, subs| {
+                Ok(TestClass::HeaderManager(
+                    HeaderManager::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            // Several config elements share the tag, told apart by their GUI
+            "ConfigTestElement"
+                if e.attributes.get("guiclass").map(String::as_str) == Some("HttpDefaultsGui") =>
+            {
+                |e, subs| {
+                    Ok(TestClass::HttpDefaults(
+                        HttpDefaults::parse(e)?,
+                        subs,
+                        Some(e.clone()),
+                    ))
+                }
+            }
+            "DNSCacheManager" => |e, subs| {
+                Ok(TestClass::DnsCacheManager(
+                    DnsCacheManager::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "KeystoreConfig" => |e, subs| {
+                Ok(TestClass::KeystoreConfig(
+                    KeystoreConfig::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "Arguments" => |e, subs| {
+                Ok(TestClass::Arguments(
+                    Arguments::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "UserParameters" => |e, subs| {
+                Ok(TestClass::UserParameters(
+                    UserParameters::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "RandomVariableConfig" => |e, subs| {
+                Ok(TestClass::RandomVariableConfig(
+                    RandomVariableConfig::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "CounterConfig" => |e, subs| {
+                Ok(TestClass::CounterConfig(
+                    CounterConfig::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "GenericController" => |e, subs| {
+                Ok(TestClass::SimpleController(
+                    SimpleController::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "TransactionController" => |e, subs| {
+                Ok(TestClass::TransactionController(
+                    TransactionController::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "HTTPSamplerProxy" => |e, subs| {
+                Ok(TestClass::HttpSampler(
+                    HttpSampler::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "BoundaryExtractor" => |e, subs| {
+                Ok(TestClass::BoundaryExtractor(
+                    BoundaryExtractor::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "XPath2Extractor" => |e, subs| {
+                Ok(TestClass::XPath2Extractor(
+                    XPath2Extractor::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "HtmlExtractor" => |e, subs| {
+                Ok(TestClass::HtmlExtractor(
+                    HtmlExtractor::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "JSR223PreProcessor"
+            | "JSR223PostProcessor"
+            | "JSR223Sampler"
+            | "BeanShellPreProcessor"
+            | "BeanShellPostProcessor"
+            | "BeanShellSampler" => |e, subs| {
+                Ok(TestClass::Script(
+                    ScriptElement::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "ConstantTimer" => |e, subs| {
+                Ok(TestClass::ConstantTimer(
+                    ConstantTimer::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "UniformRandomTimer" => |e, subs| {
+                Ok(TestClass::UniformRandomTimer(
+                    UniformRandomTimer::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "GaussianRandomTimer" => |e, subs| {
+                Ok(TestClass::GaussianRandomTimer(
+                    GaussianRandomTimer::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            "ResponseAssertion" => |e, subs| {
+                Ok(TestClass::ResponseAssertion(
+                    ResponseAssertion::parse(e)?,
+                    subs,
+                    Some(e.clone()),
+                ))
+            },
+            _ => return None,
+        };
+        Some(parser)
+    }
+
+    pub fn children(&self) -> &[TestClass] {
+        match self {
+            TestClass::TestPlan(_, subs, _)
+            | TestClass::ThreadGroup(_, subs, _)
+            | TestClass::UltimateThreadGroup(_, subs, _)
+            | TestClass::ConcurrencyThreadGroup(_, subs, _)
+            | TestClass::SteppingThreadGroup(_, subs, _)
+            | TestClass::ArrivalsThreadGroup(_, subs, _)
+            | TestClass::HeaderManager(_, subs, _)
+            | TestClass::HttpDefaults(_, subs, _)
+            | TestClass::DnsCacheManager(_, subs, _)
+            | TestClass::KeystoreConfig(_, subs, _)
+            | TestClass::Arguments(_, subs, _)
+            | TestClass::UserParameters(_, subs, _)
+            | TestClass::RandomVariableConfig(_, subs, _)
+            | TestClass::CounterConfig(_, subs, _)
+            | TestClass::SimpleController(_, subs, _)
+            | TestClass::TransactionController(_, subs, _)
+            | TestClass::HttpSampler(_, subs, _)
+            | TestClass::BoundaryExtractor(_, subs, _)
+            | TestClass::XPath2Extractor(_, subs, _)
+            | TestClass::HtmlExtractor(_, subs, _)
+            | TestClass::Script(_, subs, _)
+            | TestClass::ConstantTimer(_, subs, _)
+            | TestClass::UniformRandomTimer(_, subs, _)
+            | TestClass::GaussianRandomTimer(_, subs, _)
+            | TestClass::ResponseAssertion(_, subs, _) => subs,
+            TestClass::Unknown(..) => &[],
+        }
+    }
+
+    pub fn children_mut(&mut self) -> &mut [TestClass] {
+        match self {
+            TestClass::TestPlan(_, subs, _)
+            | TestClass::ThreadGroup(_, subs, _)
+            | TestClass::UltimateThreadGroup(_, subs, _)
+            | TestClass::ConcurrencyThreadGroup(_, subs, _)
+            | TestClass::SteppingThreadGroup(_, subs, _)
+            | TestClass::ArrivalsThreadGroup(_, subs, _)
+            | TestClass::HeaderManager(_, subs, _)
+            | TestClass::HttpDefaults(_, subs, _)
+            | TestClass::DnsCacheManager(_, subs, _)
+            | TestClass::KeystoreConfig(_, subs, _)
+            | TestClass::Arguments(_, subs, _)
+            | TestClass::UserParameters(_, subs, _)
+            | TestClass::RandomVariableConfig(_, subs, _)
+            | TestClass::CounterConfig(_, subs, _)
+            | TestClass::SimpleController(_, subs, _)
+            | TestClass::TransactionController(_, subs, _)
+            | TestClass::HttpSampler(_, subs, _)
+            | TestClass::BoundaryExtractor(_, subs, _)
+            | TestClass::XPath2Extractor(_, subs, _)
+            | TestClass::HtmlExtractor(_, subs, _)
+            | TestClass::Script(_, subs, _)
+            | TestClass::ConstantTimer(_, subs, _)
+            | TestClass::UniformRandomTimer(_, subs, _)
+            | TestClass::GaussianRandomTimer(_, subs, _)
+            | TestClass::ResponseAssertion(_, subs, _) => subs,
+            TestClass::Unknown(..) => &mut [],
+        }
+    }
+
+    /// Drops the elements the tree was parsed from, here and below, so it is
+    /// written from its model alone, as a tree imported with serde is.
+    pub fn forget_sources(&mut self) {
+        match self {
+            TestClass::TestPlan(_, subs, source)
+            | TestClass::ThreadGroup(_, subs, source)
+            | TestClass::UltimateThreadGroup(_, subs, source)
+            | TestClass::ConcurrencyThreadGroup(_, subs, source)
+            | TestClass::SteppingThreadGroup(_, subs, source)
+            | TestClass::ArrivalsThreadGroup(_, subs, source)
+            | TestClass::HeaderManager(_, subs, source)
+            | TestClass::HttpDefaults(_, subs, source)
+            | TestClass::DnsCacheManager(_, subs, source)
+            | TestClass::KeystoreConfig(_, subs, source)
+            | TestClass::Arguments(_, subs, source)
+            | TestClass::UserParameters(_, subs, source)
+            | TestClass::RandomVariableConfig(_, subs, source)
+            | TestClass::CounterConfig(_, subs, source)
+            | TestClass::SimpleController(_, subs, source)
+            | TestClass::TransactionController(_, subs, source)
+            | TestClass::HttpSampler(_, subs, source)
+            | TestClass::BoundaryExtractor(_, subs, source)
+            | TestClass::XPath2Extractor(_, subs, source)
+            | TestClass::HtmlExtractor(_, subs, source)
+            | TestClass::Script(_, subs, source)
+            | TestClass::ConstantTimer(_, subs, source)
+            | TestClass::UniformRandomTimer(_, subs, source)
+            | TestClass::GaussianRandomTimer(_, subs, source)
+            | TestClass::ResponseAssertion(_, subs, source) => {
+                *source = None;
+                subs.iter_mut().for_each(TestClass::forget_sources);
+            }
+            TestClass::Unknown(..) => {}
+        }
+    }
+
+    /// `false` for elements switched off in JMeter, which leaves out their
+    /// whole subtree.
+    pub fn enabled(&self) -> bool {
+        match self {
+            TestClass::TestPlan(e, ..) => e.enabled,
+            TestClass::ThreadGroup(e, ..) => e.enabled,
+            TestClass::UltimateThreadGroup(e, ..) => e.enabled,
+            TestClass::ConcurrencyThreadGroup(e, ..) => e.enabled,
+            TestClass::SteppingThreadGroup(e, ..) => e.enabled,
+            TestClass::ArrivalsThreadGroup(e, ..) => e.enabled,
+            TestClass::HeaderManager(e, ..) => e.enabled,
+            TestClass::HttpDefaults(e, ..) => e.enabled,
+            TestClass::DnsCacheManager(e, ..) => e.enabled,
+            TestClass::KeystoreConfig(e, ..) => e.enabled,
+            TestClass::Arguments(e, ..) => e.enabled,
+            TestClass::UserParameters(e, ..) => e.enabled,
+            TestClass::RandomVariableConfig(e, ..) => e.enabled,
+            TestClass::CounterConfig(e, ..) => e.enabled,
+            TestClass::SimpleController(e, ..) => e.enabled,
+            TestClass::TransactionController(e, ..) => e.enabled,
+            TestClass::HttpSampler(e, ..) => e.enabled,
+            TestClass::BoundaryExtractor(e, ..) => e.enabled,
+            TestClass::XPath2Extractor(e, ..) => e.enabled,
+            TestClass::HtmlExtractor(e, ..) => e.enabled,
+            TestClass::Script(e, ..) => e.enabled,
+            TestClass::ConstantTimer(e, ..) => e.enabled,
+            TestClass::UniformRandomTimer(e, ..) => e.enabled,
+            TestClass::GaussianRandomTimer(e, ..) => e.enabled,
+            TestClass::ResponseAssertion(e, ..) => e.enabled,
+            TestClass::Unknown(e, _) => e.attributes.get("enabled").is_none_or(|v| v != "false"),
+        }
+    }
+
+    /// The element as it should be saved, merged into the element it was
+    /// read from when there is one.
+    pub fn to_element(&self) -> Element {
+        let (element, source) = match self {
+            TestClass::TestPlan(e, _, source) => (e.to_element(), source),
+            TestClass::ThreadGroup(e, _, source) => (e.to_element(), source),
+            TestClass::UltimateThreadGroup(e, _, source) => (e.to_element(), source),
+            TestClass::ConcurrencyThreadGroup(e, _, source) => (e.to_element(), source),
+            TestClass::SteppingThreadGroup(e, _, source) => (e.to_element(), source),
+            TestClass::ArrivalsThreadGroup(e, _, source) => (e.to_element(), source),
+            TestClass::HeaderManager(e, _, source) => (e.to_element(), source),
+            TestClass::HttpDefaults(e, _, source) => (e.to_element(), source),
+            TestClass::DnsCacheManager(e, _, source) => (e.to_element(), source),
+            TestClass::KeystoreConfig(e, _, source) => (e.to_element(), source),
+            TestClass::Arguments(e, _, source) => (e.to_element(), source),
+            TestClass::UserParameters(e, _, source) => (e.to_element(), source),
+            TestClass::RandomVariableConfig(e, _, source) => (e.to_element(), source),
+            TestClass::CounterConfig(e, _, source) => (e.to_element(), source),
+            TestClass::SimpleController(e, _, source) => (e.to_element(), source),
+            TestClass::TransactionController(e, _, source) => (e.to_element(), source),
+            TestClass::HttpSampler(e, _, source) => (e.to_element(), source),
+            TestClass::BoundaryExtractor(e, _, source) => (e.to_element(), source),
+            TestClass::XPath2Extractor(e, _, source) => (e.to_element(), source),
+            TestClass::HtmlExtractor(e, _, source) => (e.to_element(), source),
+            TestClass::Script(e, _, source) => (e.to_element(), source),
+            TestClass::ConstantTimer(e, _, source) => (e.to_element(), source),
+            TestClass::UniformRandomTimer(e, _, source) => (e.to_element(), source),
+            TestClass::GaussianRandomTimer(e, _, source) => (e.to_element(), source),
+            TestClass::ResponseAssertion(e, _, source) => (e.to_element(), source),
+            TestClass::Unknown(e, _) => return e.clone(),
+        };
+        match source {
+            Some(source) => merge_element(source, &element),
+            None => element,
+        }
+    }
+
+    /// The `<hashTree>` following the element, holding its children as
+    /// element/hashTree pairs.
+    pub fn hash_tree(&self) -> Element {
+        if let TestClass::Unknown(_, hash_tree) = self {
+            return hash_tree.clone();
+        }
+        let mut hash_tree = Element::new("hashTree");
+        for child in self.children() {
+            hash_tree
+                .children
+                .push(XMLNode::Element(child.to_element()));
+            hash_tree.children.push(XMLNode::Element(child.hash_tree()));
+        }
+        hash_tree
+    }
+
+    /// The whole `<jmeterTestPlan>` document rooted at this element, the
+    /// counterpart of [`TestClass::root`], as [`JmeterTestPlan::new`] would
+    /// write it.
+    pub fn to_document(&self) -> Element {
+        jmeter_test_plan::document(
+            [("version", "1.2"), ("properties", "5.0"), ("jmeter", "5.5")],
+            &[self],
+            &[],
+        )
+    }
+
+    /// Writes [`TestClass::to_document`] indented the way JMeter saves it.
+    pub fn write_jmx<W: Write>(&self, w: W) -> Result<(), xmltree::Error> {
+        jmeter_test_plan::write_document(self.to_document(), w)
+    }
+
+    /// The plan-level variables, then every enabled `<Arguments>` in document
+    /// order regardless of where it sits (User Defined Variables are global),
+    /// each value able to reference the ones defined before it.
+    pub fn global_variables(&self) -> HashMap<String, String> {
+        fn globals(node: &TestClass, vars: &mut HashMap<String, String>) {
+            match node {
+                TestClass::TestPlan(plan, ..) => {
+                    for (name, value) in &plan.variables {
+                        vars.insert(name.clone(), substitute(value, vars));
+                    }
+                }
+                TestClass::Arguments(arguments, ..) if arguments.enabled => {
+                    for argument in &arguments.arguments {
+                        vars.insert(argument.name.clone(), substitute(&argument.value, vars));
+                    }
+                }
+                _ => {}
+            }
+            node.children().iter().for_each(|c| globals(c, vars));
+        }
+
+        let mut vars = HashMap::new();
+        globals(self, &mut vars);
+        vars
+    }
+
+    /// The name of a thread group of any kind, `None` for other nodes.
+    pub fn thread_group_name(&self) -> Option<&str> {
+        match self {
+            TestClass::ThreadGroup(group, ..) => Some(&group.test_name),
+            TestClass::UltimateThreadGroup(group, ..) => Some(&group.test_name),
+            TestClass::ConcurrencyThreadGroup(group, ..) => Some(&group.test_name),
+            TestClass::SteppingThreadGroup(group, ..) => Some(&group.test_name),
+            TestClass::ArrivalsThreadGroup(group, ..) => Some(&group.test_name),
+            _ => None,
+        }
+    }
+
+    /// Variables the `user`th user (0-based) of `thread_group` starts with.
+    ///
+    /// Follows JMeter's evaluation order: the [global
+    /// variables](TestClass::global_variables), then `<UserParameters>`, only
+    /// those at plan level or inside `thread_group`.
+    pub fn user_variables(&self, thread_group: &str, user: usize) -> HashMap<String, String> {
+        fn parameters(
+            node: &TestClass,
+            thread_group: &str,
+            user: usize,
+            vars: &mut HashMap<String, String>,
+        ) {
+            match node {
+                _ if node
+                    .thread_group_name()
+                    .is_some_and(|name| name != thread_group) =>
+                {
+                    return
+                }
+                TestClass::UserParameters(parameters, ..) if parameters.enabled => {
+                    for (name, value) in parameters.values_for(user) {
+                        vars.insert(name, substitute(&value, vars));
+                    }
+                }
+                _ => {}
+            }
+            node.children()
+                .iter()
+                .for_each(|c| parameters(c, thread_group, user, vars));
+        }
+
+        let mut vars = self.global_variables();
+        parameters(self, thread_group, user, &mut vars);
+        vars
+    }
+}
+
+/// Replaces `${name}` references to already known variables, leaving function
+/// calls and unknown names for runtime evaluation.
+pub fn substitute(value: &str, vars: &HashMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) if vars.contains_key(&after[..end]) => {
+                result.push_str(&vars[&after[..end]]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                result.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{TestClass, Value};
+    use std::collections::HashMap;
+    use xmltree::Element;
+
+    #[test]
+    fn check_user_variables() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/test_class/user_variables.jmx")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let root = TestClass::root(&xml).unwrap();
+        assert_eq!(
+            root.user_variables("Users", 1),
+            HashMap::from([
+                (String::from("protocol"), String::from("https")),
+                (String::from("host"), String::from("example.github.com")),
+                (
+                    String::from("base"),
+                    String::from("https://example.github.com/api")
+                ),
+                (String::from("user"), String::from("bob")),
+            ])
+        );
+        assert_eq!(root.user_variables("Admins", 1).get("user"), None);
+    }
+
+    /// Every plan among the fixtures reads back the same from the XML its
+    /// model alone writes, so no modelled property is lost.
+    #[test]
+    fn check_model_round_trip() {
+        let mut checked = 0;
+        for dir in std::fs::read_dir("./unittests_data").unwrap() {
+            for file in std::fs::read_dir(dir.unwrap().path()).unwrap() {
+                let path = file.unwrap().path();
+                if path.extension().and_then(|e| e.to_str()) != Some("jmx") {
+                    continue;
+                }
+                let xml = std::fs::read_to_string(&path).unwrap();
+                let mut root = TestClass::root(&Element::parse(xml.trim().as_bytes()).unwrap())
+                    .unwrap_or_else(|err| panic!("{}: {err:#}", path.display()));
+                root.forget_sources();
+                let mut written = TestClass::root(&root.to_document()).unwrap();
+                written.forget_sources();
+                assert_eq!(written, root, "{}", path.display());
+                checked += 1;
+            }
+        }
+        assert!(checked > 10);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_serde() {
+        use crate::{FromJmx, TestPlan};
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/test_class/user_variables.jmx")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let root = TestClass::root(&xml).unwrap();
+        let json = serde_json::to_value(&root).unwrap();
+        assert_eq!(json["TestPlan"][0]["variables"]["protocol"], "https");
+        assert_eq!(
+            json["TestPlan"][1][0]["ThreadGroup"][0]["test_name"],
+            "Users"
+        );
+        let imported: TestClass = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&imported).unwrap(), json);
+        assert_eq!(
+            imported.user_variables("Users", 1),
+            root.user_variables("Users", 1)
+        );
+
+        // maps are written in key order, the same on every export
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/test_plan/test_plan.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let text = serde_json::to_string(&TestPlan::parse(&xml).unwrap()).unwrap();
+        assert!(text.contains(r#""variables":{"ip":"example.github.com","protocol":"https"}"#));
+        for _ in 0..8 {
+            let again = TestPlan::parse(&xml).unwrap();
+            assert_eq!(serde_json::to_string(&again).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn check_round_trip() {
+        let jmx =
+            std::fs::read_to_string("./unittests_data/test_class/user_variables.jmx").unwrap();
+        let mut root = TestClass::root(&Element::parse(jmx.trim().as_bytes()).unwrap()).unwrap();
+        let mut written = vec![];
+        root.write_jmx(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), jmx);
+
+        // a modified element only changes the modified property
+        match &mut root.children_mut()[0] {
+            TestClass::ThreadGroup(group, ..) => group.num_threads = Value::Literal(5),
+            other => panic!("expected the Users thread group, found {other:?}"),
         }
+        let mut written = vec![];
+        root.write_jmx(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        let changed = jmx
+            .lines()
+            .zip(written.lines())
+            .filter(|(a, b)| a != b)
+            .collect::<Vec<(&str, &str)>>();
+        assert_eq!(
+            changed,
+            vec![(
+                r#"        <stringProp name="ThreadGroup.num_threads">2</stringProp>"#,
+                r#"        <stringProp name="ThreadGroup.num_threads">5</stringProp>"#
+            )]
+        );
+        assert_eq!(jmx.lines().count(), written.lines().count());
     }
 }
@@ -1,13 +1,177 @@
-use jmeter_to_goose::TestClass;
-use std::fs::read_to_string;
-use xmltree::Element;
+use anyhow::Context;
+use anyhow::{anyhow, bail};
+use jmeter_to_goose::{parse_properties, JmeterTestPlan, LoadTest};
+use std::collections::HashMap;
+use std::fs::{read_to_string, File};
+use std::io::BufReader;
+use std::path::Path;
+
+const USAGE: &str = "usage:
+    jmeter-to-goose [PLAN.jmx]
+        print the parsed plan (defaults to test.xml)
+    jmeter-to-goose convert [--include-disabled] [--addprop FILE]... [-Jname=value]...
+            [-Dname=value]... PLAN.jmx OUT_DIR
+        write a Goose load test crate for the plan to OUT_DIR, with disabled
+        elements left out or, with --include-disabled, commented out. The
+        JMeter (-J, --addprop) and system (-D) properties become the test's
+        defaults, which its own -J, -D and --addprop options override. -Djavax.net.ssl.keyStore=PATH names the
+        client certificates of a Keystore Configuration
+    jmeter-to-goose export --format json|yaml PLAN.jmx
+        write the parsed plan as JSON or YAML to stdout
+    jmeter-to-goose import --format json|yaml FILE
+        write the .jmx of an exported plan to stdout";
 
 fn main() -> anyhow::Result<()> {
-    let xml = read_to_string("test.xml")?;
-    let doc = Element::parse(xml.as_bytes()).unwrap();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    match args.first().map(String::as_str) {
+        Some("convert") => {
+            let Convert {
+                include_disabled,
+                properties,
+                system_properties,
+                paths,
+            } = Convert::parse(&args[1..])?;
+            let [path, out_dir] = paths.as_slice() else {
+                bail!("{USAGE}");
+            };
+            let document = read_plan(path)?;
+            let plan = document.test_plan().context("no test plan")?;
+            let mut test = if include_disabled {
+                LoadTest::lower_including_disabled(plan)
+            } else {
+                LoadTest::lower(plan)
+            };
+            test.properties = properties;
+            test.system_properties = system_properties;
+            let jmx_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+            test.write_goose_crate(jmx_dir, Path::new(out_dir))?;
+            for element in &test.unsupported {
+                eprintln!("not converted: {element}");
+            }
+            for element in &test.approximated {
+                eprintln!("approximated: {element}");
+            }
+            let skipped = if include_disabled {
+                "disabled, commented out"
+            } else {
+                "disabled, skipped"
+            };
+            for element in &test.skipped {
+                eprintln!("{skipped}: {element}");
+            }
+            Ok(())
+        }
+        Some("export") => {
+            let (format, path) = format_and_path(&args[1..])?;
+            export(&read_plan(path)?, format)
+        }
+        Some("import") => {
+            let (format, path) = format_and_path(&args[1..])?;
+            import(&read_to_string(path)?, format)?.write_jmx(std::io::stdout())?;
+            Ok(())
+        }
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Some(path) if args.len() == 1 => {
+            println!("{:#?}", read_plan(path)?);
+            Ok(())
+        }
+        None => {
+            println!("{:#?}", read_plan("test.xml")?);
+            Ok(())
+        }
+        Some(_) => bail!("{USAGE}"),
+    }
+}
+
+/// The options of `convert`.
+struct Convert<'a> {
+    include_disabled: bool,
+    /// From `--addprop` files and `-J` options, in order
+    properties: HashMap<String, String>,
+    /// From `-D` options
+    system_properties: HashMap<String, String>,
+    paths: Vec<&'a str>,
+}
 
-    let root = TestClass::root(&doc);
-    println!("{:#?}", root);
+impl<'a> Convert<'a> {
+    fn parse(args: &'a [String]) -> anyhow::Result<Self> {
+        let mut convert = Convert {
+            include_disabled: false,
+            properties: HashMap::new(),
+            system_properties: HashMap::new(),
+            paths: vec![],
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--include-disabled" {
+                convert.include_disabled = true;
+            } else if arg == "--addprop" {
+                let path = args.next().ok_or_else(|| anyhow!("{USAGE}"))?;
+                let text = read_to_string(path).with_context(|| format!("reading {path}"))?;
+                convert.properties.extend(parse_properties(&text));
+            } else if let Some(property) = arg.strip_prefix("-J") {
+                let (name, value) = property.split_once('=').unwrap_or((property, ""));
+                convert
+                    .properties
+                    .insert(name.to_string(), value.to_string());
+            } else if let Some(property) = arg.strip_prefix("-D") {
+                let (name, value) = property.split_once('=').unwrap_or((property, ""));
+                convert
+                    .system_properties
+                    .insert(name.to_string(), value.to_string());
+            } else if arg.starts_with('-') {
+                bail!("unknown option {arg:?}\n{USAGE}");
+            } else {
+                convert.paths.push(arg);
+            }
+        }
+        Ok(convert)
+    }
+}
+
+fn read_plan(path: &str) -> anyhow::Result<JmeterTestPlan> {
+    JmeterTestPlan::from_reader(BufReader::new(File::open(path)?))
+}
 
+/// Parses `--format json|yaml PATH`, in either order.
+fn format_and_path(args: &[String]) -> anyhow::Result<(&str, &str)> {
+    match args {
+        [flag, format, path] | [path, flag, format] if flag == "--format" => {
+            match format.as_str() {
+                "json" | "yaml" => Ok((format, path)),
+                other => bail!("unknown format {other:?}, expected json or yaml"),
+            }
+        }
+        _ => Err(anyhow!("{USAGE}")),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn export(plan: &JmeterTestPlan, format: &str) -> anyhow::Result<()> {
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(plan)?),
+        _ => print!("{}", serde_yaml::to_string(plan)?),
+    }
     Ok(())
 }
+
+#[cfg(feature = "serde")]
+fn import(exported: &str, format: &str) -> anyhow::Result<JmeterTestPlan> {
+    Ok(match format {
+        "json" => serde_json::from_str(exported)?,
+        _ => serde_yaml::from_str(exported)?,
+    })
+}
+
+#[cfg(not(feature = "serde"))]
+fn export(_: &JmeterTestPlan, _: &str) -> anyhow::Result<()> {
+    bail!("export needs jmeter-to-goose built with the `serde` feature")
+}
+
+#[cfg(not(feature = "serde"))]
+fn import(_: &str, _: &str) -> anyhow::Result<JmeterTestPlan> {
+    bail!("import needs jmeter-to-goose built with the `serde` feature")
+}
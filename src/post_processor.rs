@@ -0,0 +1,405 @@
+use crate::{test_element, FromJmx, GooseCodegen, JmxElement, PropertyBag, Serializer, ToProperty};
+use xmltree::Element;
+
+/// Stores the `matches: Vec<String>` computed by an extractor snippet the way
+/// JMeter does: `match_number > 0` picks the nth match, `0` a random one, and
+/// `-1`, like any negative number, exposes `ref_1..ref_N` plus `ref_matchNr`.
+/// The names and default are user text, only ever emitted as literals.
+fn store_matches(ref_name: &str, match_number: i32, default: &str) -> String {
+    let match_nr = format!("{ref_name}_matchNr");
+    match match_number {
+        ..=-1 => format!(
+            r#"    vars.insert({ref_name:?}.to_string(), {default:?}.to_string());
+    let previous = vars
+        .get({match_nr:?})
+        .and_then(|n| n.parse::<usize>().ok())
+        .unwrap_or(0);
+    for i in matches.len() + 1..=previous {{
+        vars.remove(&format!("{{}}_{{i}}", {ref_name:?}));
+    }}
+    vars.insert({match_nr:?}.to_string(), matches.len().to_string());
+    for (i, value) in matches.iter().enumerate() {{
+        vars.insert(format!("{{}}_{{}}", {ref_name:?}, i + 1), value.clone());
+    }}
+"#
+        ),
+        0 => format!(
+            r#"    let value = rand::seq::SliceRandom::choose(matches.as_slice(), &mut rand::thread_rng())
+        .cloned()
+        .unwrap_or_else(|| {default:?}.to_string());
+    vars.insert({ref_name:?}.to_string(), value);
+"#
+        ),
+        n => format!(
+            r#"    let value = matches
+        .get({})
+        .cloned()
+        .unwrap_or_else(|| {default:?}.to_string());
+    vars.insert({ref_name:?}.to_string(), value);
+"#,
+            n - 1
+        ),
+    }
+}
+
+/// Boundary Extractor
+/// `<BoundaryExtractor>`
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "BoundaryExtractor", gui = "BoundaryExtractorGui")]
+pub struct BoundaryExtractor {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+    /// The scope searched: "false" for the body, "unescaped", "as_document",
+    /// "true" for the headers, "request_headers", "URL", "code" or "message"
+    #[jmx(prop = "BoundaryExtractor.useHeaders")]
+    pub use_headers: String,
+    #[jmx(prop = "BoundaryExtractor.refname")]
+    pub ref_name: String,
+    #[jmx(prop = "BoundaryExtractor.lboundary")]
+    pub left_boundary: String,
+    #[jmx(prop = "BoundaryExtractor.rboundary")]
+    pub right_boundary: String,
+    #[jmx(prop = "BoundaryExtractor.default")]
+    pub default: String,
+    #[jmx(prop = "BoundaryExtractor.default_empty_value")]
+    pub default_empty_value: bool,
+    /// JMeter reads an empty match number as 0 (random match)
+    #[jmx(prop = "BoundaryExtractor.match_number")]
+    pub match_number: i32,
+}
+
+impl GooseCodegen for BoundaryExtractor {
+    fn to_goose(&self) -> String {
+        // "as_document" extracts the text of documents with Tika, which
+        // leaves the body of text responses as it is
+        let source = match self.use_headers.as_str() {
+            "true" => "headers",
+            "unescaped" => "unescape_html(body)",
+            "request_headers" => "request_headers(&goose.request)",
+            "URL" => "url",
+            "code" => "status.to_string()",
+            "message" => {
+                "reqwest::StatusCode::from_u16(status)\n        .ok()\n        .and_then(|s| s.canonical_reason())\n        .unwrap_or_default()"
+            }
+            _ => "body",
+        };
+        let default = if self.default_empty_value {
+            ""
+        } else {
+            self.default.as_str()
+        };
+        format!(
+            r#"{{
+    let (left, right) = ({:?}, {:?});
+    let mut matches: Vec<String> = Vec::new();
+    let source = {source};
+    let mut rest: &str = &source;
+    while let Some(start) = rest.find(left) {{
+        let after = &rest[start + left.len()..];
+        if right.is_empty() {{
+            matches.push(after.to_string());
+            break;
+        }}
+        match after.find(right) {{
+            Some(end) => {{
+                matches.push(after[..end].to_string());
+                rest = &after[end + right.len()..];
+            }}
+            None => break,
+        }}
+    }}
+{}}}"#,
+            self.left_boundary,
+            self.right_boundary,
+            store_matches(&self.ref_name, self.match_number, default)
+        )
+    }
+}
+
+/// XPath2 Extractor
+/// `<XPath2Extractor>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XPath2Extractor {
+    pub test_name: String,
+    pub enabled: bool,
+    pub ref_name: String,
+    pub xpath_query: String,
+    pub default: String,
+    pub match_number: i32,
+    /// `prefix=uri` pairs, one per line in JMeter
+    pub namespaces: Vec<(String, String)>,
+    pub fragment: bool,
+}
+
+impl FromJmx for XPath2Extractor {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("XPath2Extractor")?;
+        let namespaces = e
+            .get_str("XPathExtractor2.namespaces")?
+            .lines()
+            .filter_map(|l| l.trim().split_once('='))
+            .map(|(prefix, uri)| (prefix.to_string(), uri.to_string()))
+            .collect::<Vec<(String, String)>>();
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            ref_name: e.get_str("XPathExtractor2.refname")?,
+            xpath_query: e.get_str("XPathExtractor2.xpathQuery")?,
+            default: e.get_str("XPathExtractor2.default")?,
+            match_number: e.get_i64("XPathExtractor2.matchNumber")?.try_into()?,
+            namespaces,
+            fragment: e.get_bool("XPathExtractor2.fragment")?,
+        })
+    }
+}
+
+impl Serializer for XPath2Extractor {
+    fn to_element(&self) -> Element {
+        let namespaces = self
+            .namespaces
+            .iter()
+            .map(|(prefix, uri)| format!("{prefix}={uri}"))
+            .collect::<Vec<String>>()
+            .join("\n");
+        test_element(
+            "XPath2Extractor",
+            "XPath2ExtractorGui",
+            &self.test_name,
+            self.enabled,
+            [
+                self.default.to_property("XPathExtractor2.default"),
+                self.ref_name.to_property("XPathExtractor2.refname"),
+                self.match_number.to_property("XPathExtractor2.matchNumber"),
+                self.xpath_query.to_property("XPathExtractor2.xpathQuery"),
+                namespaces.to_property("XPathExtractor2.namespaces"),
+                self.fragment.to_property("XPathExtractor2.fragment"),
+            ]
+            .iter()
+            .map(|p| p.to_element())
+            .collect(),
+        )
+    }
+}
+
+impl GooseCodegen for XPath2Extractor {
+    fn to_goose(&self) -> String {
+        let namespaces = self
+            .namespaces
+            .iter()
+            .map(|(prefix, uri)| format!("    context.set_namespace({prefix:?}, {uri:?});\n"))
+            .collect::<String>();
+        let context = if namespaces.is_empty() {
+            "context"
+        } else {
+            "mut context"
+        };
+        format!(
+            r#"{{
+    let {context} = sxd_xpath::Context::new();
+{namespaces}    let xpath = sxd_xpath::Factory::new().build({:?}).ok().flatten();
+    let matches: Vec<String> = match (sxd_document::parser::parse(body), xpath) {{
+        (Ok(package), Some(xpath)) => {{
+            let document = package.as_document();
+            match xpath.evaluate(&context, document.root()) {{
+                Ok(sxd_xpath::Value::Nodeset(nodes)) => nodes
+                    .document_order()
+                    .iter()
+                    .map(|n| n.string_value())
+                    .collect(),
+                Ok(value) => vec![value.string()],
+                Err(_) => Vec::new(),
+            }}
+        }}
+        _ => Vec::new(),
+    }};
+{}}}"#,
+            self.xpath_query,
+            store_matches(&self.ref_name, self.match_number, &self.default)
+        )
+    }
+}
+
+/// CSS Selector Extractor
+/// `<HtmlExtractor>`
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "HtmlExtractor", gui = "HtmlExtractorGui")]
+pub struct HtmlExtractor {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+    #[jmx(prop = "HtmlExtractor.refname")]
+    pub ref_name: String,
+    #[jmx(prop = "HtmlExtractor.expr")]
+    pub expr: String,
+    /// Empty means the element text is extracted
+    #[jmx(prop = "HtmlExtractor.attribute")]
+    pub attribute: String,
+    #[jmx(prop = "HtmlExtractor.default")]
+    pub default: String,
+    #[jmx(prop = "HtmlExtractor.default_empty_value")]
+    pub default_empty_value: bool,
+    #[jmx(prop = "HtmlExtractor.match_number")]
+    pub match_number: i32,
+}
+
+impl GooseCodegen for HtmlExtractor {
+    fn to_goose(&self) -> String {
+        let value = if self.attribute.is_empty() {
+            "e.text().collect::<String>()".to_string()
+        } else {
+            format!(
+                "e.value().attr({:?}).unwrap_or_default().to_string()",
+                self.attribute
+            )
+        };
+        let default = if self.default_empty_value {
+            ""
+        } else {
+            self.default.as_str()
+        };
+        format!(
+            r#"{{
+    let document = scraper::Html::parse_document(body);
+    let matches: Vec<String> = match scraper::Selector::parse({:?}) {{
+        Ok(selector) => document.select(&selector).map(|e| {value}).collect(),
+        Err(_) => Vec::new(),
+    }};
+{}}}"#,
+            self.expr,
+            store_matches(&self.ref_name, self.match_number, default)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{FromJmx, GooseCodegen, Serializer};
+    use xmltree::Element;
+
+    #[test]
+    fn check_parse_boundary_extractor() {
+        use crate::BoundaryExtractor;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/post_processor/boundary_extractor.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            BoundaryExtractor::parse(&xml).unwrap(),
+            BoundaryExtractor {
+                test_name: String::from("Boundary Extractor"),
+                enabled: true,
+                use_headers: String::from("false"),
+                ref_name: String::from("token"),
+                left_boundary: String::from("token=\""),
+                right_boundary: String::from("\""),
+                default: String::from("NOT_FOUND"),
+                default_empty_value: false,
+                match_number: 1,
+            }
+        )
+    }
+
+    #[test]
+    fn check_parse_xpath2_extractor() {
+        use crate::XPath2Extractor;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/post_processor/xpath2_extractor.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let extractor = XPath2Extractor::parse(&xml).unwrap();
+        assert_eq!(
+            XPath2Extractor::parse(&extractor.to_element()).unwrap(),
+            extractor
+        );
+        assert_eq!(
+            extractor,
+            XPath2Extractor {
+                test_name: String::from("XPath2 Extractor"),
+                enabled: true,
+                ref_name: String::from("title"),
+                xpath_query: String::from("//title/text()"),
+                default: String::from("none"),
+                match_number: -1,
+                namespaces: vec![(
+                    String::from("atom"),
+                    String::from("http://www.w3.org/2005/Atom")
+                )],
+                fragment: false,
+            }
+        );
+        let code = extractor.to_goose();
+        assert!(code
+            .contains(r#"vars.insert("title_matchNr".to_string(), matches.len().to_string());"#));
+        assert!(code.contains(r#"vars.insert(format!("{}_{}", "title", i + 1), value.clone());"#));
+        assert!(code.contains(r#"context.set_namespace("atom", "http://www.w3.org/2005/Atom");"#));
+    }
+
+    #[test]
+    fn check_parse_html_extractor() {
+        use crate::HtmlExtractor;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/post_processor/html_extractor.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            HtmlExtractor::parse(&xml).unwrap(),
+            HtmlExtractor {
+                test_name: String::from("CSS Selector Extractor"),
+                enabled: true,
+                ref_name: String::from("link"),
+                expr: String::from("a.nav"),
+                attribute: String::from("href"),
+                default: String::from(""),
+                default_empty_value: true,
+                match_number: 0,
+            }
+        )
+    }
+
+    #[test]
+    fn check_store_matches_user_text() {
+        use crate::BoundaryExtractor;
+
+        let mut extractor = BoundaryExtractor {
+            test_name: String::from("Boundary Extractor"),
+            enabled: true,
+            use_headers: String::from("false"),
+            ref_name: String::from(r#"a"{b}"#),
+            left_boundary: String::from("<"),
+            right_boundary: String::from(">"),
+            default: String::from(r#"none "{}" \"#),
+            default_empty_value: false,
+            match_number: 2,
+        };
+        let code = extractor.to_goose();
+        assert!(code.contains(r#".unwrap_or_else(|| "none \"{}\" \\".to_string());"#));
+        assert!(code.contains(r#"vars.insert("a\"{b}".to_string(), value);"#));
+
+        // Any negative number stores all the matches, as -1 does
+        extractor.match_number = -3;
+        let code = extractor.to_goose();
+        assert!(!code.contains(".get(-"));
+        assert!(code.contains(r#"vars.remove(&format!("{}_{i}", "a\"{b}"));"#));
+        assert!(code.contains(r#"vars.insert(format!("{}_{}", "a\"{b}", i + 1), value.clone());"#));
+        assert!(code
+            .contains(r#"vars.insert("a\"{b}_matchNr".to_string(), matches.len().to_string());"#));
+    }
+}
@@ -0,0 +1,104 @@
+//! Java `.properties` files, the format of JMeter's `jmeter.properties`,
+//! `user.properties` and the files given with `-q`/`--addprop`.
+
+use std::collections::HashMap;
+
+/// Parses `text` the way `java.util.Properties.load` does: `#` and `!`
+/// comments, `key=value`, `key: value` or `key value` lines, lines continued
+/// by a trailing backslash and backslash escapes. Later keys override
+/// earlier ones.
+pub fn parse_properties(text: &str) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let mut line = line.trim_start().to_string();
+        if line.is_empty() || line.starts_with(['#', '!']) {
+            continue;
+        }
+        while continues(&line) {
+            line.pop();
+            match lines.next() {
+                Some(next) => line.push_str(next.trim_start()),
+                None => break,
+            }
+        }
+        let (key, value) = split(&line);
+        properties.insert(unescape(key), unescape(value));
+    }
+    properties
+}
+
+/// Whether `line` ends with an odd number of backslashes.
+fn continues(line: &str) -> bool {
+    line.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
+
+/// The key up to the first unescaped `=`, `:` or whitespace, and the value
+/// after it.
+fn split(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' | ':' => return (&line[..i], line[i + 1..].trim_start()),
+            ' ' | '\t' | '\u{c}' => {
+                let rest = line[i..].trim_start();
+                let rest = rest.strip_prefix(['=', ':']).unwrap_or(rest);
+                return (&line[..i], rest.trim_start());
+            }
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('f') => result.push('\u{c}'),
+            Some('u') => {
+                let hex = chars.by_ref().take(4).collect::<String>();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => result.push(c),
+                    None => result.push_str(&hex),
+                }
+            }
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parse_properties;
+
+    #[test]
+    fn check_parse_properties() {
+        let properties = parse_properties(
+            &std::fs::read_to_string("./unittests_data/properties/user.properties").unwrap(),
+        );
+        assert_eq!(properties["threads"], "50");
+        assert_eq!(properties["host"], "staging.example.com");
+        assert_eq!(properties["ramp"], "10");
+        assert_eq!(properties["jmeter.save.saveservice.output_format"], "csv");
+        assert_eq!(properties["path"], "/api/v1/items");
+        assert_eq!(properties["greeting"], "Grüße, \"world\"");
+        assert_eq!(properties["key with spaces"], "value");
+        assert_eq!(properties["empty"], "");
+        assert_eq!(properties["duration"], "120");
+        assert!(!properties.contains_key("commented"));
+        assert_eq!(properties.len(), 9);
+    }
+}
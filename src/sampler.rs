@@ -0,0 +1,458 @@
+use crate::element_prop::{arguments_prop, element_prop};
+use crate::{
+    test_element, CollectionProp, ElementArgument, ElementHttpFileArg, FromJmx, JmxElement,
+    Property, PropertyBag, Serializer, ToProperty,
+};
+use std::path::Path;
+use xmltree::{Element, XMLNode};
+
+/// `let form = reqwest::multipart::Form::new()...;` with a text part per
+/// argument and a file part per upload, read from its fixture in the
+/// generated crate or, for paths read at run time, from wherever the path
+/// points then. Values and paths are substituted like other arguments.
+pub(crate) fn multipart_form(
+    arguments: &[ElementArgument],
+    files: &[ElementHttpFileArg],
+) -> String {
+    let mut form = String::from("let form = reqwest::multipart::Form::new()");
+    for argument in arguments {
+        form.push_str(&format!(
+            "\n    .text({:?}, substitute({:?}, vars))",
+            argument.name, argument.value
+        ));
+    }
+    for file in files {
+        let mime = if file.mime_type.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\n        .mime_str({:?})\n        .unwrap()",
+                file.mime_type
+            )
+        };
+        let part = if file.is_read_at_run_time() {
+            format!(
+                r#"{{
+            let path = substitute({:?}, vars);
+            let file_name = path.rsplit(['/', '\\']).next().unwrap_or_default().to_string();
+            reqwest::multipart::Part::bytes(std::fs::read(&path).expect(&path)).file_name(file_name)
+        }}"#,
+                file.path
+            )
+        } else {
+            let fixture = match file.fixture_path() {
+                Ok(fixture) => fixture,
+                Err(e) => {
+                    form.push_str(&format!(
+                        "\n    // TODO(jmeter-to-goose): {:?}",
+                        e.to_string()
+                    ));
+                    continue;
+                }
+            };
+            let path = format!("/{}", fixture.to_string_lossy().replace('\\', "/"));
+            let file_name = fixture
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            format!(
+                r#"reqwest::multipart::Part::bytes(
+            std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), {path:?}))
+                .expect({:?}),
+        )
+        .file_name({file_name:?})"#,
+                format!("fixture {}", &path[1..])
+            )
+        };
+        form.push_str(&format!(
+            "\n    .part(\n        {:?},\n        {part}{mime},\n    )",
+            file.param_name
+        ));
+    }
+    form.push(';');
+    form
+}
+
+/// HTTP Request
+/// `<HTTPSamplerProxy>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HttpSampler {
+    pub test_name: String,
+    pub enabled: bool,
+    pub domain: String,
+    pub port: String,
+    pub protocol: String,
+    pub path: String,
+    pub method: String,
+    pub content_encoding: String,
+    pub follow_redirects: bool,
+    pub auto_redirects: bool,
+    pub use_keepalive: bool,
+    pub do_multipart_post: bool,
+    /// The arguments' values are the body, sent as they are
+    pub post_body_raw: bool,
+    /// Milliseconds, blank for the system's
+    pub connect_timeout: String,
+    /// Milliseconds, blank to wait forever
+    pub response_timeout: String,
+    /// `HttpClient4` or `Java`, blank for JMeter's default
+    pub implementation: String,
+    /// Embedded resources of HTML responses are fetched too
+    pub image_parser: bool,
+    /// Embedded resources are fetched in parallel
+    pub concurrent_dwn: bool,
+    /// How many embedded resources are fetched at a time, blank for 6
+    pub concurrent_pool: String,
+    /// Only embedded resources matching it are fetched
+    pub embedded_url_re: String,
+    pub arguments: Vec<ElementArgument>,
+    pub files: Vec<ElementHttpFileArg>,
+}
+
+impl FromJmx for HttpSampler {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("HTTPSamplerProxy")?;
+        // The `<elementProp>`s of `collection` in the element under `name`
+        // whose `elementType` is one of `types`. JMeter omits the element
+        // when it is empty.
+        let items = |name: &str, collection: &str, types: &[&str]| match e.get(name) {
+            None => Ok(vec![]),
+            Some(_) => e.get_element(name)?.get_collection(collection).map(|c| {
+                c.iter()
+                    .filter(|p| {
+                        p.as_element()
+                            .is_some_and(|p| types.contains(&p.element_type.as_str()))
+                    })
+                    .collect::<Vec<&Property>>()
+            }),
+        };
+        let arguments = items(
+            "HTTPsampler.Arguments",
+            "Arguments.arguments",
+            &["Argument", "HTTPArgument"],
+        )?
+        .into_iter()
+        .map(ElementArgument::try_from)
+        .collect::<anyhow::Result<Vec<ElementArgument>>>()?;
+        let files = items("HTTPsampler.Files", "HTTPFileArgs.files", &["HTTPFileArg"])?
+            .into_iter()
+            .map(ElementHttpFileArg::try_from)
+            .collect::<anyhow::Result<Vec<ElementHttpFileArg>>>()?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            domain: e.get_str("HTTPSampler.domain")?,
+            port: e.get_str("HTTPSampler.port")?,
+            protocol: e.get_str("HTTPSampler.protocol")?,
+            path: e.get_str("HTTPSampler.path")?,
+            method: e.get_str("HTTPSampler.method")?,
+            content_encoding: e.get_str("HTTPSampler.contentEncoding")?,
+            follow_redirects: e.get_bool("HTTPSampler.follow_redirects")?,
+            auto_redirects: e.get_bool("HTTPSampler.auto_redirects")?,
+            use_keepalive: e.get_bool("HTTPSampler.use_keepalive")?,
+            do_multipart_post: e.get_bool("HTTPSampler.DO_MULTIPART_POST")?,
+            post_body_raw: e.get_bool("HTTPSampler.postBodyRaw")?,
+            connect_timeout: e.get_str("HTTPSampler.connect_timeout")?,
+            response_timeout: e.get_str("HTTPSampler.response_timeout")?,
+            implementation: e.get_str("HTTPSampler.implementation")?,
+            image_parser: e.get_bool("HTTPSampler.image_parser")?,
+            concurrent_dwn: e.get_bool("HTTPSampler.concurrentDwn")?,
+            concurrent_pool: e.get_str("HTTPSampler.concurrentPool")?,
+            embedded_url_re: e.get_str("HTTPSampler.embedded_url_re")?,
+            arguments,
+            files,
+        })
+    }
+}
+
+impl HttpSampler {
+    /// JMeter sends a multipart body when asked to, or whenever a POST
+    /// carries files.
+    pub fn is_multipart(&self) -> bool {
+        self.do_multipart_post || (self.method == "POST" && !self.files.is_empty())
+    }
+
+    /// The body of a sampler with `post_body_raw`, its arguments' values one
+    /// after another as JMeter sends them.
+    pub fn raw_body(&self) -> Option<String> {
+        self.post_body_raw
+            .then(|| self.arguments.iter().map(|a| a.value.as_str()).collect())
+    }
+
+    /// Builds `let form = reqwest::multipart::Form::new()...;` for multipart
+    /// samplers, to be attached with `.multipart(form)` on the request
+    /// builder. Files are read from the fixtures copied by [`Self::copy_fixtures`].
+    pub fn multipart_form(&self) -> Option<String> {
+        if !self.is_multipart() {
+            return None;
+        }
+        Some(multipart_form(&self.arguments, &self.files))
+    }
+
+    /// Copies the sampler's upload files with
+    /// [`ElementHttpFileArg::copy_fixture`].
+    pub fn copy_fixtures(&self, jmx_dir: &Path, out_dir: &Path) -> std::io::Result<()> {
+        self.files
+            .iter()
+            .try_for_each(|file| file.copy_fixture(jmx_dir, out_dir))
+    }
+}
+
+impl Serializer for HttpSampler {
+    fn to_element(&self) -> Element {
+        let files = CollectionProp {
+            props: self
+                .files
+                .iter()
+                .map(|f| ElementHttpFileArg {
+                    path: f.path.clone(),
+                    param_name: f.param_name.clone(),
+                    mime_type: f.mime_type.clone(),
+                })
+                .collect::<Vec<ElementHttpFileArg>>(),
+        };
+        let mut arguments = Element::new("collectionProp");
+        arguments
+            .attributes
+            .insert(String::from("name"), String::from("Arguments.arguments"));
+        arguments.children = self
+            .arguments
+            .iter()
+            .map(|a| XMLNode::Element(a.to_http_element()))
+            .collect();
+        let mut props = vec![];
+        // JMeter only saves the flag for raw bodies
+        if self.post_body_raw {
+            props.push(
+                self.post_body_raw
+                    .to_property("HTTPSampler.postBodyRaw")
+                    .to_element(),
+            );
+        }
+        if !self.files.is_empty() {
+            props.push(element_prop(
+                "HTTPsampler.Files",
+                "HTTPFileArgs",
+                vec![files.to_element()],
+            ));
+        }
+        props.push(arguments_prop(
+            "HTTPsampler.Arguments",
+            "HTTPArgumentsPanel",
+            arguments,
+        ));
+        props.extend(
+            [
+                self.domain.to_property("HTTPSampler.domain"),
+                self.port.to_property("HTTPSampler.port"),
+                self.protocol.to_property("HTTPSampler.protocol"),
+                self.content_encoding
+                    .to_property("HTTPSampler.contentEncoding"),
+                self.path.to_property("HTTPSampler.path"),
+                self.method.to_property("HTTPSampler.method"),
+                self.follow_redirects
+                    .to_property("HTTPSampler.follow_redirects"),
+                self.auto_redirects
+                    .to_property("HTTPSampler.auto_redirects"),
+                self.use_keepalive.to_property("HTTPSampler.use_keepalive"),
+                self.do_multipart_post
+                    .to_property("HTTPSampler.DO_MULTIPART_POST"),
+                self.implementation
+                    .to_property("HTTPSampler.implementation"),
+                self.image_parser.to_property("HTTPSampler.image_parser"),
+                self.concurrent_dwn.to_property("HTTPSampler.concurrentDwn"),
+                self.concurrent_pool
+                    .to_property("HTTPSampler.concurrentPool"),
+                self.embedded_url_re
+                    .to_property("HTTPSampler.embedded_url_re"),
+                self.connect_timeout
+                    .to_property("HTTPSampler.connect_timeout"),
+                self.response_timeout
+                    .to_property("HTTPSampler.response_timeout"),
+            ]
+            .iter()
+            .map(|p| p.to_element()),
+        );
+        test_element(
+            "HTTPSamplerProxy",
+            "HttpTestSampleGui",
+            &self.test_name,
+            self.enabled,
+            props,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{FromJmx, HttpSampler, Serializer};
+    use std::path::Path;
+    use xmltree::Element;
+
+    #[test]
+    fn check_parse_http_sampler_upload() {
+        use crate::{ElementArgument, ElementHttpFileArg};
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/sampler/http_sampler_upload.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let sampler = HttpSampler::parse(&xml).unwrap();
+        assert_eq!(HttpSampler::parse(&sampler.to_element()).unwrap(), sampler);
+        assert_eq!(
+            sampler,
+            HttpSampler {
+                test_name: String::from("Upload avatar"),
+                enabled: true,
+                domain: String::from("${ip}"),
+                port: String::from(""),
+                protocol: String::from("${protocol}"),
+                path: String::from("/upload"),
+                method: String::from("POST"),
+                content_encoding: String::from(""),
+                follow_redirects: true,
+                auto_redirects: false,
+                use_keepalive: true,
+                do_multipart_post: true,
+                post_body_raw: false,
+                connect_timeout: String::new(),
+                response_timeout: String::new(),
+                implementation: String::new(),
+                image_parser: false,
+                concurrent_dwn: false,
+                concurrent_pool: String::new(),
+                embedded_url_re: String::new(),
+                arguments: vec![ElementArgument {
+                    name: String::from("description"),
+                    value: String::from("my avatar"),
+                    metadata: String::from("=")
+                }],
+                files: vec![ElementHttpFileArg {
+                    path: String::from("data/avatar.png"),
+                    param_name: String::from("avatar"),
+                    mime_type: String::from("image/png"),
+                }],
+            }
+        );
+        assert_eq!(
+            sampler.multipart_form().unwrap(),
+            r#"let form = reqwest::multipart::Form::new()
+    .text("description", substitute("my avatar", vars))
+    .part(
+        "avatar",
+        reqwest::multipart::Part::bytes(
+            std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/data/avatar.png"))
+                .expect("fixture fixtures/data/avatar.png"),
+        )
+        .file_name("avatar.png")
+        .mime_str("image/png")
+        .unwrap(),
+    );"#
+        );
+
+        // A path with a variable is read where it points at run time
+        let mut sampler = sampler;
+        sampler.files[0].path = String::from("${avatars}/${user}.png");
+        assert!(sampler
+            .multipart_form()
+            .unwrap()
+            .contains(r#"let path = substitute("${avatars}/${user}.png", vars);"#));
+    }
+
+    #[test]
+    fn check_parse_http_sampler_prop_kinds() {
+        // Booleans saved as `<stringProp>`, a timeout as `<intProp>` and an
+        // upload with a `<boolProp>`, as other JMeter versions and plugins do
+        let jmx = std::fs::read_to_string("./unittests_data/sampler/http_sampler_upload.xml")
+            .unwrap()
+            .replace(
+                r#"<boolProp name="HTTPSampler.follow_redirects">true</boolProp>"#,
+                r#"<stringProp name="HTTPSampler.follow_redirects">true</stringProp>"#,
+            )
+            .replace(
+                r#"<stringProp name="HTTPSampler.connect_timeout"></stringProp>"#,
+                r#"<intProp name="HTTPSampler.connect_timeout">2000</intProp>"#,
+            )
+            .replace(
+                r#"<stringProp name="File.paramname">avatar</stringProp>"#,
+                r#"<stringProp name="File.paramname">avatar</stringProp><boolProp name="File.encode">true</boolProp>"#,
+            );
+        let sampler = HttpSampler::parse(&Element::parse(jmx.trim().as_bytes()).unwrap()).unwrap();
+        assert!(sampler.follow_redirects);
+        assert_eq!(sampler.connect_timeout, "2000");
+        assert_eq!(sampler.files[0].param_name, "avatar");
+
+        let jmx = jmx.replace(
+            r#"<boolProp name="HTTPSampler.use_keepalive">true</boolProp>"#,
+            r#"<collectionProp name="HTTPSampler.use_keepalive"/>"#,
+        );
+        let error =
+            HttpSampler::parse(&Element::parse(jmx.trim().as_bytes()).unwrap()).unwrap_err();
+        assert!(format!("{error:#}").starts_with("<HTTPSamplerProxy>: HTTPSampler.use_keepalive"));
+    }
+
+    #[test]
+    fn check_parse_http_sampler_raw_body() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/sampler/http_sampler_raw_body.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let sampler = HttpSampler::parse(&xml).unwrap();
+        assert_eq!(HttpSampler::parse(&sampler.to_element()).unwrap(), sampler);
+        assert!(sampler.post_body_raw);
+        assert_eq!(sampler.method, "PUT");
+        assert_eq!(sampler.arguments.len(), 1);
+        assert_eq!(sampler.arguments[0].name, "");
+        assert_eq!(
+            sampler.raw_body().unwrap(),
+            r#"{"name": "${name}", "newsletter": true}"#
+        );
+    }
+
+    #[test]
+    fn check_parse_http_sampler_timeouts() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/sampler/http_sampler_timeouts.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let sampler = HttpSampler::parse(&xml).unwrap();
+        assert_eq!(HttpSampler::parse(&sampler.to_element()).unwrap(), sampler);
+        assert!(!sampler.use_keepalive);
+        assert_eq!(sampler.connect_timeout, "2000");
+        assert_eq!(sampler.response_timeout, "${report_timeout}");
+        assert_eq!(sampler.implementation, "Java");
+        assert!(sampler.concurrent_dwn);
+        assert_eq!(sampler.embedded_url_re, r".*\.css");
+    }
+
+    #[test]
+    fn check_copy_fixtures() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/sampler/http_sampler_upload.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let out_dir = std::env::temp_dir().join("jmeter-to-goose-copy-fixtures");
+        HttpSampler::parse(&xml)
+            .unwrap()
+            .copy_fixtures(Path::new("./unittests_data/sampler"), &out_dir)
+            .unwrap();
+        assert_eq!(
+            std::fs::read(out_dir.join("fixtures/data/avatar.png")).unwrap(),
+            std::fs::read("./unittests_data/sampler/data/avatar.png").unwrap()
+        );
+    }
+}
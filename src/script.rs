@@ -0,0 +1,405 @@
+use crate::{test_element, Deserializer, GooseCodegen, Serializer, StringProp, ToProperty};
+use anyhow::bail;
+use xmltree::Element;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScriptKind {
+    PreProcessor,
+    PostProcessor,
+    Sampler,
+}
+
+/// JSR223 and BeanShell scripting elements
+/// `<JSR223PreProcessor>`, `<JSR223PostProcessor>`, `<JSR223Sampler>`,
+/// `<BeanShellPreProcessor>`, `<BeanShellPostProcessor>`, `<BeanShellSampler>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScriptElement {
+    pub kind: ScriptKind,
+    pub test_name: String,
+    pub enabled: bool,
+    pub language: String,
+    pub parameters: String,
+    pub filename: String,
+    pub script: String,
+}
+
+impl ScriptElement {
+    pub fn parse(e: &Element) -> anyhow::Result<Self> {
+        let (kind, prefix, language) = match e.name.as_str() {
+            "JSR223PreProcessor" => (ScriptKind::PreProcessor, "", None),
+            "JSR223PostProcessor" => (ScriptKind::PostProcessor, "", None),
+            "JSR223Sampler" => (ScriptKind::Sampler, "", None),
+            "BeanShellPreProcessor" => (ScriptKind::PreProcessor, "", Some("beanshell")),
+            "BeanShellPostProcessor" => (ScriptKind::PostProcessor, "", Some("beanshell")),
+            "BeanShellSampler" => (ScriptKind::Sampler, "BeanShellSampler.", Some("beanshell")),
+            other => bail!("unexpected script element <{other}>"),
+        };
+        let test_name = e.attributes.get("testname").cloned().unwrap_or_default();
+        // JMeter treats a missing flag as enabled
+        let enabled = e.attributes.get("enabled").is_none_or(|v| v != "false");
+        let props = e
+            .children
+            .iter()
+            .filter_map(|x| x.as_element())
+            .filter(|x| x.name == "stringProp")
+            .map(StringProp::parse)
+            .collect::<Vec<StringProp>>();
+        let find = |name: &str| {
+            props
+                .iter()
+                .find(|p| p.name == name)
+                .map(|p| p.value.clone())
+                .unwrap_or_default()
+        };
+        let language = match language {
+            Some(language) => language.to_string(),
+            None => find("scriptLanguage"),
+        };
+        let script_prop = if prefix.is_empty() { "script" } else { "query" };
+        Ok(Self {
+            kind,
+            test_name,
+            enabled,
+            language,
+            parameters: find(&format!("{prefix}parameters")),
+            filename: find(&format!("{prefix}filename")),
+            script: find(&format!("{prefix}{script_prop}")),
+        })
+    }
+
+    /// Translates the script into Rust statements, or `None` when any statement
+    /// falls outside the recognized `vars`/`props`/`prev`/`log` subset.
+    pub fn translate(&self) -> Option<Vec<String>> {
+        if !self.filename.is_empty() {
+            return None;
+        }
+        self.script
+            .lines()
+            .flat_map(|l| split_top_level(l, ';'))
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty() && !s.starts_with("//"))
+            .map(|s| self.translate_statement(s))
+            .collect()
+    }
+
+    fn translate_statement(&self, statement: &str) -> Option<String> {
+        let (target, args) = call(statement)?;
+        match (target, args.as_slice()) {
+            ("vars.put", [key, value]) => Some(format!(
+                "vars.insert({}, {});",
+                self.translate_expr(key)?,
+                self.translate_expr(value)?
+            )),
+            ("props.put", [key, value]) => Some(format!(
+                "props.insert({}, {});",
+                self.translate_expr(key)?,
+                self.translate_expr(value)?
+            )),
+            ("vars.remove", [key]) => Some(format!("vars.remove(&{});", self.translate_expr(key)?)),
+            ("props.remove", [key]) => {
+                Some(format!("props.remove(&{});", self.translate_expr(key)?))
+            }
+            ("log.info" | "log.warn" | "log.error" | "log.debug", [message]) => Some(format!(
+                "log::{}!(\"{{}}\", {});",
+                &target[4..],
+                self.translate_expr(message)?
+            )),
+            _ => None,
+        }
+    }
+
+    /// Translates a `+` concatenation of literals and accessor calls into a
+    /// `String` expression.
+    fn translate_expr(&self, expr: &str) -> Option<String> {
+        let terms = split_top_level(expr, '+')
+            .into_iter()
+            .map(|t| self.translate_term(t.trim()))
+            .collect::<Option<Vec<String>>>()?;
+        match terms.as_slice() {
+            [term] => Some(term.clone()),
+            _ => Some(format!(
+                "format!(\"{}\", {})",
+                "{}".repeat(terms.len()),
+                terms.join(", ")
+            )),
+        }
+    }
+
+    fn translate_term(&self, term: &str) -> Option<String> {
+        if let Some(literal) = string_literal(term) {
+            return Some(format!("{literal:?}.to_string()"));
+        }
+        if !term.is_empty() && term.chars().all(|c| c.is_ascii_digit()) {
+            return Some(format!("{term:?}.to_string()"));
+        }
+        let (target, args) = call(term)?;
+        let prev = self.kind == ScriptKind::PostProcessor;
+        match (target, args.as_slice()) {
+            ("vars.get", [key]) => Some(format!(
+                "vars.get(&{}).cloned().unwrap_or_default()",
+                self.translate_expr(key)?
+            )),
+            ("props.get", [key]) => Some(format!(
+                "props.get(&{}).cloned().unwrap_or_default()",
+                self.translate_expr(key)?
+            )),
+            ("prev.getResponseCode", []) if prev => Some("status.to_string()".to_string()),
+            ("prev.getResponseDataAsString", []) if prev => Some("body.to_string()".to_string()),
+            ("String.valueOf", [value]) => self.translate_expr(value),
+            _ => None,
+        }
+    }
+
+    fn stub_name(&self) -> String {
+        let name = self
+            .test_name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        format!("todo_{}", name.trim_matches('_'))
+    }
+}
+
+impl Serializer for ScriptElement {
+    /// BeanShell elements are written back as BeanShell, anything else as
+    /// JSR223 with its `scriptLanguage`.
+    fn to_element(&self) -> Element {
+        let kind = match self.kind {
+            ScriptKind::PreProcessor => "PreProcessor",
+            ScriptKind::PostProcessor => "PostProcessor",
+            ScriptKind::Sampler => "Sampler",
+        };
+        let (tag, gui, props) = match (self.language.as_str(), self.kind) {
+            ("beanshell", ScriptKind::Sampler) => (
+                String::from("BeanShellSampler"),
+                "BeanShellSamplerGui",
+                vec![
+                    self.script.to_property("BeanShellSampler.query"),
+                    self.filename.to_property("BeanShellSampler.filename"),
+                    self.parameters.to_property("BeanShellSampler.parameters"),
+                    false.to_property("BeanShellSampler.resetInterpreter"),
+                ],
+            ),
+            ("beanshell", _) => (
+                format!("BeanShell{kind}"),
+                "TestBeanGUI",
+                vec![
+                    self.filename.to_property("filename"),
+                    self.parameters.to_property("parameters"),
+                    false.to_property("resetInterpreter"),
+                    self.script.to_property("script"),
+                ],
+            ),
+            _ => (
+                format!("JSR223{kind}"),
+                "TestBeanGUI",
+                vec![
+                    self.language.to_property("scriptLanguage"),
+                    self.parameters.to_property("parameters"),
+                    self.filename.to_property("filename"),
+                    String::from("true").to_property("cacheKey"),
+                    self.script.to_property("script"),
+                ],
+            ),
+        };
+        test_element(
+            &tag,
+            gui,
+            &self.test_name,
+            self.enabled,
+            props.iter().map(|p| p.to_element()).collect(),
+        )
+    }
+}
+
+impl GooseCodegen for ScriptElement {
+    fn to_goose(&self) -> String {
+        if let Some(statements) = self.translate() {
+            let body = statements
+                .iter()
+                .map(|s| format!("    {s}\n"))
+                .collect::<String>();
+            return format!("{{\n{body}}}");
+        }
+        let (params, args) = match self.kind {
+            ScriptKind::PostProcessor => (
+                "vars: &mut HashMap<String, String>, props: &mut HashMap<String, String>, body: &str, status: u16",
+                "vars, props, body, status",
+            ),
+            _ => (
+                "vars: &mut HashMap<String, String>, props: &mut HashMap<String, String>",
+                "vars, props",
+            ),
+        };
+        let source = if self.filename.is_empty() {
+            self.script
+                .lines()
+                .map(|l| format!("        // {l}\n"))
+                .collect::<String>()
+        } else {
+            format!("        // script file: {:?}\n", self.filename)
+        };
+        let name = self.stub_name();
+        format!(
+            r#"{{
+    // TODO(jmeter-to-goose): {:?} ({:?}) could not be translated, port it by hand.
+    #[allow(unused_variables)]
+    fn {name}({params}) {{
+        // parameters: {:?}
+{source}    }}
+    {name}({args});
+}}"#,
+            self.test_name, self.language, self.parameters
+        )
+    }
+}
+
+/// Splits on `sep` outside of quotes and parentheses.
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                c if c == sep && depth == 0 => {
+                    parts.push(&s[start..i]);
+                    start = i + c.len_utf8();
+                }
+                _ => {}
+            },
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Splits `target(arg, ...)` into the target and its arguments.
+fn call(s: &str) -> Option<(&str, Vec<&str>)> {
+    let (target, rest) = s.split_once('(')?;
+    let args = rest.strip_suffix(')')?;
+    if !target
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+    {
+        return None;
+    }
+    let args = match args.trim() {
+        "" => vec![],
+        args => split_top_level(args, ','),
+    };
+    Some((target.trim(), args))
+}
+
+/// Unquotes a Groovy string literal. GStrings with `${...}` interpolation are
+/// not treated as literals.
+fn string_literal(s: &str) -> Option<String> {
+    let quote = s.chars().next()?;
+    if !(quote == '"' || quote == '\'') || s.len() < 2 || !s.ends_with(quote) {
+        return None;
+    }
+    let inner = &s[1..s.len() - 1];
+    if quote == '"' && inner.contains("${") {
+        return None;
+    }
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                c => value.push(c),
+            },
+            c if c == quote => return None,
+            c => value.push(c),
+        }
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GooseCodegen, ScriptElement, ScriptKind, Serializer};
+    use xmltree::Element;
+
+    #[test]
+    fn check_parse_jsr223_post_processor() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/script/jsr223_post_processor.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let script = ScriptElement::parse(&xml).unwrap();
+        assert_eq!(ScriptElement::parse(&script.to_element()).unwrap(), script);
+        assert_eq!(
+            script,
+            ScriptElement {
+                kind: ScriptKind::PostProcessor,
+                test_name: String::from("Save status"),
+                enabled: true,
+                language: String::from("groovy"),
+                parameters: String::from(""),
+                filename: String::from(""),
+                script: String::from(
+                    "vars.put(\"code\", prev.getResponseCode());\nlog.info(\"status \" + vars.get(\"code\"))"
+                ),
+            }
+        );
+        assert_eq!(
+            script.to_goose(),
+            r#"{
+    vars.insert("code".to_string(), status.to_string());
+    log::info!("{}", format!("{}{}", "status ".to_string(), vars.get(&"code".to_string()).cloned().unwrap_or_default()));
+}"#
+        );
+    }
+
+    #[test]
+    fn check_parse_bean_shell_sampler() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/script/bean_shell_sampler.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let script = ScriptElement::parse(&xml).unwrap();
+        assert_eq!(ScriptElement::parse(&script.to_element()).unwrap(), script);
+        assert_eq!(script.kind, ScriptKind::Sampler);
+        assert_eq!(script.language, "beanshell");
+        assert_eq!(script.translate(), None);
+        let code = script.to_goose();
+        assert!(code.contains("fn todo_build_payload(vars: &mut HashMap<String, String>"));
+        assert!(code.contains("        // int n = Integer.parseInt(vars.get(\"count\"));\n"));
+
+        // Parameters and file names stay on their comment line
+        let script = ScriptElement {
+            parameters: String::from("a\n}\nfn injected() {"),
+            filename: String::from("x.bsh\nfn other() {}"),
+            ..script
+        };
+        let code = script.to_goose();
+        assert!(code.contains("        // parameters: \"a\\n}\\nfn injected() {\"\n"));
+        assert!(code.contains("        // script file: \"x.bsh\\nfn other() {}\"\n"));
+    }
+}
@@ -0,0 +1,12 @@
+//! Serializes a `HashMap` with its keys in order, so exports of the same plan
+//! are identical. Deserializing needs nothing special.
+
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+
+pub fn serialize<S: Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().collect::<BTreeMap<&String, &V>>())
+}
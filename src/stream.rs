@@ -0,0 +1,180 @@
+//! Parses a .jmx straight from XML events.
+//!
+//! [`TestClass::root`] needs the whole document as an `Element` first, which
+//! for plans of hundreds of megabytes costs more memory than the model
+//! itself. [`TestClass::from_reader`] only materializes one element at a
+//! time: each node's own element, handed to the same parsers, the
+//! `<hashTree>` of elements kept as `Unknown`, and other elements under
+//! `<jmeterTestPlan>`.
+//!
+//! The `<hashTree>` of an `Unknown` element is the limit. Simple and
+//! Transaction Controllers are streamed into, but a Loop, If or While
+//! Controller is read with everything beneath it, so a plan nesting its
+//! samplers in one holds their XML in memory much like the DOM would.
+//! `benches/parse.rs` measures such a plan next to the others.
+
+use crate::{JmeterTestPlan, TestClass};
+use anyhow::bail;
+use std::io::Read;
+use xml::attribute::OwnedAttribute;
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
+use xml::reader::{EventReader, ParserConfig, XmlEvent};
+use xmltree::{Element, XMLNode};
+
+/// A start tag as xml-rs reports it
+type StartTag = (OwnedName, Vec<OwnedAttribute>, Namespace);
+
+impl JmeterTestPlan {
+    /// Same as [`JmeterTestPlan::parse`] on the parsed document, without
+    /// building it.
+    pub fn from_reader<R: Read>(reader: R) -> anyhow::Result<Self> {
+        let config = ParserConfig::new().ignore_comments(false);
+        let mut reader = EventReader::new_with_config(reader, config);
+        let attributes = match next_start(&mut reader)? {
+            Some((name, attributes, _)) if name.local_name == "jmeterTestPlan" => attributes,
+            _ => bail!("expected <jmeterTestPlan>"),
+        };
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|a| a.name.local_name == name)
+                .map(|a| a.value.clone())
+                .unwrap_or_default()
+        };
+        let mut nodes = None;
+        let mut extra = vec![];
+        while let Some(start) = next_start(&mut reader)? {
+            if start.0.local_name == "hashTree" && nodes.is_none() {
+                nodes = Some(hash_tree(&mut reader)?);
+            } else {
+                extra.push(element(&mut reader, start)?);
+            }
+        }
+        let Some(nodes) = nodes else {
+            bail!("expected <hashTree> in <jmeterTestPlan>");
+        };
+        Ok(Self {
+            version: attribute("version"),
+            properties: attribute("properties"),
+            jmeter: attribute("jmeter"),
+            nodes,
+            extra,
+        })
+    }
+}
+
+impl TestClass {
+    /// Same as [`TestClass::root`] on the parsed document, without building
+    /// it.
+    pub fn from_reader<R: Read>(reader: R) -> anyhow::Result<Self> {
+        JmeterTestPlan::from_reader(reader)?.into_test_plan()
+    }
+}
+
+/// The start tag of the next element of the current one, `None` at its end.
+fn next_start<R: Read>(reader: &mut EventReader<R>) -> anyhow::Result<Option<StartTag>> {
+    loop {
+        match reader.next()? {
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => return Ok(Some((name, attributes, namespace))),
+            XmlEvent::EndElement { .. } | XmlEvent::EndDocument => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// The nodes of a `<hashTree>` whose start tag was just read, up to its end
+/// tag.
+fn hash_tree<R: Read>(reader: &mut EventReader<R>) -> anyhow::Result<Vec<TestClass>> {
+    let mut nodes = vec![];
+    while let Some(start) = next_start(reader)? {
+        let e = element(reader, start)?;
+        let tree = match next_start(reader)? {
+            Some(start) if start.0.local_name == "hashTree" => start,
+            _ => bail!("expected <hashTree> after <{}>", e.name),
+        };
+        nodes.push(match TestClass::node_parser(&e) {
+            Some(parser) => parser(&e, hash_tree(reader)?)?,
+            None => TestClass::Unknown(e, element(reader, tree)?),
+        });
+    }
+    Ok(nodes)
+}
+
+/// Reads the rest of the element started by `start`, as
+/// `xmltree::Element::parse` would.
+fn element<R: Read>(
+    reader: &mut EventReader<R>,
+    (name, attributes, namespace): StartTag,
+) -> anyhow::Result<Element> {
+    let mut e = Element::new(&name.local_name);
+    e.prefix = name.prefix;
+    e.namespace = name.namespace;
+    e.namespaces = (!namespace.is_essentially_empty()).then_some(namespace);
+    for attribute in attributes {
+        e.attributes
+            .insert(attribute.name.local_name, attribute.value);
+    }
+    loop {
+        match reader.next()? {
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                let child = element(reader, (name, attributes, namespace))?;
+                e.children.push(XMLNode::Element(child));
+            }
+            XmlEvent::EndElement { .. } => return Ok(e),
+            XmlEvent::Characters(s) | XmlEvent::CData(s) => e.children.push(XMLNode::Text(s)),
+            XmlEvent::Comment(s) => e.children.push(XMLNode::Comment(s)),
+            XmlEvent::ProcessingInstruction { name, data } => {
+                e.children.push(XMLNode::ProcessingInstruction(name, data))
+            }
+            XmlEvent::Whitespace(_) | XmlEvent::StartDocument { .. } => {}
+            XmlEvent::EndDocument => bail!("unexpected end of document in <{}>", e.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::TestClass;
+    use std::fs::{read_to_string, File};
+    use xmltree::Element;
+
+    #[test]
+    fn check_from_reader() {
+        for path in [
+            "./unittests_data/test_class/user_variables.jmx",
+            "./unittests_data/ir/scoped.jmx",
+        ] {
+            let xml = Element::parse(read_to_string(path).unwrap().trim().as_bytes()).unwrap();
+            let streamed = TestClass::from_reader(File::open(path).unwrap()).unwrap();
+            assert_eq!(streamed, TestClass::root(&xml).unwrap());
+        }
+    }
+
+    #[test]
+    fn check_from_reader_errors() {
+        let err = TestClass::from_reader("<hashTree/>".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "expected <jmeterTestPlan>");
+        let err = TestClass::from_reader(
+            "<jmeterTestPlan><hashTree><TestPlan/></hashTree></jmeterTestPlan>".as_bytes(),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "expected <hashTree> after <TestPlan>");
+
+        // an element the model can't read fails the whole plan, as with the DOM
+        let jmx = "<jmeterTestPlan><hashTree><TestPlan/><hashTree/></hashTree></jmeterTestPlan>";
+        let expected = "<TestPlan>: TestPlan.user_defined_variables: missing elementProp";
+        let err = TestClass::from_reader(jmx.as_bytes()).unwrap_err();
+        assert_eq!(format!("{err:#}"), expected);
+        let err = TestClass::root(&Element::parse(jmx.as_bytes()).unwrap()).unwrap_err();
+        assert_eq!(format!("{err:#}"), expected);
+    }
+}
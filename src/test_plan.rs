@@ -1,42 +1,102 @@
-use crate::{CollectionProp, Deserializer, ElementArgument, HashMappable};
+use crate::element_prop::arguments_prop;
+use crate::{
+    test_element, CollectionProp, ElementArgument, FromJmx, FromProperty, HashMappable, JmxElement,
+    PropertyBag, Serializer, ToProperty,
+};
 use std::collections::HashMap;
 use xmltree::Element;
 
 /// TestPlan
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TestPlan {
     pub test_name: String,
     pub enabled: bool,
+    pub comments: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::sorted_map::serialize")
+    )]
     pub variables: HashMap<String, String>,
+    /// Save response data and sampler data in functional test mode
+    pub functional_mode: bool,
+    /// Run the tearDown thread groups after a shutdown of the main ones
+    pub tear_down_on_shutdown: bool,
+    /// Run the thread groups one after another rather than side by side
+    pub serialize_threadgroups: bool,
+    /// Jars or directories added to the classpath, separated by commas
+    pub user_define_classpath: String,
 }
 
-impl TestPlan {
-    pub fn parse(e: &Element) -> Self {
-        assert_eq!(e.name.as_str(), "TestPlan");
-        let test_name = e.attributes.get("testname").unwrap().clone();
-        let enabled = e
-            .attributes
-            .get("enabled")
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
-        let variables = CollectionProp::<ElementArgument>::parse(
-            e.children
-                .iter()
-                .find(|c| c.as_element().unwrap().name.as_str() == "elementProp")
-                .unwrap()
-                .as_element()
-                .unwrap()
-                .children[0]
-                .as_element()
-                .unwrap(),
+impl FromJmx for TestPlan {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("TestPlan")?;
+        let variables = e
+            .get_element("TestPlan.user_defined_variables")?
+            .get_collection("Arguments.arguments")?
+            .iter()
+            .map(ElementArgument::try_from)
+            .collect::<anyhow::Result<Vec<ElementArgument>>>()?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            comments: e.get_str("TestPlan.comments")?,
+            variables: CollectionProp { props: variables }.to_hash_map(),
+            functional_mode: bool::from_property(e, "TestPlan.functional_mode", Some("false"))?,
+            tear_down_on_shutdown: bool::from_property(
+                e,
+                "TestPlan.tearDown_on_shutdown",
+                Some("true"),
+            )?,
+            serialize_threadgroups: bool::from_property(
+                e,
+                "TestPlan.serialize_threadgroups",
+                Some("false"),
+            )?,
+            user_define_classpath: e.get_str("TestPlan.user_define_classpath")?,
+        })
+    }
+}
+
+impl Serializer for TestPlan {
+    /// Variables are written sorted by name.
+    fn to_element(&self) -> Element {
+        let mut variables = self
+            .variables
+            .iter()
+            .map(|(name, value)| ElementArgument {
+                name: name.clone(),
+                value: value.clone(),
+                metadata: String::from("="),
+            })
+            .collect::<Vec<ElementArgument>>();
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+        test_element(
+            "TestPlan",
+            "TestPlanGui",
+            &self.test_name,
+            self.enabled,
+            vec![
+                self.comments.to_property("TestPlan.comments").to_element(),
+                self.functional_mode
+                    .to_property("TestPlan.functional_mode")
+                    .to_element(),
+                self.tear_down_on_shutdown
+                    .to_property("TestPlan.tearDown_on_shutdown")
+                    .to_element(),
+                self.serialize_threadgroups
+                    .to_property("TestPlan.serialize_threadgroups")
+                    .to_element(),
+                arguments_prop(
+                    "TestPlan.user_defined_variables",
+                    "ArgumentsPanel",
+                    CollectionProp { props: variables }.to_element(),
+                ),
+                self.user_define_classpath
+                    .to_property("TestPlan.user_define_classpath")
+                    .to_element(),
+            ],
         )
-        .to_hash_map();
-        Self {
-            test_name,
-            enabled,
-            variables,
-        }
     }
 }
 
@@ -45,7 +105,7 @@ mod test {
 
     #[test]
     fn check_parse() {
-        use crate::TestPlan;
+        use crate::{FromJmx, Serializer, TestPlan};
         use std::collections::HashMap;
         use xmltree::Element;
 
@@ -56,16 +116,30 @@ mod test {
                 .as_bytes(),
         )
         .unwrap();
+        let plan = TestPlan::parse(&xml).unwrap();
         assert_eq!(
-            TestPlan::parse(&xml),
+            plan,
             TestPlan {
                 test_name: String::from("Test Plan"),
                 enabled: true,
+                comments: String::new(),
                 variables: HashMap::from([
                     (String::from("protocol"), String::from("https")),
                     (String::from("ip"), String::from("example.github.com"))
-                ])
+                ]),
+                functional_mode: false,
+                tear_down_on_shutdown: true,
+                serialize_threadgroups: false,
+                user_define_classpath: String::new(),
             }
-        )
+        );
+        let plan = TestPlan {
+            comments: String::from("Smoke test of the API"),
+            functional_mode: true,
+            tear_down_on_shutdown: false,
+            user_define_classpath: String::from("lib/extra.jar"),
+            ..plan
+        };
+        assert_eq!(TestPlan::parse(&plan.to_element()).unwrap(), plan);
     }
 }
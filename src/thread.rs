@@ -1,102 +1,616 @@
-use crate::{BoolProp, Deserializer, ElementLoopController, StringProp};
+use crate::config_element::{java_hash, string_collection};
+use crate::{
+    element_prop, test_element, CollectionProp, ElementLoopController, FromJmx, FromProperty,
+    JmxElement, Property, PropertyBag, Serializer, ToProperty, Value,
+};
+use anyhow::{anyhow, Context};
 use xmltree::Element;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ThreadGroup {
     pub test_name: String,
     pub enabled: bool,
     pub on_sample_error: String,
-    pub loops: i32,
-    pub num_threads: i32,
-    pub ramp_time: i32,
+    /// `-1` loops forever
+    pub loops: Value<i32>,
+    pub num_threads: Value<i32>,
+    pub ramp_time: Value<i32>,
     pub scheduler: bool,
-    pub duration: Option<i32>,
-    pub delay: Option<i32>,
+    pub duration: Option<Value<i32>>,
+    pub delay: Option<Value<i32>>,
 }
 
-impl ThreadGroup {
-    pub fn parse(e: &Element) -> Self {
-        assert_eq!(e.name.as_str(), "ThreadGroup");
-        let test_name = e.attributes.get("testname").unwrap().clone();
-        let enabled = e
-            .attributes
-            .get("enabled")
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
-        let children = e
-            .children
+impl FromJmx for ThreadGroup {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag("ThreadGroup")?;
+        let loop_controller =
+            ElementLoopController::try_from(e.get_element("ThreadGroup.main_controller")?)
+                .context("ThreadGroup.main_controller")?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            on_sample_error: e.get_str("ThreadGroup.on_sample_error")?,
+            loops: loop_controller.loops,
+            num_threads: Value::from_property(e, "ThreadGroup.num_threads", None)?,
+            ramp_time: Value::from_property(e, "ThreadGroup.ramp_time", None)?,
+            scheduler: e.get_bool("ThreadGroup.scheduler")?,
+            // Left unset rather than failing on anything unreadable, as these
+            // only matter with the scheduler on
+            duration: Option::from_property(e, "ThreadGroup.duration", None)
+                .ok()
+                .flatten(),
+            delay: Option::from_property(e, "ThreadGroup.delay", None)
+                .ok()
+                .flatten(),
+        })
+    }
+}
+
+impl Serializer for ThreadGroup {
+    fn to_element(&self) -> Element {
+        let mut props = vec![
+            self.on_sample_error
+                .to_property("ThreadGroup.on_sample_error")
+                .to_element(),
+            ElementLoopController {
+                loops: self.loops.clone(),
+            }
+            .to_element(),
+        ];
+        props.extend(
+            [
+                self.num_threads.to_property("ThreadGroup.num_threads"),
+                self.ramp_time.to_property("ThreadGroup.ramp_time"),
+                self.scheduler.to_property("ThreadGroup.scheduler"),
+                self.duration.to_property("ThreadGroup.duration"),
+                self.delay.to_property("ThreadGroup.delay"),
+            ]
             .iter()
-            .map(|x| x.as_element())
-            .filter(|x| x.is_some())
-            .map(|x| x.unwrap())
-            .collect::<Vec<&Element>>();
-        let on_sample_error = StringProp::parse(
-            children
-                .iter()
-                .find(|x| x.attributes.get("name").unwrap() == "ThreadGroup.on_sample_error")
-                .unwrap(),
+            .map(|p| p.to_element()),
+        );
+        test_element(
+            "ThreadGroup",
+            "ThreadGroupGui",
+            &self.test_name,
+            self.enabled,
+            props,
         )
-        .value;
-        let loop_controller = ElementLoopController::parse(
-            children
-                .iter()
-                .find(|x| x.attributes.get("name").unwrap() == "ThreadGroup.main_controller")
-                .unwrap(),
+    }
+}
+
+/// Ultimate Thread Group from JMeter-Plugins
+/// `<kg.apc.jmeter.threads.UltimateThreadGroup>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UltimateThreadGroup {
+    pub test_name: String,
+    pub enabled: bool,
+    pub on_sample_error: String,
+    /// `-1` loops forever
+    pub loops: Value<i32>,
+    /// The rows of the schedule, which run side by side
+    pub schedule: Vec<UltimateThreadGroupRow>,
+}
+
+/// One row of an [`UltimateThreadGroup`]'s schedule, times in seconds
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UltimateThreadGroupRow {
+    pub threads: Value<u64>,
+    pub initial_delay: Value<u64>,
+    pub startup_time: Value<u64>,
+    pub hold_load_for: Value<u64>,
+    pub shutdown_time: Value<u64>,
+}
+
+impl UltimateThreadGroupRow {
+    fn values(&self) -> [&Value<u64>; 5] {
+        [
+            &self.threads,
+            &self.initial_delay,
+            &self.startup_time,
+            &self.hold_load_for,
+            &self.shutdown_time,
+        ]
+    }
+}
+
+const ULTIMATE_THREAD_GROUP: &str = "kg.apc.jmeter.threads.UltimateThreadGroup";
+
+impl FromJmx for UltimateThreadGroup {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag(ULTIMATE_THREAD_GROUP)?;
+        let loop_controller =
+            ElementLoopController::try_from(e.get_element("ThreadGroup.main_controller")?)
+                .context("ThreadGroup.main_controller")?;
+        let schedule = e
+            .get_collection("ultimatethreadgroupdata")?
+            .iter()
+            .map(|row| {
+                let values = row
+                    .as_collection()
+                    .ok_or_else(|| anyhow!("expected a collectionProp, found {row:?}"))?
+                    .iter()
+                    .map(|p| {
+                        p.as_text()
+                            .ok_or_else(|| anyhow!("expected a stringProp, found {p:?}"))?
+                            .parse::<Value<u64>>()
+                            .map_err(|err| anyhow!("ultimatethreadgroupdata: {err}"))
+                    })
+                    .collect::<anyhow::Result<Vec<Value<u64>>>>()?;
+                let [threads, initial_delay, startup_time, hold_load_for, shutdown_time] =
+                    <[Value<u64>; 5]>::try_from(values).map_err(|values| {
+                        anyhow!(
+                            "expected 5 values in a schedule row, found {}",
+                            values.len()
+                        )
+                    })?;
+                Ok(UltimateThreadGroupRow {
+                    threads,
+                    initial_delay,
+                    startup_time,
+                    hold_load_for,
+                    shutdown_time,
+                })
+            })
+            .collect::<anyhow::Result<Vec<UltimateThreadGroupRow>>>()?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            on_sample_error: e.get_str("ThreadGroup.on_sample_error")?,
+            loops: loop_controller.loops,
+            schedule,
+        })
+    }
+}
+
+impl Serializer for UltimateThreadGroup {
+    fn to_element(&self) -> Element {
+        let schedule = Property::Collection(
+            String::from("ultimatethreadgroupdata"),
+            CollectionProp {
+                props: self
+                    .schedule
+                    .iter()
+                    .map(|row| {
+                        let values = row.values().map(Value::to_string);
+                        string_collection(&java_hash(&values.concat()).to_string(), &values)
+                    })
+                    .collect::<Vec<Property>>(),
+            },
         );
-        let num_threads = StringProp::parse(
-            children
-                .iter()
-                .find(|x| x.attributes.get("name").unwrap() == "ThreadGroup.num_threads")
-                .unwrap(),
+        test_element(
+            ULTIMATE_THREAD_GROUP,
+            "kg.apc.jmeter.threads.UltimateThreadGroupGui",
+            &self.test_name,
+            self.enabled,
+            vec![
+                schedule.to_element(),
+                ElementLoopController {
+                    loops: self.loops.clone(),
+                }
+                .to_element(),
+                self.on_sample_error
+                    .to_property("ThreadGroup.on_sample_error")
+                    .to_element(),
+            ],
         )
-        .value
-        .parse::<i32>()
-        .unwrap();
-        let ramp_time = StringProp::parse(
-            children
-                .iter()
-                .find(|x| x.attributes.get("name").unwrap() == "ThreadGroup.ramp_time")
-                .unwrap(),
+    }
+}
+
+/// Concurrency Thread Group from the BlazeMeter plugins, keeping
+/// `target_level` users busy
+/// `<com.blazemeter.jmeter.threads.concurrency.ConcurrencyThreadGroup>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConcurrencyThreadGroup {
+    pub test_name: String,
+    pub enabled: bool,
+    pub on_sample_error: String,
+    pub target_level: Value<u64>,
+    /// In `unit`s
+    pub ramp_up: Value<u64>,
+    /// Steps the ramp-up takes, 0 ramping up smoothly
+    pub steps: Value<u64>,
+    /// How long to hold `target_level`, in `unit`s
+    pub hold: Value<u64>,
+    /// Iterations per user, `None` for no limit
+    pub iterations: Option<Value<u64>>,
+    /// `S` for seconds, `M` for minutes
+    pub unit: String,
+    pub log_filename: String,
+}
+
+const CONCURRENCY_THREAD_GROUP: &str =
+    "com.blazemeter.jmeter.threads.concurrency.ConcurrencyThreadGroup";
+
+impl ConcurrencyThreadGroup {
+    /// Seconds per `unit`.
+    pub fn unit_secs(&self) -> u64 {
+        if self.unit == "M" {
+            60
+        } else {
+            1
+        }
+    }
+}
+
+impl FromJmx for ConcurrencyThreadGroup {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag(CONCURRENCY_THREAD_GROUP)?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            on_sample_error: e.get_str("ThreadGroup.on_sample_error")?,
+            target_level: Value::from_property(e, "TargetLevel", None)?,
+            ramp_up: Value::from_property(e, "RampUp", None)?,
+            steps: Value::from_property(e, "Steps", None)?,
+            hold: Value::from_property(e, "Hold", None)?,
+            iterations: Option::from_property(e, "Iterations", None)?,
+            unit: String::from_property(e, "Unit", Some("M"))?,
+            log_filename: e.get_str("LogFilename")?,
+        })
+    }
+}
+
+impl Serializer for ConcurrencyThreadGroup {
+    fn to_element(&self) -> Element {
+        let mut props = vec![
+            element_prop(
+                "ThreadGroup.main_controller",
+                "com.blazemeter.jmeter.control.VirtualUserController",
+                vec![],
+            ),
+            self.on_sample_error
+                .to_property("ThreadGroup.on_sample_error")
+                .to_element(),
+        ];
+        props.extend(
+            [
+                self.target_level.to_property("TargetLevel"),
+                self.ramp_up.to_property("RampUp"),
+                self.steps.to_property("Steps"),
+                self.hold.to_property("Hold"),
+                self.log_filename.to_property("LogFilename"),
+                self.iterations.to_property("Iterations"),
+                self.unit.to_property("Unit"),
+            ]
+            .iter()
+            .map(|p| p.to_element()),
+        );
+        test_element(
+            CONCURRENCY_THREAD_GROUP,
+            "com.blazemeter.jmeter.threads.concurrency.ConcurrencyThreadGroupGui",
+            &self.test_name,
+            self.enabled,
+            props,
+        )
+    }
+}
+
+/// Stepping Thread Group from JMeter-Plugins, starting users in batches
+/// and stopping them the same way
+/// `<kg.apc.jmeter.threads.SteppingThreadGroup>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SteppingThreadGroup {
+    pub test_name: String,
+    pub enabled: bool,
+    pub on_sample_error: String,
+    /// `-1` loops forever
+    pub loops: Value<i32>,
+    pub num_threads: Value<u64>,
+    /// Times are in seconds
+    pub initial_delay: Value<u64>,
+    /// Users started first, 0 for a batch of `start_users_count`
+    pub start_users_burst: Value<u64>,
+    /// Users in each further batch
+    pub start_users_count: Value<u64>,
+    /// Pause between batches
+    pub start_users_period: Value<u64>,
+    /// Time each batch takes to start
+    pub ramp_up: Value<u64>,
+    /// How long all users run before stopping
+    pub flight_time: Value<u64>,
+    pub stop_users_count: Value<u64>,
+    pub stop_users_period: Value<u64>,
+}
+
+const STEPPING_THREAD_GROUP: &str = "kg.apc.jmeter.threads.SteppingThreadGroup";
+
+impl FromJmx for SteppingThreadGroup {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag(STEPPING_THREAD_GROUP)?;
+        let loop_controller =
+            ElementLoopController::try_from(e.get_element("ThreadGroup.main_controller")?)
+                .context("ThreadGroup.main_controller")?;
+        let value = |name: &str| Value::from_property(e, name, Some("0"));
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            on_sample_error: e.get_str("ThreadGroup.on_sample_error")?,
+            loops: loop_controller.loops,
+            num_threads: Value::from_property(e, "ThreadGroup.num_threads", None)?,
+            initial_delay: value("Threads initial delay")?,
+            start_users_burst: value("Start users count burst")?,
+            start_users_count: value("Start users count")?,
+            start_users_period: value("Start users period")?,
+            ramp_up: value("rampUp")?,
+            flight_time: value("flighttime")?,
+            stop_users_count: value("Stop users count")?,
+            stop_users_period: value("Stop users period")?,
+        })
+    }
+}
+
+impl Serializer for SteppingThreadGroup {
+    fn to_element(&self) -> Element {
+        let mut props = vec![self
+            .on_sample_error
+            .to_property("ThreadGroup.on_sample_error")
+            .to_element()];
+        props.extend(
+            [
+                self.num_threads.to_property("ThreadGroup.num_threads"),
+                self.initial_delay.to_property("Threads initial delay"),
+                self.start_users_count.to_property("Start users count"),
+                self.start_users_burst
+                    .to_property("Start users count burst"),
+                self.start_users_period.to_property("Start users period"),
+                self.stop_users_count.to_property("Stop users count"),
+                self.stop_users_period.to_property("Stop users period"),
+                self.flight_time.to_property("flighttime"),
+                self.ramp_up.to_property("rampUp"),
+            ]
+            .iter()
+            .map(|p| p.to_element()),
+        );
+        props.push(
+            ElementLoopController {
+                loops: self.loops.clone(),
+            }
+            .to_element(),
+        );
+        test_element(
+            STEPPING_THREAD_GROUP,
+            "kg.apc.jmeter.threads.SteppingThreadGroupGui",
+            &self.test_name,
+            self.enabled,
+            props,
+        )
+    }
+}
+
+/// Arrivals Thread Group from the BlazeMeter plugins, starting
+/// `target_level` iterations per `unit` however many users that takes
+/// `<com.blazemeter.jmeter.threads.arrivals.ArrivalsThreadGroup>`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArrivalsThreadGroup {
+    pub test_name: String,
+    pub enabled: bool,
+    pub on_sample_error: String,
+    /// Arrivals per `unit`
+    pub target_level: Value<u64>,
+    /// In `unit`s
+    pub ramp_up: Value<u64>,
+    /// Steps the ramp-up takes, 0 ramping up smoothly
+    pub steps: Value<u64>,
+    /// How long to hold `target_level`, in `unit`s
+    pub hold: Value<u64>,
+    /// Iterations per user, `None` for no limit
+    pub iterations: Option<Value<u64>>,
+    /// Most users running at once, `None` for no limit
+    pub concurrency_limit: Option<Value<u64>>,
+    /// `S` for seconds, `M` for minutes
+    pub unit: String,
+    pub log_filename: String,
+}
+
+const ARRIVALS_THREAD_GROUP: &str = "com.blazemeter.jmeter.threads.arrivals.ArrivalsThreadGroup";
+
+impl ArrivalsThreadGroup {
+    /// Seconds per `unit`.
+    pub fn unit_secs(&self) -> u64 {
+        if self.unit == "M" {
+            60
+        } else {
+            1
+        }
+    }
+}
+
+impl FromJmx for ArrivalsThreadGroup {
+    fn from_jmx(e: &JmxElement) -> anyhow::Result<Self> {
+        e.expect_tag(ARRIVALS_THREAD_GROUP)?;
+        Ok(Self {
+            test_name: e.test_name.clone(),
+            enabled: e.enabled,
+            on_sample_error: e.get_str("ThreadGroup.on_sample_error")?,
+            target_level: Value::from_property(e, "TargetLevel", None)?,
+            ramp_up: Value::from_property(e, "RampUp", None)?,
+            steps: Value::from_property(e, "Steps", None)?,
+            hold: Value::from_property(e, "Hold", None)?,
+            iterations: Option::from_property(e, "Iterations", None)?,
+            concurrency_limit: Option::from_property(e, "ConcurrencyLimit", None)?,
+            unit: String::from_property(e, "Unit", Some("M"))?,
+            log_filename: e.get_str("LogFilename")?,
+        })
+    }
+}
+
+impl Serializer for ArrivalsThreadGroup {
+    fn to_element(&self) -> Element {
+        let mut props = vec![
+            element_prop(
+                "ThreadGroup.main_controller",
+                "com.blazemeter.jmeter.control.VirtualUserController",
+                vec![],
+            ),
+            self.on_sample_error
+                .to_property("ThreadGroup.on_sample_error")
+                .to_element(),
+        ];
+        props.extend(
+            [
+                self.target_level.to_property("TargetLevel"),
+                self.ramp_up.to_property("RampUp"),
+                self.steps.to_property("Steps"),
+                self.hold.to_property("Hold"),
+                self.log_filename.to_property("LogFilename"),
+                self.iterations.to_property("Iterations"),
+                self.concurrency_limit.to_property("ConcurrencyLimit"),
+                self.unit.to_property("Unit"),
+            ]
+            .iter()
+            .map(|p| p.to_element()),
+        );
+        test_element(
+            ARRIVALS_THREAD_GROUP,
+            "com.blazemeter.jmeter.threads.arrivals.ArrivalsThreadGroupGui",
+            &self.test_name,
+            self.enabled,
+            props,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::thread::{
+        ArrivalsThreadGroup, ConcurrencyThreadGroup, SteppingThreadGroup, ThreadGroup,
+        UltimateThreadGroup,
+    };
+    use crate::{FromJmx, Serializer, Value};
+    use xmltree::Element;
+
+    fn parse(path: &str) -> ThreadGroup {
+        let xml = Element::parse(std::fs::read_to_string(path).unwrap().trim().as_bytes()).unwrap();
+        ThreadGroup::parse(&xml).unwrap()
+    }
+
+    #[test]
+    fn check_parse_int_props() {
+        let group = parse("./unittests_data/thread/int_props.xml");
+        assert_eq!(ThreadGroup::parse(&group.to_element()).unwrap(), group);
+        assert_eq!(group.loops, Value::Literal(-1));
+        assert_eq!(group.num_threads, Value::Literal(25));
+        assert_eq!(group.ramp_time, Value::Literal(5));
+        assert_eq!(group.duration, Some(Value::Literal(300)));
+        assert_eq!(group.delay, None);
+
+        // Loops that aren't a number fail the thread group
+        let jmx = std::fs::read_to_string("./unittests_data/thread/int_props.xml")
+            .unwrap()
+            .replace(
+                r#"<intProp name="LoopController.loops">-1</intProp>"#,
+                r#"<objectProp name="LoopController.loops"/>"#,
+            );
+        let error =
+            ThreadGroup::parse(&Element::parse(jmx.trim().as_bytes()).unwrap()).unwrap_err();
+        assert!(format!("{error:#}")
+            .starts_with("<ThreadGroup>: ThreadGroup.main_controller: LoopController.loops"));
+    }
+
+    #[test]
+    fn check_parse_expressions() {
+        let group = parse("./unittests_data/thread/expressions.xml");
+        assert_eq!(ThreadGroup::parse(&group.to_element()).unwrap(), group);
+        assert_eq!(group.loops, Value::Expression(String::from("${loops}")));
+        assert_eq!(group.loops.property(), None);
+        assert_eq!(group.num_threads.property(), Some(("threads", "10")));
+        assert_eq!(group.ramp_time.property(), Some(("ramp", "2")));
+        assert_eq!(
+            group.duration.as_ref().and_then(Value::property),
+            Some(("duration", "1"))
+        );
+    }
+
+    #[test]
+    fn check_parse_ultimate() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/thread/ultimate.xml")
+                .unwrap()
+                .as_bytes(),
         )
-        .value
-        .parse::<i32>()
         .unwrap();
-        let scheduler = BoolProp::parse(
-            children
-                .iter()
-                .find(|x| x.attributes.get("name").unwrap() == "ThreadGroup.scheduler")
-                .unwrap(),
+        let group = UltimateThreadGroup::parse(&xml).unwrap();
+        assert_eq!(
+            UltimateThreadGroup::parse(&group.to_element()).unwrap(),
+            group
+        );
+        assert_eq!(group.loops, Value::Literal(-1));
+        assert_eq!(group.schedule.len(), 3);
+        assert_eq!(
+            group.schedule[1].values().map(Value::literal),
+            [Some(&20), Some(&30), Some(&10), Some(&20), Some(&10)]
+        );
+        assert_eq!(group.schedule[2].threads.property(), Some(("spike", "5")));
+        assert_eq!(group.schedule[2].initial_delay, Value::Literal(50));
+    }
+
+    #[test]
+    fn check_parse_concurrency() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/thread/concurrency.xml")
+                .unwrap()
+                .as_bytes(),
         )
-        .value;
-        let duration = StringProp::parse(
-            children
-                .iter()
-                .find(|x| x.attributes.get("name").unwrap() == "ThreadGroup.duration")
-                .unwrap(),
+        .unwrap();
+        let group = ConcurrencyThreadGroup::parse(&xml).unwrap();
+        assert_eq!(
+            ConcurrencyThreadGroup::parse(&group.to_element()).unwrap(),
+            group
+        );
+        assert_eq!(group.target_level.property(), Some(("users", "40")));
+        assert_eq!(group.ramp_up, Value::Literal(2));
+        assert_eq!(group.steps, Value::Literal(4));
+        assert_eq!(group.hold, Value::Literal(10));
+        assert_eq!(group.iterations, None);
+        assert_eq!(group.unit_secs(), 60);
+    }
+
+    #[test]
+    fn check_parse_stepping() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/thread/stepping.xml")
+                .unwrap()
+                .as_bytes(),
         )
-        .value
-        .parse::<i32>()
-        .ok();
-        let delay = StringProp::parse(
-            children
-                .iter()
-                .find(|x| x.attributes.get("name").unwrap() == "ThreadGroup.delay")
-                .unwrap(),
+        .unwrap();
+        let group = SteppingThreadGroup::parse(&xml).unwrap();
+        assert_eq!(
+            SteppingThreadGroup::parse(&group.to_element()).unwrap(),
+            group
+        );
+        assert_eq!(group.num_threads, Value::Literal(100));
+        assert_eq!(group.start_users_count, Value::Literal(10));
+        assert_eq!(group.start_users_burst, Value::Literal(0));
+        assert_eq!(group.start_users_period, Value::Literal(30));
+        assert_eq!(group.ramp_up, Value::Literal(5));
+        assert_eq!(group.flight_time, Value::Literal(60));
+        assert_eq!(group.stop_users_count, Value::Literal(5));
+        assert_eq!(group.stop_users_period, Value::Literal(1));
+    }
+
+    #[test]
+    fn check_parse_arrivals() {
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/thread/arrivals.xml")
+                .unwrap()
+                .as_bytes(),
         )
-        .value
-        .parse::<i32>()
-        .ok();
-        Self {
-            test_name,
-            enabled,
-            on_sample_error,
-            loops: loop_controller.loops,
-            num_threads,
-            ramp_time,
-            scheduler,
-            duration,
-            delay,
-        }
+        .unwrap();
+        let group = ArrivalsThreadGroup::parse(&xml).unwrap();
+        assert_eq!(
+            ArrivalsThreadGroup::parse(&group.to_element()).unwrap(),
+            group
+        );
+        assert_eq!(group.target_level, Value::Literal(120));
+        assert_eq!(group.hold.property(), Some(("hold", "5")));
+        assert_eq!(group.concurrency_limit, Some(Value::Literal(20)));
+        assert_eq!(group.iterations, None);
+        assert_eq!(group.unit_secs(), 60);
     }
 }
@@ -0,0 +1,85 @@
+use crate::JmxElement;
+
+/// Constant Timer
+/// `<ConstantTimer>`
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "ConstantTimer", gui = "ConstantTimerGui")]
+pub struct ConstantTimer {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+    /// Milliseconds, may reference variables
+    #[jmx(prop = "ConstantTimer.delay", default = "300")]
+    pub delay: String,
+}
+
+/// Uniform Random Timer
+/// `<UniformRandomTimer>`
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "UniformRandomTimer", gui = "UniformRandomTimerGui")]
+pub struct UniformRandomTimer {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+    /// Constant delay offset in milliseconds
+    #[jmx(prop = "ConstantTimer.delay", default = "0")]
+    pub delay: String,
+    /// Maximum random delay in milliseconds added to `delay`
+    #[jmx(prop = "RandomTimer.range", default = "100.0")]
+    pub range: String,
+}
+
+/// Gaussian Random Timer
+/// `<GaussianRandomTimer>`
+#[derive(Debug, PartialEq, Clone, JmxElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[jmx(tag = "GaussianRandomTimer", gui = "GaussianRandomTimerGui")]
+pub struct GaussianRandomTimer {
+    #[jmx(test_name)]
+    pub test_name: String,
+    #[jmx(enabled)]
+    pub enabled: bool,
+    /// Constant delay offset in milliseconds
+    #[jmx(prop = "ConstantTimer.delay", default = "300")]
+    pub delay: String,
+    /// Standard deviation in milliseconds
+    #[jmx(prop = "RandomTimer.range", default = "100.0")]
+    pub range: String,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{FromJmx, Serializer};
+    use xmltree::Element;
+
+    #[test]
+    fn check_parse_uniform_random_timer() {
+        use crate::UniformRandomTimer;
+
+        let xml = Element::parse(
+            std::fs::read_to_string("./unittests_data/timer/uniform_random_timer.xml")
+                .unwrap()
+                .trim()
+                .as_bytes(),
+        )
+        .unwrap();
+        let timer = UniformRandomTimer::parse(&xml).unwrap();
+        assert_eq!(
+            UniformRandomTimer::parse(&timer.to_element()).unwrap(),
+            timer
+        );
+        assert_eq!(
+            timer,
+            UniformRandomTimer {
+                test_name: String::from("Think time"),
+                enabled: true,
+                delay: String::from("1000"),
+                range: String::from("500.0"),
+            }
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! Serde for the raw `Element`s of unknown test elements, kept as their XML.
+
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+use xmltree::{Element, EmitterConfig};
+
+pub fn serialize<S: Serializer>(e: &Element, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut xml = vec![];
+    e.write_with_config(
+        &mut xml,
+        EmitterConfig::new().write_document_declaration(false),
+    )
+    .map_err(serde::ser::Error::custom)?;
+    serializer.serialize_str(&String::from_utf8_lossy(&xml))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Element, D::Error> {
+    let xml = String::deserialize(deserializer)?;
+    Element::parse(xml.as_bytes()).map_err(D::Error::custom)
+}
+
+/// The same for a `Vec<Element>`, as a sequence of XML strings.
+pub mod many {
+    use serde::ser::SerializeSeq;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use xmltree::Element;
+
+    pub fn serialize<S: Serializer>(
+        elements: &[Element],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        /// Lets `super::serialize` write one element of the sequence.
+        struct Xml<'a>(&'a Element);
+
+        impl serde::Serialize for Xml<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                super::serialize(self.0, serializer)
+            }
+        }
+
+        let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+        for e in elements {
+            seq.serialize_element(&Xml(e))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Element>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|xml| Element::parse(xml.as_bytes()).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}